@@ -0,0 +1,606 @@
+use std::sync::LazyLock;
+
+use alloy_dyn_abi::{
+    DynSolType, DynSolValue, ErrorExt, EventExt, FunctionExt, JsonAbiExt, Specifier,
+};
+use alloy_json_abi::{Error, Event, EventParam, Function, Param};
+use alloy_primitives::{B256, hex, keccak256};
+use rmcp::ErrorData;
+use serde_json::{Value, json};
+
+use crate::common::{common::resolve_address, error::invalid_input, provider::RetryProvider};
+
+/// Parse a function signature such as `balanceOf(address)(uint256)` or
+/// `function balanceOf(address owner) view returns (uint256)`.
+pub fn parse_function(sig: &str) -> Result<Function, ErrorData> {
+    Function::parse(sig.trim()).map_err(|e| {
+        invalid_input(
+            format!("Invalid function signature: {sig}"),
+            Some(json!({
+                "input": sig,
+                "expected": "a signature such as balanceOf(address)(uint256)",
+                "reason": e.to_string(),
+            })),
+        )
+    })
+}
+
+/// Coerce string arguments into values of the types `params` declares.
+///
+/// Address arguments may be ENS names, which are resolved through `provider`.
+pub async fn coerce_args(
+    provider: &RetryProvider,
+    params: &[Param],
+    args: &[String],
+) -> Result<Vec<DynSolValue>, ErrorData> {
+    check_arg_count(params, args)?;
+
+    let mut values = Vec::with_capacity(args.len());
+    for (index, (param, arg)) in params.iter().zip(args).enumerate() {
+        let ty = resolve_type(param, &param.selector_type())?;
+        values.push(coerce_value(provider, &ty, &param.selector_type(), index, arg).await?);
+    }
+    Ok(values)
+}
+
+/// Coerce string arguments like [`coerce_args`], without a provider: addresses must be hex.
+pub fn coerce_args_offline(
+    params: &[Param],
+    args: &[String],
+) -> Result<Vec<DynSolValue>, ErrorData> {
+    check_arg_count(params, args)?;
+
+    params
+        .iter()
+        .zip(args)
+        .enumerate()
+        .map(|(index, (param, arg))| {
+            let ty = resolve_type(param, &param.selector_type())?;
+            coerce_str(&ty, &param.selector_type(), index, arg)
+        })
+        .collect()
+}
+
+fn check_arg_count(params: &[Param], args: &[String]) -> Result<(), ErrorData> {
+    if params.len() == args.len() {
+        return Ok(());
+    }
+    Err(invalid_input(
+        format!("Expected {} arguments, got {}", params.len(), args.len()),
+        Some(json!({ "expected": params.len(), "got": args.len(), "reason": "argument_count" })),
+    ))
+}
+
+/// Compute the topic filtering an indexed event parameter by `arg`. Value types are padded to a
+/// word; strings and bytes are hashed, as Solidity does when indexing them.
+pub async fn indexed_topic(
+    provider: &RetryProvider,
+    param: &EventParam,
+    index: usize,
+    arg: &str,
+) -> Result<B256, ErrorData> {
+    let ty = resolve_type(param, &param.selector_type())?;
+    let value = coerce_value(provider, &ty, &param.selector_type(), index, arg).await?;
+    match &value {
+        DynSolValue::String(s) => Ok(keccak256(s)),
+        DynSolValue::Bytes(bytes) => Ok(keccak256(bytes)),
+        value => value.as_word().ok_or_else(|| {
+            invalid_input(
+                format!(
+                    "Cannot filter on indexed {} parameters",
+                    param.selector_type()
+                ),
+                Some(json!({ "index": index, "type": param.selector_type() })),
+            )
+        }),
+    }
+}
+
+async fn coerce_value(
+    provider: &RetryProvider,
+    ty: &DynSolType,
+    type_name: &str,
+    index: usize,
+    arg: &str,
+) -> Result<DynSolValue, ErrorData> {
+    if *ty == DynSolType::Address {
+        return Ok(DynSolValue::Address(resolve_address(provider, arg).await?));
+    }
+    coerce_str(ty, type_name, index, arg)
+}
+
+fn coerce_str(
+    ty: &DynSolType,
+    type_name: &str,
+    index: usize,
+    arg: &str,
+) -> Result<DynSolValue, ErrorData> {
+    ty.coerce_str(arg).map_err(|e| {
+        invalid_input(
+            format!("Invalid argument {index} for type {type_name}: {arg}"),
+            Some(json!({
+                "index": index,
+                "input": arg,
+                "type": type_name,
+                "reason": e.to_string(),
+            })),
+        )
+    })
+}
+
+fn resolve_type(
+    param: &impl Specifier<DynSolType>,
+    type_name: &str,
+) -> Result<DynSolType, ErrorData> {
+    param.resolve().map_err(|e| {
+        invalid_input(
+            format!("Unsupported type: {type_name}"),
+            Some(json!({ "type": type_name, "reason": e.to_string() })),
+        )
+    })
+}
+
+/// Encode calldata for `func`: its selector followed by the ABI-encoded arguments.
+pub fn encode_call(func: &Function, values: &[DynSolValue]) -> Result<Vec<u8>, ErrorData> {
+    func.abi_encode_input(values).map_err(|e| {
+        invalid_input(
+            format!("Failed to encode arguments for {}", func.signature()),
+            Some(json!({ "reason": e.to_string() })),
+        )
+    })
+}
+
+/// Decode return data against the outputs `func` declares.
+pub fn decode_output(func: &Function, data: &[u8]) -> Result<Vec<DecodedParam>, ErrorData> {
+    let values = func.abi_decode_output(data).map_err(|e| {
+        invalid_input(
+            format!("Failed to decode output of {}", func.signature()),
+            Some(json!({ "data": hex::encode_prefixed(data), "reason": e.to_string() })),
+        )
+    })?;
+    Ok(decoded_params(&func.outputs, &values))
+}
+
+/// Describe decoded values together with the parameters they were decoded for.
+pub fn decoded_params(params: &[Param], values: &[DynSolValue]) -> Vec<DecodedParam> {
+    params
+        .iter()
+        .zip(values)
+        .map(|(param, value)| DecodedParam::new(param, value))
+        .collect()
+}
+
+/// Parse 0x-prefixed (or bare) hex data.
+pub fn parse_hex(input: &str) -> Result<Vec<u8>, ErrorData> {
+    hex::decode(input.trim()).map_err(|e| {
+        invalid_input(
+            format!("Invalid hex data: {input}"),
+            Some(json!({ "input": input, "reason": e.to_string() })),
+        )
+    })
+}
+
+/// A decoded value together with the parameter it was decoded for.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, schemars::JsonSchema)]
+pub struct DecodedParam {
+    /// The parameter name, when the signature gives one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+
+    /// The canonical type, such as uint256 or (address,bytes4[])[].
+    #[serde(rename = "type")]
+    pub ty: String,
+
+    /// Whether an event parameter is indexed, for decoded logs.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub indexed: Option<bool>,
+
+    /// The value: integers as decimal strings, checksummed addresses, 0x hex bytes, and arrays
+    /// for arrays and tuples.
+    pub value: Value,
+}
+
+impl DecodedParam {
+    pub fn new(param: &Param, value: &DynSolValue) -> Self {
+        Self {
+            name: (!param.name.is_empty()).then(|| param.name.clone()),
+            ty: param.selector_type().into_owned(),
+            indexed: None,
+            value: value_json(value),
+        }
+    }
+}
+
+/// A log decoded as a known event.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, schemars::JsonSchema)]
+pub struct DecodedLog {
+    /// The event name.
+    pub name: String,
+
+    /// The event signature.
+    pub signature: String,
+
+    /// The event parameters, indexed or not, in declaration order.
+    pub params: Vec<DecodedParam>,
+}
+
+/// Convert a decoded value to JSON. Integers are decimal strings so no precision is lost,
+/// addresses are checksummed and byte strings are 0x-prefixed hex.
+pub fn value_json(value: &DynSolValue) -> Value {
+    match value {
+        DynSolValue::Bool(b) => Value::Bool(*b),
+        DynSolValue::Int(i, _) => Value::String(i.to_string()),
+        DynSolValue::Uint(u, _) => Value::String(u.to_string()),
+        DynSolValue::FixedBytes(word, size) => Value::String(hex::encode_prefixed(&word[..*size])),
+        DynSolValue::Address(address) => Value::String(address.to_checksum(None)),
+        DynSolValue::Function(function) => Value::String(function.to_string()),
+        DynSolValue::Bytes(bytes) => Value::String(hex::encode_prefixed(bytes)),
+        DynSolValue::String(s) => Value::String(s.clone()),
+        DynSolValue::Array(values)
+        | DynSolValue::FixedArray(values)
+        | DynSolValue::Tuple(values) => Value::Array(values.iter().map(value_json).collect()),
+        DynSolValue::CustomStruct {
+            prop_names, tuple, ..
+        } => Value::Object(
+            prop_names
+                .iter()
+                .cloned()
+                .zip(tuple.iter().map(value_json))
+                .collect(),
+        ),
+    }
+}
+
+/// Revert data decoded as an error.
+#[derive(Debug, serde::Serialize, schemars::JsonSchema)]
+pub struct DecodedRevert {
+    /// The 4-byte error selector.
+    pub selector: String,
+
+    /// The error signature.
+    pub signature: String,
+
+    /// The error arguments, with their names and types.
+    pub decoded: Vec<DecodedParam>,
+
+    /// What a Panic(uint256) code means.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+/// Decode revert data. With `error` the data must match that custom error; otherwise the builtin
+/// `Error(string)` and `Panic(uint256)` reverts are recognised.
+pub fn decode_revert(error: Option<&Error>, data: &[u8]) -> Result<DecodedRevert, ErrorData> {
+    let selector = data.get(..4).map(hex::encode_prefixed).ok_or_else(|| {
+        invalid_input(
+            "Revert data is shorter than a selector",
+            Some(json!({ "data": hex::encode_prefixed(data), "reason": "invalid_length" })),
+        )
+    })?;
+
+    let builtin;
+    let error = match error {
+        Some(error) => error,
+        None => {
+            builtin = BUILTIN_ERRORS
+                .iter()
+                .map(|sig| Error::parse(sig).expect("valid builtin error signature"))
+                .find(|error| hex::encode_prefixed(error.selector()) == selector)
+                .ok_or_else(|| {
+                    invalid_input(
+                        format!("Unknown error selector {selector}; pass the error signature"),
+                        Some(json!({ "selector": selector, "reason": "unknown_selector" })),
+                    )
+                })?;
+            &builtin
+        }
+    };
+
+    let decoded = error.decode_error(data).map_err(|e| {
+        invalid_input(
+            format!("Failed to decode revert data as {}", error.signature()),
+            Some(json!({ "data": hex::encode_prefixed(data), "reason": e.to_string() })),
+        )
+    })?;
+    let params = decoded_params(&error.inputs, &decoded.body);
+
+    let reason = decoded
+        .body
+        .first()
+        .and_then(DynSolValue::as_uint)
+        .filter(|_| error.name == "Panic")
+        .map(|code| panic_reason(u64::try_from(code.0).unwrap_or(u64::MAX)).to_string());
+    Ok(DecodedRevert {
+        selector,
+        signature: error.signature(),
+        decoded: params,
+        reason,
+    })
+}
+
+/// Reverts every Solidity contract can produce.
+const BUILTIN_ERRORS: &[&str] = &["Error(string message)", "Panic(uint256 code)"];
+
+/// Describe a Solidity panic code.
+fn panic_reason(code: u64) -> &'static str {
+    match code {
+        0x00 => "generic compiler inserted panic",
+        0x01 => "assertion failed",
+        0x11 => "arithmetic overflow or underflow",
+        0x12 => "division or modulo by zero",
+        0x21 => "invalid enum value",
+        0x22 => "invalid storage byte array encoding",
+        0x31 => "pop on an empty array",
+        0x32 => "array index out of bounds",
+        0x41 => "out of memory",
+        0x51 => "call to a zero-initialized function",
+        _ => "unknown panic code",
+    }
+}
+
+/// Events decoded without the caller naming them. Signatures sharing a topic0 differ in how many
+/// parameters are indexed, so the topic count picks the right one.
+const KNOWN_EVENTS: &[&str] = &[
+    "event Transfer(address indexed from, address indexed to, uint256 value)",
+    "event Transfer(address indexed from, address indexed to, uint256 indexed tokenId)",
+    "event Approval(address indexed owner, address indexed spender, uint256 value)",
+    "event Approval(address indexed owner, address indexed approved, uint256 indexed tokenId)",
+    "event ApprovalForAll(address indexed owner, address indexed operator, bool approved)",
+    "event TransferSingle(address indexed operator, address indexed from, address indexed to, uint256 id, uint256 value)",
+    "event TransferBatch(address indexed operator, address indexed from, address indexed to, uint256[] ids, uint256[] values)",
+    "event Deposit(address indexed dst, uint256 wad)",
+    "event Withdrawal(address indexed src, uint256 wad)",
+    "event Swap(address indexed sender, uint256 amount0In, uint256 amount1In, uint256 amount0Out, uint256 amount1Out, address indexed to)",
+    "event Swap(address indexed sender, address indexed recipient, int256 amount0, int256 amount1, uint160 sqrtPriceX96, uint128 liquidity, int24 tick)",
+    "event Sync(uint112 reserve0, uint112 reserve1)",
+    "event OwnershipTransferred(address indexed previousOwner, address indexed newOwner)",
+    "event Upgraded(address indexed implementation)",
+    "event AdminChanged(address previousAdmin, address newAdmin)",
+    "event BeaconUpgraded(address indexed beacon)",
+    "event Initialized(uint64 version)",
+    "event Initialized(uint8 version)",
+];
+
+static KNOWN: LazyLock<Vec<Event>> = LazyLock::new(|| {
+    KNOWN_EVENTS
+        .iter()
+        .map(|sig| Event::parse(sig).expect("valid builtin event signature"))
+        .collect()
+});
+
+/// Parse a custom error signature such as `InsufficientBalance(uint256 available, uint256 required)`.
+pub fn parse_error(sig: &str) -> Result<Error, ErrorData> {
+    Error::parse(sig.trim()).map_err(|e| {
+        invalid_input(
+            format!("Invalid error signature: {sig}"),
+            Some(json!({
+                "input": sig,
+                "expected": "a signature such as InsufficientBalance(uint256,uint256)",
+                "reason": e.to_string(),
+            })),
+        )
+    })
+}
+
+/// Parse an event signature such as `Transfer(address indexed,address indexed,uint256)`.
+pub fn parse_event(sig: &str) -> Result<Event, ErrorData> {
+    Event::parse(sig.trim()).map_err(|e| {
+        invalid_input(
+            format!("Invalid event signature: {sig}"),
+            Some(json!({
+                "input": sig,
+                "expected": "a signature such as Transfer(address indexed from, address indexed to, uint256 value)",
+                "reason": e.to_string(),
+            })),
+        )
+    })
+}
+
+/// Parse caller supplied event signatures, to be tried before the builtin ones.
+pub fn parse_events(sigs: &[String]) -> Result<Vec<Event>, ErrorData> {
+    sigs.iter().map(|sig| parse_event(sig)).collect()
+}
+
+/// Decode a log against `events`, then the builtin events. Returns `None` when no known event
+/// matches its topics and data.
+pub fn decode_log(events: &[Event], topics: &[B256], data: &[u8]) -> Option<DecodedLog> {
+    let topic0 = topics.first()?;
+    events
+        .iter()
+        .chain(KNOWN.iter())
+        .filter(|event| !event.anonymous && event.selector() == *topic0)
+        .find_map(|event| {
+            let decoded = event.decode_log_parts(topics.iter().copied(), data).ok()?;
+            let mut indexed = decoded.indexed.iter();
+            let mut body = decoded.body.iter();
+            let params = event
+                .inputs
+                .iter()
+                .map(|param| {
+                    let value = if param.indexed {
+                        indexed.next()
+                    } else {
+                        body.next()
+                    }?;
+                    Some(DecodedParam {
+                        name: (!param.name.is_empty()).then(|| param.name.clone()),
+                        ty: param.selector_type().into_owned(),
+                        indexed: Some(param.indexed),
+                        value: value_json(value),
+                    })
+                })
+                .collect::<Option<Vec<_>>>()?;
+            Some(DecodedLog {
+                name: event.name.clone(),
+                signature: event.signature(),
+                params,
+            })
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::{Address, U256};
+
+    use super::*;
+
+    #[test]
+    fn test_parse_function_cast_style() {
+        let func = parse_function("balanceOf(address)(uint256)").unwrap();
+        assert_eq!(func.name, "balanceOf");
+        assert_eq!(func.inputs.len(), 1);
+        assert_eq!(func.outputs.len(), 1);
+        assert_eq!(hex::encode(func.selector()), "70a08231");
+    }
+
+    #[test]
+    fn test_parse_function_human_readable() {
+        let func =
+            parse_function("function balanceOf(address owner) view returns (uint256)").unwrap();
+        assert_eq!(func.inputs[0].name, "owner");
+        assert_eq!(func.outputs[0].ty, "uint256");
+    }
+
+    #[test]
+    fn test_parse_function_invalid() {
+        let err = parse_function("balanceOf(address").unwrap_err();
+        assert_eq!(err.code, rmcp::model::ErrorCode::INVALID_PARAMS);
+    }
+
+    #[test]
+    fn test_encode_and_decode_round_trip() {
+        let func = parse_function("transfer(address,uint256)(bool)").unwrap();
+        let to: Address = "0xd8dA6BF26964aF9D7eEd9e03E53415D37aA96045"
+            .parse()
+            .unwrap();
+        let calldata = encode_call(
+            &func,
+            &[
+                DynSolValue::Address(to),
+                DynSolValue::Uint(U256::from(1000), 256),
+            ],
+        )
+        .unwrap();
+        assert_eq!(calldata.len(), 4 + 64);
+        assert_eq!(hex::encode(&calldata[..4]), "a9059cbb");
+
+        let mut output = [0u8; 32];
+        output[31] = 1;
+        let decoded = decode_output(&func, &output).unwrap();
+        assert_eq!(
+            serde_json::to_value(&decoded).unwrap(),
+            json!([{ "type": "bool", "value": true }])
+        );
+    }
+
+    #[test]
+    fn test_decode_output_error() {
+        let func = parse_function("totalSupply()(uint256)").unwrap();
+        let err = decode_output(&func, &[1, 2, 3]).unwrap_err();
+        assert_eq!(err.data.unwrap()["data"], "0x010203");
+    }
+
+    #[test]
+    fn test_value_json() {
+        let value = DynSolValue::Tuple(vec![
+            DynSolValue::Uint(U256::MAX, 256),
+            DynSolValue::Bytes(vec![0xde, 0xad]),
+            DynSolValue::String("hi".to_string()),
+        ]);
+        assert_eq!(
+            value_json(&value),
+            json!([U256::MAX.to_string(), "0xdead", "hi"])
+        );
+    }
+
+    fn word(address: &str) -> B256 {
+        address.parse::<Address>().unwrap().into_word()
+    }
+
+    #[test]
+    fn test_known_events_parse() {
+        assert_eq!(KNOWN.len(), KNOWN_EVENTS.len());
+    }
+
+    #[test]
+    fn test_decode_erc20_transfer() {
+        let transfer = Event::parse("Transfer(address,address,uint256)").unwrap();
+        let topics = [
+            transfer.selector(),
+            word("0xd8dA6BF26964aF9D7eEd9e03E53415D37aA96045"),
+            word("0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48"),
+        ];
+        let data = U256::from(1000).to_be_bytes::<32>();
+
+        let decoded = decode_log(&[], &topics, &data).unwrap();
+        assert_eq!(decoded.name, "Transfer");
+        assert_eq!(decoded.params[0].name.as_deref(), Some("from"));
+        assert_eq!(
+            decoded.params[0].value,
+            "0xd8dA6BF26964aF9D7eEd9e03E53415D37aA96045"
+        );
+        assert_eq!(decoded.params[2].value, "1000");
+        assert_eq!(decoded.params[2].indexed, Some(false));
+    }
+
+    #[test]
+    fn test_decode_erc721_transfer_by_topic_count() {
+        let transfer = Event::parse("Transfer(address,address,uint256)").unwrap();
+        let topics = [
+            transfer.selector(),
+            word("0xd8dA6BF26964aF9D7eEd9e03E53415D37aA96045"),
+            word("0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48"),
+            B256::with_last_byte(7),
+        ];
+
+        let decoded = decode_log(&[], &topics, &[]).unwrap();
+        assert_eq!(decoded.params[2].name.as_deref(), Some("tokenId"));
+        assert_eq!(decoded.params[2].value, "7");
+    }
+
+    #[test]
+    fn test_decode_log_with_caller_events() {
+        let events = parse_events(&["Ping(uint256 indexed id)".to_string()]).unwrap();
+        let topics = [events[0].selector(), B256::with_last_byte(1)];
+
+        let decoded = decode_log(&events, &topics, &[]).unwrap();
+        assert_eq!(decoded.signature, "Ping(uint256)");
+        assert!(decode_log(&[], &topics, &[]).is_none());
+    }
+
+    #[test]
+    fn test_parse_event_invalid() {
+        let err = parse_event("Ping(uint256").unwrap_err();
+        assert_eq!(err.code, rmcp::model::ErrorCode::INVALID_PARAMS);
+    }
+
+    #[test]
+    fn test_decode_revert_string() {
+        let error = Error::parse("Error(string)").unwrap();
+        let data = error
+            .abi_encode_input(&[DynSolValue::String("boom".to_string())])
+            .unwrap();
+
+        let decoded = decode_revert(None, &data).unwrap();
+        assert_eq!(decoded.selector, "0x08c379a0");
+        assert_eq!(decoded.decoded[0].value, "boom");
+        assert!(decoded.reason.is_none());
+    }
+
+    #[test]
+    fn test_decode_revert_errors() {
+        let err = decode_revert(None, &[0x08, 0xc3]).unwrap_err();
+        assert_eq!(err.data.unwrap()["reason"], "invalid_length");
+
+        let error = parse_error("Unauthorized()").unwrap();
+        let err = decode_revert(Some(&error), &[1, 2, 3, 4]).unwrap_err();
+        assert_eq!(err.code, rmcp::model::ErrorCode::INVALID_PARAMS);
+    }
+
+    #[test]
+    fn test_panic_reason() {
+        assert_eq!(panic_reason(0x01), "assertion failed");
+        assert_eq!(panic_reason(0x32), "array index out of bounds");
+        assert_eq!(panic_reason(0x99), "unknown panic code");
+    }
+}
@@ -1,365 +1,1514 @@
-use alloy_primitives::{Address, U256};
+use alloy_dyn_abi::DynSolValue;
+use alloy_json_abi::Function;
+use alloy_primitives::{Address, B256, Bytes, U256, b256, hex, keccak256, utils::Unit};
+use alloy_provider::{
+    Provider,
+    network::{AnyNetwork, Network},
+};
+use alloy_rpc_types::{BlockId, EIP1186AccountProofResponse, TransactionRequest};
+use alloy_serde::WithOtherFields;
+use alloy_transport::TransportError;
+use alloy_trie::{
+    EMPTY_ROOT_HASH, KECCAK_EMPTY, Nibbles, TrieAccount,
+    proof::{ProofVerificationError, verify_proof},
+};
 use cast::Cast;
-use foundry_cli::{opts::RpcOpts, utils, utils::LoadConfig};
-use futures::TryFutureExt;
+use futures::{StreamExt, TryFutureExt, TryStreamExt};
 use rmcp::{
     ErrorData, handler::server::wrapper::Parameters, model::*, schemars, tool, tool_router,
 };
 use serde_default::DefaultFromSerde;
-use serde_json::Value;
+use serde_json::{Value, json};
 
-use crate::common::{common::*, server::Server};
+use crate::common::{abi, bytecode, common::*, error::*, provider::RetryProvider, server::Server};
 
 #[derive(Debug, Clone, serde::Deserialize, DefaultFromSerde, schemars::JsonSchema)]
 pub struct BalanceArgs {
-    /// The RPC endpoint, default value is http://localhost:8545.
-    #[serde(default = "default_rpc")]
-    pub rpc: String,
+    #[serde(flatten)]
+    pub endpoint: EndpointArgs,
 
-    /// The block height to query at. Can also be the tags earliest, finalized, safe, latest, pending or block hash.
     #[serde(default)]
-    pub block: Option<String>,
+    pub block: Option<BlockParam>,
 
-    /// The account address to query.
+    /// The account to query, as a hex address or an ENS name.
     pub who: String,
 
-    /// Format the balance in ether.
+    /// The unit to format the balance in: a named denomination such as wei, gwei or ether, or a number of decimals from 0 to 77. Defaults to wei.
+    #[serde(default)]
+    pub unit: Option<UnitSpec>,
+
+    /// Shorthand for `unit: "ether"`.
     #[serde(default)]
     pub ether: bool,
+
+    /// ERC-20 token addresses or ENS names. When given, the result is a JSON object with the native balance and each token's balance, decimals and symbol.
+    #[serde(default)]
+    pub tokens: Vec<String>,
 }
 
 #[derive(Debug, Clone, serde::Deserialize, DefaultFromSerde, schemars::JsonSchema)]
 pub struct NonceArgs {
-    /// The RPC endpoint, default value is http://localhost:8545.
-    #[serde(default = "default_rpc")]
-    pub rpc: String,
+    #[serde(flatten)]
+    pub endpoint: EndpointArgs,
 
-    /// The block height to query at. Can also be the tags earliest, finalized, safe, latest, pending or block hash.
     #[serde(default)]
-    pub block: Option<String>,
+    pub block: Option<BlockParam>,
 
-    /// The account address to query.
+    /// The account to query, as a hex address or an ENS name.
     pub who: String,
 }
 
 #[derive(Debug, Clone, serde::Deserialize, DefaultFromSerde, schemars::JsonSchema)]
 pub struct CodeArgs {
-    /// The RPC endpoint, default value is http://localhost:8545.
-    #[serde(default = "default_rpc")]
-    pub rpc: String,
+    #[serde(flatten)]
+    pub endpoint: EndpointArgs,
 
-    /// The block height to query at. Can also be the tags earliest, finalized, safe, latest, pending or block hash.
     #[serde(default)]
-    pub block: Option<String>,
+    pub block: Option<BlockParam>,
 
-    /// An Ethereum Address
+    /// An Ethereum address, or an ENS name.
     pub address: Option<String>,
 
-    /// An ENS Name (format does not get checked)
+    /// An ENS name. Specify either this or `address`.
     pub name: Option<String>,
 
-    /// Disassemble bytecodes into individual opcodes.
+    /// Disassemble bytecodes into individual opcodes. With `analyze`, the opcodes are listed as JSON with their program counters.
     #[serde(default)]
     pub disassemble: bool,
+
+    /// Return a JSON analysis of the bytecode instead of the code itself: dispatcher selectors, PUSH4 constants, EIP-1167 or EIP-7702 delegation, solc metadata and PUSH32 operands, immutables among them.
+    #[serde(default)]
+    pub analyze: bool,
 }
 
 #[derive(Debug, Clone, serde::Deserialize, DefaultFromSerde, schemars::JsonSchema)]
 pub struct StorageArgs {
-    /// The RPC endpoint, default value is http://localhost:8545.
-    #[serde(default = "default_rpc")]
-    pub rpc: String,
+    #[serde(flatten)]
+    pub endpoint: EndpointArgs,
 
-    /// The block height to query at. Can also be the tags earliest, finalized, safe, latest, pending or block hash.
     #[serde(default)]
-    pub block: Option<String>,
+    pub block: Option<BlockParam>,
 
-    /// The contract address to query.
+    /// The contract to query, as a hex address or an ENS name.
     pub address: String,
 
-    /// The storage slot to query.
-    pub slot: String,
+    /// The storage slot to query, as a number (decimal or 0x hex) or a 32-byte key. With `count`, the first slot to read; with `range`, the hashed key to start scanning from.
+    #[serde(default)]
+    pub slot: Option<String>,
+
+    /// Several storage slots to read at once, instead of `slot`.
+    #[serde(default)]
+    pub slots: Vec<String>,
+
+    /// Read this many consecutive slots starting at `slot` (or 0), at most 256. With `range`, the maximum number of entries to return (default 32).
+    #[serde(default)]
+    pub count: Option<u64>,
+
+    /// Scan the contract's non-empty storage with debug_storageRangeAt, which returns hashed keys with their preimages when the node knows them. Not every node supports it.
+    #[serde(default)]
+    pub range: bool,
 
-    /// Return the proof for the queried storage slot.
+    /// Return the eth_getProof account and storage proofs for the queried slots, with the storage hash and code hash.
     #[serde(default)]
     pub proof: bool,
+
+    /// Return the proofs and verify them locally against the block's state root. Implies `proof`.
+    #[serde(default)]
+    pub verify_proof: bool,
 }
 
 #[derive(Debug, Clone, serde::Deserialize, DefaultFromSerde, schemars::JsonSchema)]
 pub struct CodeSizeArgs {
-    /// The RPC endpoint, default value is http://localhost:8545.
-    #[serde(default = "default_rpc")]
-    pub rpc: String,
+    #[serde(flatten)]
+    pub endpoint: EndpointArgs,
 
-    /// The block height to query at. Can also be the tags earliest, finalized, safe, latest, pending or block hash.
     #[serde(default)]
-    pub block: Option<String>,
+    pub block: Option<BlockParam>,
 
-    /// An Ethereum Address
+    /// An Ethereum address, or an ENS name.
     pub address: Option<String>,
 
-    /// An ENS Name (format does not get checked)
+    /// An ENS name. Specify either this or `address`.
     pub name: Option<String>,
 }
 
+#[derive(Debug, Clone, serde::Deserialize, DefaultFromSerde, schemars::JsonSchema)]
+pub struct ResolveNameArgs {
+    #[serde(flatten)]
+    pub endpoint: EndpointArgs,
+
+    /// The ENS name to resolve.
+    pub name: String,
+}
+
+#[derive(Debug, Clone, serde::Deserialize, DefaultFromSerde, schemars::JsonSchema)]
+pub struct LookupAddressArgs {
+    #[serde(flatten)]
+    pub endpoint: EndpointArgs,
+
+    /// The address to look up.
+    pub who: String,
+
+    /// Check that the name resolves back to the same address.
+    #[serde(default)]
+    pub verify: bool,
+}
+
+#[derive(Debug, Clone, serde::Deserialize, DefaultFromSerde, schemars::JsonSchema)]
+pub struct CallArgs {
+    #[serde(flatten)]
+    pub endpoint: EndpointArgs,
+
+    #[serde(default)]
+    pub block: Option<BlockParam>,
+
+    /// The contract to call, as a hex address or an ENS name.
+    pub to: String,
+
+    /// The function signature, e.g. balanceOf(address)(uint256). Types in the second parentheses are used to decode the result. With `data`, the signature is only used for decoding.
+    #[serde(default)]
+    pub sig: Option<String>,
+
+    /// The function arguments, one string per input. Address arguments may be ENS names.
+    #[serde(default)]
+    pub args: Vec<String>,
+
+    /// Raw calldata as 0x-prefixed hex, sent instead of encoding `sig` and `args`.
+    #[serde(default)]
+    pub data: Option<String>,
+
+    /// The sender of the call, as a hex address or an ENS name.
+    #[serde(default)]
+    pub from: Option<String>,
+
+    /// The value to send with the call in wei, decimal or 0x hex.
+    #[serde(default)]
+    pub value: Option<String>,
+}
+
+#[derive(Debug, Clone, serde::Deserialize, DefaultFromSerde, schemars::JsonSchema)]
+pub struct ProxyInfoArgs {
+    #[serde(flatten)]
+    pub endpoint: EndpointArgs,
+
+    #[serde(default)]
+    pub block: Option<BlockParam>,
+
+    /// The proxy, as a hex address or an ENS name.
+    pub address: String,
+}
+
+#[derive(Debug, serde::Serialize, schemars::JsonSchema)]
+pub struct BalanceOutput {
+    /// The account.
+    pub address: String,
+
+    /// The native balance of the account.
+    pub native: NativeBalance,
+
+    /// The requested ERC-20 balances, in the order the tokens were given.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tokens: Vec<TokenBalance>,
+}
+
+#[derive(Debug, serde::Serialize, schemars::JsonSchema)]
+pub struct NativeBalance {
+    /// The balance in wei.
+    pub raw: String,
+
+    /// The balance in `unit`.
+    pub formatted: String,
+
+    /// The unit the balance is formatted in.
+    pub unit: String,
+
+    /// The decimals of the unit.
+    pub decimals: u8,
+}
+
+#[derive(Debug, serde::Serialize, schemars::JsonSchema)]
+pub struct NonceOutput {
+    /// The nonce of the account.
+    pub nonce: u64,
+}
+
+#[derive(Debug, serde::Serialize, schemars::JsonSchema)]
+pub struct CodeOutput {
+    /// The contract.
+    pub address: String,
+
+    /// The bytecode as hex, or its disassembly.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code: Option<String>,
+
+    /// The bytecode analysis, when `analyze` is set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub analysis: Option<bytecode::BytecodeAnalysis>,
+}
+
+#[derive(Debug, Default, serde::Serialize, schemars::JsonSchema)]
+pub struct StorageOutput {
+    /// The account the storage belongs to.
+    pub address: String,
+
+    /// The hash of the block the storage was read at, for range scans and proofs. A range scan at
+    /// the chain head reads the end of the block before it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub block_hash: Option<String>,
+
+    /// The slots read, each with its value and decoded interpretations.
+    pub slots: Vec<SlotValue>,
+
+    /// The hashed key a range scan continues from, null once the storage is exhausted.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_key: Option<Value>,
+
+    /// The eth_getProof proof of the account and the slots, when a proof was requested.
+    #[serde(flatten)]
+    pub proof: Option<StorageProof>,
+}
+
+#[derive(Debug, Clone, serde::Serialize, schemars::JsonSchema)]
+pub struct SlotValue {
+    /// The slot. Null in a range scan when the node does not know the preimage of its hash.
+    pub slot: Option<String>,
+
+    /// The raw 32-byte value.
+    pub value: String,
+
+    /// The value read in the ways it could be meant.
+    pub decoded: DecodedWord,
+
+    /// The keccak256 hash of the slot, which range scans are keyed and ordered by.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hashed_slot: Option<String>,
+}
+
+#[derive(Debug, Clone, serde::Serialize, schemars::JsonSchema)]
+pub struct DecodedWord {
+    /// The value as a uint256, in decimal.
+    pub uint: String,
+
+    /// The value as an address, when it is a non-zero, left-padded 20 bytes.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub address: Option<String>,
+
+    /// The value as a bool, when it is 0 or 1.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bool: Option<bool>,
+
+    /// The value as a string shorter than 32 bytes, stored in place.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub string: Option<String>,
+}
+
+#[derive(Debug, serde::Serialize, schemars::JsonSchema)]
+pub struct StorageProof {
+    /// The state root of the block.
+    pub state_root: String,
+
+    /// The account balance in wei.
+    pub balance: String,
+
+    /// The account nonce.
+    pub nonce: u64,
+
+    /// The hash of the account's code.
+    pub code_hash: String,
+
+    /// The root of the account's storage trie.
+    pub storage_hash: String,
+
+    /// The trie nodes from the state root to the account.
+    pub account_proof: Vec<String>,
+
+    /// Each slot's key, value and the trie nodes from the storage root to it.
+    pub storage_proof: Vec<Value>,
+
+    /// Whether the proofs check out against the state root, when `verify_proof` is set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub verification: Option<Value>,
+}
+
+#[derive(Debug, serde::Serialize, schemars::JsonSchema)]
+pub struct CodeSizeOutput {
+    /// The size of the bytecode in bytes.
+    pub size: usize,
+}
+
+#[derive(Debug, serde::Serialize, schemars::JsonSchema)]
+pub struct ResolveNameOutput {
+    /// The ENS name.
+    pub name: String,
+
+    /// The address the name resolves to.
+    pub address: String,
+}
+
+#[derive(Debug, serde::Serialize, schemars::JsonSchema)]
+pub struct LookupAddressOutput {
+    /// The address.
+    pub address: String,
+
+    /// The primary ENS name of the address.
+    pub name: String,
+}
+
+#[derive(Debug, serde::Serialize, schemars::JsonSchema)]
+pub struct CallOutput {
+    /// The called contract.
+    pub to: String,
+
+    /// The calldata sent.
+    pub calldata: String,
+
+    /// The raw return data.
+    pub output: String,
+
+    /// The decoded return values, when the signature declares outputs.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub decoded: Option<Vec<abi::DecodedParam>>,
+}
+
+#[derive(Debug, serde::Serialize, schemars::JsonSchema)]
+pub struct ProxyInfoOutput {
+    /// The queried contract.
+    pub address: String,
+
+    /// Whether the contract is a recognised proxy.
+    pub is_proxy: bool,
+
+    /// The final implementation behind the proxy chain. Null for diamonds and non-proxies.
+    pub implementation: Option<String>,
+
+    /// Each proxy followed, starting with the queried contract.
+    pub chain: Vec<ProxyHop>,
+}
+
 #[tool_router(router = account_router, vis = "pub")]
 impl Server {
-    #[tool(description = "Get the balance of an account in wei or ether")]
+    #[tool(
+        description = "Get the balance of an account as JSON with the raw wei value and the value formatted in a unit, optionally with ERC-20 token balances",
+        output_schema = output_schema::<BalanceOutput>()
+    )]
     async fn balance(
         &self,
         Parameters(args): Parameters<BalanceArgs>,
     ) -> Result<CallToolResult, ErrorData> {
-        let rpc = RpcOpts {
-            url: Some(args.rpc.clone()),
-            accept_invalid_certs: false,
-            no_proxy: false,
-            flashbots: false,
-            jwt_secret: None,
-            rpc_timeout: None,
-            rpc_headers: None,
-            curl: false,
+        let provider = self.provider(&args.endpoint)?;
+
+        let unit_spec = match (args.unit, args.ether) {
+            (Some(unit), _) => unit,
+            (None, true) => UnitSpec::Named(UnitName::Ether),
+            (None, false) => UnitSpec::Named(UnitName::Wei),
         };
-        let config = rpc.load_config().map_err(|e| {
-            ErrorData::parse_error("Invalid RPC URL", Some(Value::String(format!("{:?}", e))))
-        })?;
-        let provider = utils::get_provider(&config).map_err(|e| {
-            ErrorData::internal_error(
-                "Failed to get provider",
-                Some(Value::String(format!("{:?}", e))),
-            )
-        })?;
+        let unit = unit_spec.unit()?;
 
-        let address: Address = args.who.parse().map_err(|e| {
-            ErrorData::parse_error(
-                "Invalid address format",
-                Some(Value::String(format!("{:?}", e))),
-            )
-        })?;
+        let address = resolve_address(&provider, &args.who).await?;
 
-        let balance = Cast::new(provider)
-            .balance(address, None)
+        let mut block = resolve_block_id(&provider, args.block).await?;
+        if !args.tokens.is_empty() {
+            // The native and token balances are read at one block, even when it is a tag.
+            block = pin_block(&provider, block).await?;
+        }
+        let balance = Cast::new(provider.clone())
+            .balance(address, Some(block))
             .await
-            .map_err(|e| {
-                ErrorData::internal_error(
-                    "Failed to get balance",
-                    Some(Value::String(format!("{:?}", e))),
-                )
-            })?;
-
-        let result = if args.ether {
-            format_balance(balance)
-        } else {
-            balance.to_string()
+            .map_err(|e| upstream_error("Failed to get balance", &*e))?;
+
+        let mut result = BalanceOutput {
+            address: address.to_checksum(None),
+            native: NativeBalance {
+                raw: balance.to_string(),
+                formatted: format_amount(balance, unit),
+                unit: unit_spec.to_string(),
+                decimals: unit.get(),
+            },
+            tokens: Vec::new(),
         };
 
-        Ok(CallToolResult::success(vec![Content::text(result)]))
+        if args.tokens.is_empty() {
+            return json_output(&result);
+        }
+
+        let mut tokens = Vec::with_capacity(args.tokens.len());
+        for token in &args.tokens {
+            tokens.push(resolve_address(&provider, token).await?);
+        }
+        result.tokens = futures::future::join_all(
+            tokens
+                .into_iter()
+                .map(|token| token_balance(&provider, token, address, block)),
+        )
+        .await;
+
+        json_output(&result)
     }
 
-    #[tool(description = "Get the nonce of an account")]
+    #[tool(
+        description = "Get the nonce of an account",
+        output_schema = output_schema::<NonceOutput>()
+    )]
     async fn nonce(
         &self,
         Parameters(args): Parameters<NonceArgs>,
     ) -> Result<CallToolResult, ErrorData> {
-        let rpc = RpcOpts {
-            url: Some(args.rpc.clone()),
-            accept_invalid_certs: false,
-            no_proxy: false,
-            flashbots: false,
-            jwt_secret: None,
-            rpc_timeout: None,
-            rpc_headers: None,
-            curl: false,
-        };
-        let config = rpc.load_config().map_err(|e| {
-            ErrorData::parse_error("Invalid RPC URL", Some(Value::String(format!("{:?}", e))))
-        })?;
-        let provider = utils::get_provider(&config).map_err(|e| {
-            ErrorData::internal_error(
-                "Failed to get provider",
-                Some(Value::String(format!("{:?}", e))),
-            )
-        })?;
+        let provider = self.provider(&args.endpoint)?;
 
-        let address: Address = args.who.parse().map_err(|e| {
-            ErrorData::parse_error(
-                "Invalid address format",
-                Some(Value::String(format!("{:?}", e))),
-            )
-        })?;
+        let address = resolve_address(&provider, &args.who).await?;
 
+        let block = resolve_block_id(&provider, args.block).await?;
         let nonce = Cast::new(provider)
-            .nonce(address, Some(get_block_id(args.block)))
+            .nonce(address, Some(block))
             .await
-            .map_err(|e| {
-                ErrorData::internal_error(
-                    "Failed to get nonce",
-                    Some(Value::String(format!("{:?}", e))),
-                )
-            })?;
+            .map_err(|e| upstream_error("Failed to get nonce", &*e))?;
 
-        Ok(CallToolResult::success(vec![Content::text(
-            nonce.to_string(),
-        )]))
+        structured(nonce.to_string(), &NonceOutput { nonce })
     }
 
-    #[tool(description = "Get the bytecode of a contract")]
+    #[tool(
+        description = "Get the bytecode of a contract, optionally disassembled or analyzed as JSON",
+        output_schema = output_schema::<CodeOutput>()
+    )]
     async fn code(
         &self,
         Parameters(args): Parameters<CodeArgs>,
     ) -> Result<CallToolResult, ErrorData> {
-        let rpc = RpcOpts {
-            url: Some(args.rpc.clone()),
-            accept_invalid_certs: false,
-            no_proxy: false,
-            flashbots: false,
-            jwt_secret: None,
-            rpc_timeout: None,
-            rpc_headers: None,
-            curl: false,
-        };
-        let config = rpc.load_config().map_err(|e| {
-            ErrorData::parse_error("Invalid RPC URL", Some(Value::String(format!("{:?}", e))))
-        })?;
-        let provider = utils::get_provider(&config).map_err(|e| {
-            ErrorData::internal_error(
-                "Failed to get provider",
-                Some(Value::String(format!("{:?}", e))),
-            )
-        })?;
-
-        let address = resolve(&provider, args.name, args.address)
-            .await
-            .map_err(|e| {
-                ErrorData::internal_error(
-                    "Failed to get address",
-                    Some(Value::String(format!("{:?}", e))),
-                )
-            })?;
+        let provider = self.provider(&args.endpoint)?;
+
+        let address = resolve(&provider, args.name, args.address).await?;
+
+        let block = resolve_block_id(&provider, args.block).await?;
+        if args.analyze {
+            let code = provider
+                .get_code_at(address)
+                .block_id(block)
+                .await
+                .map_err(|e| rpc_error("Failed to get code", &e))?;
+            return json_output(&CodeOutput {
+                address: address.to_checksum(None),
+                code: None,
+                analysis: Some(bytecode::analyze(&code, args.disassemble)),
+            });
+        }
 
         let code = Cast::new(provider)
-            .code(address, Some(get_block_id(args.block)), args.disassemble)
+            .code(address, Some(block), args.disassemble)
             .await
-            .map_err(|e| {
-                ErrorData::internal_error(
-                    "Failed to get code",
-                    Some(Value::String(format!("{:?}", e))),
-                )
-            })?;
+            .map_err(|e| upstream_error("Failed to get code", &*e))?;
 
-        Ok(CallToolResult::success(vec![Content::text(code)]))
+        let output = CodeOutput {
+            address: address.to_checksum(None),
+            code: Some(code.clone()),
+            analysis: None,
+        };
+        structured(code, &output)
     }
 
-    #[tool(description = "Get the storage value at a specific slot")]
+    #[tool(
+        description = "Get the storage value at a slot, or several slots at once with decoded interpretations, or scan storage with debug_storageRangeAt",
+        output_schema = output_schema::<StorageOutput>()
+    )]
     async fn storage(
         &self,
         Parameters(args): Parameters<StorageArgs>,
     ) -> Result<CallToolResult, ErrorData> {
-        let rpc = RpcOpts {
-            url: Some(args.rpc.clone()),
-            accept_invalid_certs: false,
-            no_proxy: false,
-            flashbots: false,
-            jwt_secret: None,
-            rpc_timeout: None,
-            rpc_headers: None,
-            curl: false,
-        };
-        let config = rpc.load_config().map_err(|e| {
-            ErrorData::parse_error("Invalid RPC URL", Some(Value::String(format!("{:?}", e))))
-        })?;
-        let provider = utils::get_provider(&config).map_err(|e| {
-            ErrorData::internal_error(
-                "Failed to get provider",
-                Some(Value::String(format!("{:?}", e))),
-            )
-        })?;
+        let provider = self.provider(&args.endpoint)?;
 
-        let address: Address = args.address.parse().map_err(|e| {
-            ErrorData::parse_error(
-                "Invalid address format",
-                Some(Value::String(format!("{:?}", e))),
-            )
-        })?;
+        let address = resolve_address(&provider, &args.address).await?;
 
-        let slot: alloy_primitives::B256 = args.slot.parse().map_err(|e| {
-            ErrorData::parse_error(
-                "Invalid slot format",
-                Some(Value::String(format!("{:?}", e))),
-            )
-        })?;
+        if args.range {
+            if !args.slots.is_empty() || args.proof || args.verify_proof {
+                return Err(ambiguous_slots(
+                    "Specify range without slots, proof or verify_proof",
+                ));
+            }
+            let start = args.slot.as_deref().map(parse_slot).transpose()?;
+            let max = check_slot_count(args.count.unwrap_or(DEFAULT_STORAGE_RANGE))?;
+            let block = resolve_block_id(&provider, args.block).await?;
+            let range =
+                storage_range(&provider, address, block, start.unwrap_or_default(), max).await?;
+            return json_output(&range);
+        }
 
-        let storage = Cast::new(provider)
-            .storage(address, slot, Some(get_block_id(args.block)))
-            .await
-            .map_err(|e| {
-                ErrorData::internal_error(
-                    "Failed to get storage",
-                    Some(Value::String(format!("{:?}", e))),
-                )
-            })?;
+        let slots = match (&args.slot, &args.slots, args.count) {
+            (Some(slot), slots, None) if slots.is_empty() => vec![parse_slot(slot)?],
+            (None, slots, None) if !slots.is_empty() => {
+                check_slot_count(slots.len() as u64)?;
+                slots
+                    .iter()
+                    .map(|slot| parse_slot(slot))
+                    .collect::<Result<Vec<_>, _>>()?
+            }
+            (start, slots, Some(count)) if slots.is_empty() => {
+                let count = check_slot_count(count)?;
+                let start = start.as_deref().map(parse_slot).transpose()?;
+                consecutive_slots(start.unwrap_or_default(), count)?
+            }
+            (None, slots, None) if slots.is_empty() => {
+                return Err(invalid_input(
+                    "Either slot, slots or count is required",
+                    Some(json!({ "reason": "missing" })),
+                ));
+            }
+            _ => {
+                return Err(ambiguous_slots(
+                    "Specify either slots, or slot with an optional count",
+                ));
+            }
+        };
 
-        Ok(CallToolResult::success(vec![Content::text(storage)]))
+        let block = resolve_block_id(&provider, args.block).await?;
+        if args.proof || args.verify_proof {
+            let proof = storage_proof(&provider, address, block, &slots, args.verify_proof).await?;
+            return json_output(&proof);
+        }
+
+        let values: Vec<U256> = futures::stream::iter(slots.iter().map(|slot| {
+            provider
+                .get_storage_at(address, *slot)
+                .block_id(block)
+                .into_future()
+        }))
+        .buffered(MAX_CONCURRENT_SLOTS)
+        .try_collect()
+        .await
+        .map_err(|e| rpc_error("Failed to get storage", &e))?;
+
+        let output = StorageOutput {
+            address: address.to_checksum(None),
+            slots: slots
+                .iter()
+                .zip(&values)
+                .map(|(slot, value)| slot_value(Some(B256::from(*slot)), B256::from(*value)))
+                .collect(),
+            ..Default::default()
+        };
+        // A single slot reads as its raw value, like cast storage.
+        if args.slots.is_empty() && args.count.is_none() {
+            return structured(B256::from(values[0]).to_string(), &output);
+        }
+        json_output(&output)
     }
 
-    #[tool(description = "Get the size of contract bytecode in bytes")]
+    #[tool(
+        description = "Get the size of contract bytecode in bytes",
+        output_schema = output_schema::<CodeSizeOutput>()
+    )]
     async fn code_size(
         &self,
         Parameters(args): Parameters<CodeSizeArgs>,
     ) -> Result<CallToolResult, ErrorData> {
-        let rpc = RpcOpts {
-            url: Some(args.rpc.clone()),
-            accept_invalid_certs: false,
-            no_proxy: false,
-            flashbots: false,
-            jwt_secret: None,
-            rpc_timeout: None,
-            rpc_headers: None,
-            curl: false,
+        let provider = self.provider(&args.endpoint)?;
+
+        let address = resolve(&provider, args.name, args.address).await?;
+
+        let block = resolve_block_id(&provider, args.block).await?;
+        let size = provider
+            .get_code_at(address)
+            .block_id(block)
+            .await
+            .map_err(|e| rpc_error("Failed to get code size", &e))?
+            .len();
+
+        structured(size.to_string(), &CodeSizeOutput { size })
+    }
+
+    #[tool(
+        description = "Resolve an ENS name to its address",
+        output_schema = output_schema::<ResolveNameOutput>()
+    )]
+    async fn resolve_name(
+        &self,
+        Parameters(args): Parameters<ResolveNameArgs>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let provider = self.provider(&args.endpoint)?;
+
+        let address = resolve_name(&provider, &args.name).await?.to_checksum(None);
+
+        let output = ResolveNameOutput {
+            name: args.name,
+            address: address.clone(),
         };
-        let config = rpc.load_config().map_err(|e| {
-            ErrorData::parse_error("Invalid RPC URL", Some(Value::String(format!("{:?}", e))))
-        })?;
-        let provider = utils::get_provider(&config).map_err(|e| {
-            ErrorData::internal_error(
-                "Failed to get provider",
-                Some(Value::String(format!("{:?}", e))),
-            )
-        })?;
+        structured(address, &output)
+    }
 
-        let address = resolve(&provider, args.name, args.address)
+    #[tool(
+        description = "Look up the primary ENS name of an address",
+        output_schema = output_schema::<LookupAddressOutput>()
+    )]
+    async fn lookup_address(
+        &self,
+        Parameters(args): Parameters<LookupAddressArgs>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let provider = self.provider(&args.endpoint)?;
+
+        let address = parse_address(&args.who)?;
+        let name = lookup_address(&provider, address, args.verify).await?;
+
+        let output = LookupAddressOutput {
+            address: address.to_checksum(None),
+            name: name.clone(),
+        };
+        structured(name, &output)
+    }
+
+    #[tool(
+        description = "Call a contract function without sending a transaction (eth_call), encoding the arguments from a signature such as balanceOf(address)(uint256) and decoding the outputs",
+        output_schema = output_schema::<CallOutput>()
+    )]
+    async fn call(
+        &self,
+        Parameters(args): Parameters<CallArgs>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let provider = self.provider(&args.endpoint)?;
+
+        let func = args.sig.as_deref().map(abi::parse_function).transpose()?;
+        let (tx, calldata) = call_request(&provider, &args, func.as_ref()).await?;
+        let to = tx.to.and_then(|to| to.to().copied()).unwrap_or_default();
+        let block = resolve_block_id(&provider, args.block).await?;
+
+        let output = provider
+            .call(WithOtherFields::new(tx))
+            .block(block)
             .await
-            .map_err(|e| {
-                ErrorData::internal_error(
-                    "Failed to get address",
-                    Some(Value::String(format!("{:?}", e))),
+            .map_err(|e| rpc_error("Call failed", &e))?;
+
+        let decoded = func
+            .filter(|func| !func.outputs.is_empty())
+            .map(|func| abi::decode_output(&func, &output))
+            .transpose()?;
+        json_output(&CallOutput {
+            to: to.to_checksum(None),
+            calldata: hex::encode_prefixed(&calldata),
+            output: output.to_string(),
+            decoded,
+        })
+    }
+
+    #[tool(
+        description = "Detect whether a contract is a proxy (EIP-1967, beacon, EIP-1822, EIP-1167, Safe, EIP-2535 diamond) and follow it to its implementation",
+        output_schema = output_schema::<ProxyInfoOutput>()
+    )]
+    async fn proxy_info(
+        &self,
+        Parameters(args): Parameters<ProxyInfoArgs>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let provider = self.provider(&args.endpoint)?;
+
+        let address = resolve_address(&provider, &args.address).await?;
+        let block = resolve_block_id(&provider, args.block).await?;
+
+        // Proxies can point at proxies, so the implementation is followed until it is not one.
+        let mut chain: Vec<ProxyHop> = Vec::new();
+        let mut current = address;
+        while chain.len() < MAX_PROXY_DEPTH {
+            let Some(hop) = detect_proxy(&provider, current, block).await? else {
+                break;
+            };
+            let next = hop.implementation;
+            chain.push(hop);
+            match next {
+                Some(next) if chain.iter().all(|hop| hop.address != next) => current = next,
+                _ => break,
+            }
+        }
+
+        let implementation = chain
+            .last()
+            .and_then(|hop| hop.implementation)
+            .map(|implementation| implementation.to_checksum(None));
+        json_output(&ProxyInfoOutput {
+            address: address.to_checksum(None),
+            is_proxy: !chain.is_empty(),
+            implementation,
+            chain,
+        })
+    }
+}
+
+/// Build the transaction request a `call` or `estimate` describes, returning it with its
+/// calldata. The calldata is `data` as given, or `func` encoded with `args`.
+pub(crate) async fn call_request(
+    provider: &RetryProvider,
+    args: &CallArgs,
+    func: Option<&Function>,
+) -> Result<(TransactionRequest, Vec<u8>), ErrorData> {
+    let calldata = match (&args.data, func) {
+        (Some(data), _) => {
+            if !args.args.is_empty() {
+                return Err(invalid_input(
+                    "Specify either data or args, not both",
+                    Some(json!({ "reason": "ambiguous" })),
+                ));
+            }
+            hex::decode(data.trim()).map_err(|e| {
+                invalid_input(
+                    format!("Invalid calldata: {data}"),
+                    Some(json!({ "input": data, "reason": e.to_string() })),
                 )
-            })?;
+            })?
+        }
+        (None, Some(func)) => {
+            let values = abi::coerce_args(provider, &func.inputs, &args.args).await?;
+            abi::encode_call(func, &values)?
+        }
+        (None, None) => {
+            return Err(invalid_input(
+                "Either sig or data is required",
+                Some(json!({ "reason": "missing" })),
+            ));
+        }
+    };
+
+    let to = resolve_address(provider, &args.to).await?;
+    let mut tx = TransactionRequest::default()
+        .to(to)
+        .input(Bytes::from(calldata.clone()).into());
+    if let Some(from) = &args.from {
+        tx = tx.from(resolve_address(provider, from).await?);
+    }
+    if let Some(value) = &args.value {
+        tx = tx.value(value.trim().parse::<U256>().map_err(|e| {
+            invalid_input(
+                format!("Invalid value: {value}"),
+                Some(json!({ "input": value, "reason": e.to_string() })),
+            )
+        })?);
+    }
+    Ok((tx, calldata))
+}
 
-        let byte_size = Cast::new(provider)
-            .codesize(address, Some(get_block_id(args.block)))
-            .await
-            .map_err(|e| {
-                ErrorData::internal_error(
-                    "Failed to get code size",
-                    Some(Value::String(format!("{:?}", e))),
+const BALANCE_OF: &str = "balanceOf(address)(uint256)";
+const DECIMALS: &str = "decimals()(uint8)";
+const SYMBOL: &str = "symbol()(string)";
+/// The `bytes32` symbol some older tokens, such as MKR, return instead of a string.
+const SYMBOL_BYTES32: &str = "symbol()(bytes32)";
+
+/// An ERC-20 balance. A token that fails reports `error` instead of a balance.
+#[derive(Debug, serde::Serialize, schemars::JsonSchema)]
+pub struct TokenBalance {
+    token: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    symbol: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    decimals: Option<u8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    balance: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    formatted: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<TokenError>,
+}
+
+/// Why a token balance could not be read, classified like tool errors.
+#[derive(Debug, serde::Serialize, schemars::JsonSchema)]
+pub struct TokenError {
+    pub kind: ErrorKind,
+    /// Whether reading the balance again may succeed.
+    pub retryable: bool,
+    pub message: String,
+}
+
+impl From<ErrorData> for TokenError {
+    fn from(error: ErrorData) -> Self {
+        let kind = error_kind(&error);
+        Self {
+            kind,
+            retryable: kind.retryable(),
+            message: error.message.into_owned(),
+        }
+    }
+}
+
+/// Fetch an ERC-20 balance with the token's decimals and symbol. A failing token is reported in
+/// its `error` field rather than failing the whole call; decimals and symbol are optional in
+/// ERC-20 and are left out when the token does not implement them.
+async fn token_balance(
+    provider: &RetryProvider,
+    token: Address,
+    owner: Address,
+    block: BlockId,
+) -> TokenBalance {
+    let (balance, decimals, symbol) = futures::join!(
+        call_function(
+            provider,
+            token,
+            BALANCE_OF,
+            &[DynSolValue::Address(owner)],
+            block
+        ),
+        call_function(provider, token, DECIMALS, &[], block),
+        token_symbol(provider, token, block),
+    );
+
+    let decimals = decimals
+        .ok()
+        .and_then(|value| value.as_str()?.parse::<u8>().ok());
+    let balance = balance.and_then(|value| {
+        value
+            .as_str()
+            .and_then(|s| s.parse::<U256>().ok())
+            .ok_or_else(|| internal_error("Unexpected output of balanceOf", value))
+    });
+    let (balance, error) = match balance {
+        Ok(balance) => (Some(balance), None),
+        Err(e) => (None, Some(TokenError::from(e))),
+    };
+    let formatted = balance
+        .zip(decimals.and_then(Unit::new))
+        .map(|(balance, unit)| format_amount(balance, unit));
+    TokenBalance {
+        token: token.to_checksum(None),
+        symbol,
+        decimals,
+        balance: balance.map(|b| b.to_string()),
+        formatted,
+        error,
+    }
+}
+
+/// Read a token's symbol, which is optional in ERC-20.
+async fn token_symbol(provider: &RetryProvider, token: Address, block: BlockId) -> Option<String> {
+    let func = abi::parse_function(SYMBOL).ok()?;
+    let data = eth_call(provider, token, abi::encode_call(&func, &[]).ok()?, block)
+        .await
+        .ok()?;
+    decode_symbol(&data)
+}
+
+/// Decode the output of `symbol()`, as a string or as a zero-padded `bytes32`.
+fn decode_symbol(data: &[u8]) -> Option<String> {
+    let func = abi::parse_function(SYMBOL).ok()?;
+    if let Ok(Value::String(symbol)) = first_output(&func, data) {
+        return Some(symbol);
+    }
+    let func = abi::parse_function(SYMBOL_BYTES32).ok()?;
+    let Value::String(word) = first_output(&func, data).ok()? else {
+        return None;
+    };
+    let bytes = hex::decode(word).ok()?;
+    let end = bytes.iter().position(|b| *b == 0).unwrap_or(bytes.len());
+    String::from_utf8(bytes[..end].to_vec()).ok()
+}
+
+/// Call the function `signature` on `to` and return its first output, decoded.
+async fn call_function(
+    provider: &RetryProvider,
+    to: Address,
+    signature: &str,
+    args: &[DynSolValue],
+    block: BlockId,
+) -> Result<Value, ErrorData> {
+    let func = abi::parse_function(signature)?;
+    let data = eth_call(provider, to, abi::encode_call(&func, args)?, block)
+        .await
+        .map_err(|e| rpc_error(format!("Failed to call {}", func.signature()), &e))?;
+    first_output(&func, &data)
+}
+
+fn first_output(func: &Function, data: &[u8]) -> Result<Value, ErrorData> {
+    abi::decode_output(func, data)?
+        .into_iter()
+        .next()
+        .map(|output| output.value)
+        .ok_or_else(|| internal_error("Function has no outputs", func.signature()))
+}
+
+async fn eth_call(
+    provider: &RetryProvider,
+    to: Address,
+    calldata: Vec<u8>,
+    block: BlockId,
+) -> Result<Bytes, TransportError> {
+    let tx = TransactionRequest::default()
+        .to(to)
+        .input(Bytes::from(calldata).into());
+    provider.call(WithOtherFields::new(tx)).block(block).await
+}
+
+/// `bytes32(uint256(keccak256("eip1967.proxy.implementation")) - 1)`
+const EIP1967_IMPLEMENTATION_SLOT: B256 =
+    b256!("0x360894a13ba1a3210667c828492db98dca3e2076cc3735a920a3ca505d382bbc");
+/// `bytes32(uint256(keccak256("eip1967.proxy.admin")) - 1)`
+const EIP1967_ADMIN_SLOT: B256 =
+    b256!("0xb53127684a568b3173ae13b9f8a6016e243e63b6e8ee1178d6a717850b5d6103");
+/// `bytes32(uint256(keccak256("eip1967.proxy.beacon")) - 1)`
+const EIP1967_BEACON_SLOT: B256 =
+    b256!("0xa3f0ad74e5423aebfd80d3ef4346578335a9a72aeaee59ff6cb3582b35133d50");
+/// `keccak256("org.zeppelinos.proxy.implementation")`, used by proxies predating EIP-1967.
+const ZEPPELINOS_IMPLEMENTATION_SLOT: B256 =
+    b256!("0x7050c9e0f4ca769c69bd3a8ef740bc37934f8e2c036e5a723fd8ee048ed3f8c3");
+/// `keccak256("PROXIABLE")`
+const EIP1822_PROXIABLE_SLOT: B256 =
+    b256!("0xc5f16f0fcc639fa48a6947836d9850f504798523bf8c9a3a87d5876cf622bcf7");
+
+/// The implementation getter of an EIP-1967 beacon.
+const IMPLEMENTATION: &str = "implementation()(address)";
+/// The singleton getter of a Safe proxy.
+const MASTER_COPY: &str = "masterCopy()(address)";
+/// The facets of an EIP-2535 diamond, from its loupe.
+const FACETS: &str = "facets()((address,bytes4[])[])";
+
+/// How many proxies pointing at proxies are followed.
+const MAX_PROXY_DEPTH: usize = 5;
+
+/// A proxy and where it delegates to.
+#[derive(Debug, serde::Serialize, schemars::JsonSchema)]
+pub struct ProxyHop {
+    /// The proxy.
+    #[serde(serialize_with = "checksummed")]
+    #[schemars(with = "String")]
+    pub address: Address,
+
+    pub kind: ProxyKind,
+
+    /// Where calls are delegated. Null for diamonds, and for beacons whose implementation could
+    /// not be read.
+    #[serde(serialize_with = "checksummed_opt")]
+    #[schemars(with = "Option<String>")]
+    pub implementation: Option<Address>,
+
+    /// The EIP-1967 admin.
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "checksummed_opt"
+    )]
+    #[schemars(with = "Option<String>")]
+    pub admin: Option<Address>,
+
+    /// The EIP-1967 beacon.
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "checksummed_opt"
+    )]
+    #[schemars(with = "Option<String>")]
+    pub beacon: Option<Address>,
+
+    /// The facets of a diamond, which has no single implementation.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub facets: Vec<Facet>,
+}
+
+/// The pattern a proxy follows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, schemars::JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ProxyKind {
+    /// The implementation is in the EIP-1967 implementation slot.
+    Eip1967,
+    /// The implementation is read from the beacon in the EIP-1967 beacon slot.
+    Eip1967Beacon,
+    /// The implementation is in the EIP-1822 (UUPS) proxiable slot.
+    Eip1822,
+    /// The implementation is in the ZeppelinOS implementation slot.
+    Zeppelinos,
+    /// A Safe proxy, whose singleton is in slot 0.
+    Safe,
+    /// An EIP-2535 diamond, routing selectors to facets.
+    Diamond,
+    /// An EIP-1167 minimal proxy.
+    Eip1167,
+    /// An EIP-7702 delegated account.
+    Eip7702,
+}
+
+impl From<bytecode::DelegationKind> for ProxyKind {
+    fn from(kind: bytecode::DelegationKind) -> Self {
+        match kind {
+            bytecode::DelegationKind::Eip1167 => Self::Eip1167,
+            bytecode::DelegationKind::Eip7702 => Self::Eip7702,
+        }
+    }
+}
+
+/// A facet of an EIP-2535 diamond.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, schemars::JsonSchema)]
+pub struct Facet {
+    /// The facet contract.
+    pub address: String,
+
+    /// The selectors the diamond routes to it.
+    pub selectors: Vec<String>,
+}
+
+fn checksummed<S: serde::Serializer>(address: &Address, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&address.to_checksum(None))
+}
+
+fn checksummed_opt<S: serde::Serializer>(
+    address: &Option<Address>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match address {
+        Some(address) => checksummed(address, serializer),
+        None => serializer.serialize_none(),
+    }
+}
+
+/// Check `address` against the known proxy patterns, most specific first. Returns `None` for
+/// accounts without code and contracts that are not recognised as proxies.
+async fn detect_proxy(
+    provider: &RetryProvider,
+    address: Address,
+    block: BlockId,
+) -> Result<Option<ProxyHop>, ErrorData> {
+    let code = Cast::new(provider.clone())
+        .code(address, Some(block), false)
+        .await
+        .map_err(|e| upstream_error("Failed to get code", &*e))?;
+    let code = hex::decode(&code).map_err(|e| internal_error("Invalid code returned", e))?;
+    if code.is_empty() {
+        return Ok(None);
+    }
+    let hop = |kind: ProxyKind| ProxyHop {
+        address,
+        kind,
+        implementation: None,
+        admin: None,
+        beacon: None,
+        facets: Vec::new(),
+    };
+
+    if let Some(delegation) = bytecode::delegation(&code) {
+        return Ok(Some(ProxyHop {
+            implementation: Some(delegation.address()),
+            ..hop(delegation.kind().into())
+        }));
+    }
+
+    let (implementation, admin, beacon, proxiable, zeppelinos, slot0) = futures::try_join!(
+        storage_address(provider, address, EIP1967_IMPLEMENTATION_SLOT, block),
+        storage_address(provider, address, EIP1967_ADMIN_SLOT, block),
+        storage_address(provider, address, EIP1967_BEACON_SLOT, block),
+        storage_address(provider, address, EIP1822_PROXIABLE_SLOT, block),
+        storage_address(provider, address, ZEPPELINOS_IMPLEMENTATION_SLOT, block),
+        storage_address(provider, address, B256::ZERO, block),
+    )?;
+
+    if implementation.is_some() {
+        return Ok(Some(ProxyHop {
+            implementation,
+            admin,
+            ..hop(ProxyKind::Eip1967)
+        }));
+    }
+    if let Some(beacon) = beacon {
+        let implementation = call_address(provider, beacon, IMPLEMENTATION, block).await;
+        return Ok(Some(ProxyHop {
+            implementation,
+            admin,
+            beacon: Some(beacon),
+            ..hop(ProxyKind::Eip1967Beacon)
+        }));
+    }
+    if proxiable.is_some() {
+        return Ok(Some(ProxyHop {
+            implementation: proxiable,
+            ..hop(ProxyKind::Eip1822)
+        }));
+    }
+    if zeppelinos.is_some() {
+        return Ok(Some(ProxyHop {
+            implementation: zeppelinos,
+            ..hop(ProxyKind::Zeppelinos)
+        }));
+    }
+
+    // Safe proxies keep their singleton in slot 0 and answer masterCopy() themselves; checking
+    // both keeps ordinary contracts that store an address in slot 0 from being mistaken for one.
+    if let Some(singleton) = slot0 {
+        let master_copy = call_address(provider, address, MASTER_COPY, block).await;
+        if master_copy == Some(singleton) {
+            return Ok(Some(ProxyHop {
+                implementation: Some(singleton),
+                ..hop(ProxyKind::Safe)
+            }));
+        }
+    }
+
+    let facets = abi::encode_call(&abi::parse_function(FACETS)?, &[])?;
+    if let Ok(data) = eth_call(provider, address, facets, block).await {
+        let facets = decode_facets(&data);
+        if !facets.is_empty() {
+            return Ok(Some(ProxyHop {
+                facets,
+                ..hop(ProxyKind::Diamond)
+            }));
+        }
+    }
+    Ok(None)
+}
+
+/// The most slots a single storage call reads.
+const MAX_STORAGE_SLOTS: u64 = 256;
+/// How many slots of a single storage call are read at the same time.
+const MAX_CONCURRENT_SLOTS: usize = 16;
+/// How many entries a storage range scan returns by default.
+const DEFAULT_STORAGE_RANGE: u64 = 32;
+
+/// Parse a storage slot given as a decimal or 0x hex number, or as a 32-byte key.
+fn parse_slot(input: &str) -> Result<U256, ErrorData> {
+    input.trim().parse::<U256>().map_err(|e| {
+        invalid_input(
+            format!("Invalid storage slot: {input}"),
+            Some(json!({
+                "input": input,
+                "expected": "a decimal or 0x hex number, or a 32-byte hex key",
+                "reason": e.to_string(),
+            })),
+        )
+    })
+}
+
+fn check_slot_count(count: u64) -> Result<usize, ErrorData> {
+    if count == 0 || count > MAX_STORAGE_SLOTS {
+        return Err(invalid_input(
+            format!("Cannot read {count} storage slots at once"),
+            Some(json!({ "input": count, "expected": format!("1 to {MAX_STORAGE_SLOTS}") })),
+        ));
+    }
+    Ok(count as usize)
+}
+
+fn consecutive_slots(start: U256, count: usize) -> Result<Vec<U256>, ErrorData> {
+    (0..count)
+        .map(|i| {
+            start.checked_add(U256::from(i)).ok_or_else(|| {
+                invalid_input(
+                    "Storage slot range overflows",
+                    Some(json!({ "start": start.to_string(), "count": count })),
                 )
-            })?;
+            })
+        })
+        .collect()
+}
+
+fn ambiguous_slots(message: &str) -> ErrorData {
+    invalid_input(message.to_string(), Some(json!({ "reason": "ambiguous" })))
+}
+
+/// Describe a storage value with the ways it could be read: always as a uint, and as an address,
+/// a bool or a short string when its bytes fit that shape.
+fn slot_value(slot: Option<B256>, value: B256) -> SlotValue {
+    let decoded = DecodedWord {
+        uint: U256::from_be_bytes(value.0).to_string(),
+        address: word_address(value.as_slice()).map(|address| address.to_checksum(None)),
+        bool: (value.0[..31].iter().all(|b| *b == 0) && value.0[31] <= 1).then(|| value.0[31] == 1),
+        string: short_string(&value),
+    };
+    SlotValue {
+        slot: slot.map(|slot| slot.to_string()),
+        value: value.to_string(),
+        decoded,
+        hashed_slot: None,
+    }
+}
+
+/// Decode a string or bytes value stored in place, as Solidity stores those shorter than 32
+/// bytes: left-aligned, with twice the length in the lowest byte.
+fn short_string(word: &B256) -> Option<String> {
+    let last = word.0[31];
+    if last % 2 != 0 || last == 0 {
+        return None;
+    }
+    let len = usize::from(last / 2);
+    if len > 31 || word.0[len..31].iter().any(|b| *b != 0) {
+        return None;
+    }
+    let string = std::str::from_utf8(&word.0[..len]).ok()?;
+    (!string.chars().any(char::is_control)).then(|| string.to_string())
+}
+
+/// Fetch the eth_getProof proofs of `slots`, optionally verifying them against the block's state
+/// root. The block is pinned by hash so the proof and the root it is checked against agree.
+async fn storage_proof(
+    provider: &RetryProvider,
+    address: Address,
+    block: BlockId,
+    slots: &[U256],
+    verify: bool,
+) -> Result<StorageOutput, ErrorData> {
+    let header = get_block(provider, block, false).await?;
+    let state_root = header.header.state_root;
+    let keys = slots.iter().map(|slot| B256::from(*slot)).collect();
+    let proof = provider
+        .get_proof(address, keys)
+        .block_id(header.header.hash.into())
+        .await
+        .map_err(|e| rpc_error("Failed to get proof", &e))?;
+    check_proof_matches(&proof, address, slots)?;
+
+    let storage_proof: Vec<Value> = proof
+        .storage_proof
+        .iter()
+        .map(|slot| {
+            json!({
+                "key": slot.key.as_b256(),
+                "value": B256::from(slot.value),
+                "proof": slot.proof,
+            })
+        })
+        .collect();
+    Ok(StorageOutput {
+        address: address.to_checksum(None),
+        block_hash: Some(header.header.hash.to_string()),
+        slots: proof
+            .storage_proof
+            .iter()
+            .map(|slot| slot_value(Some(slot.key.as_b256()), B256::from(slot.value)))
+            .collect(),
+        proof: Some(StorageProof {
+            state_root: state_root.to_string(),
+            balance: proof.balance.to_string(),
+            nonce: proof.nonce,
+            code_hash: proof.code_hash.to_string(),
+            storage_hash: proof.storage_hash.to_string(),
+            account_proof: proof
+                .account_proof
+                .iter()
+                .map(ToString::to_string)
+                .collect(),
+            storage_proof,
+            verification: verify.then(|| {
+                let mut verification = verify_account_proof(state_root, &proof);
+                let header = verify_header(&header.header);
+                verification["valid"] =
+                    json!(verification["valid"] == true && header["valid"] == true);
+                verification["header"] = header;
+                verification
+            }),
+        }),
+        ..Default::default()
+    })
+}
+
+/// Check an eth_getProof response is for the account and slots that were asked for, in order, so
+/// a proof of something else is never reported as the requested storage.
+fn check_proof_matches(
+    proof: &EIP1186AccountProofResponse,
+    address: Address,
+    slots: &[U256],
+) -> Result<(), ErrorData> {
+    let mismatch = |message: &str, expected: Value, got: Value| {
+        tool_error(
+            ErrorKind::RpcError,
+            format!("The node returned a proof for {message} than requested"),
+            Some(json!({ "reason": "proof_mismatch", "expected": expected, "got": got })),
+        )
+    };
+    if proof.address != address {
+        return Err(mismatch(
+            "another account",
+            json!(address.to_checksum(None)),
+            json!(proof.address.to_checksum(None)),
+        ));
+    }
+    if proof.storage_proof.len() != slots.len() {
+        return Err(mismatch(
+            "a different number of slots",
+            json!(slots.len()),
+            json!(proof.storage_proof.len()),
+        ));
+    }
+    for (slot, storage) in slots.iter().zip(&proof.storage_proof) {
+        if storage.key.as_b256() != B256::from(*slot) {
+            return Err(mismatch(
+                "other slots",
+                json!(B256::from(*slot)),
+                json!(storage.key.as_b256()),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Check the block hash commits to the header, and so to the state root the proofs are checked
+/// against. Headers the server cannot hash, such as ones missing fields on some L2s, are not
+/// verifiable.
+fn verify_header(header: &<AnyNetwork as Network>::HeaderResponse) -> Value {
+    let Ok(consensus) = header.inner.clone().try_into_header() else {
+        return json!({ "valid": false, "error": "the header is missing fields needed to hash it" });
+    };
+    let hash = consensus.hash_slow();
+    if hash != header.hash {
+        return json!({
+            "valid": false,
+            "error": format!("the header hashes to {hash}, not to the block hash {}", header.hash),
+        });
+    }
+    json!({ "valid": true })
+}
+
+/// Verify an eth_getProof response: the account against the state root, and each slot against
+/// the account's storage root. Accounts and slots that are empty must be proven absent.
+fn verify_account_proof(state_root: B256, proof: &EIP1186AccountProofResponse) -> Value {
+    let account = TrieAccount {
+        nonce: proof.nonce,
+        balance: proof.balance,
+        storage_root: proof.storage_hash,
+        code_hash: proof.code_hash,
+    };
+    let empty = proof.nonce == 0
+        && proof.balance.is_zero()
+        && proof.storage_hash == EMPTY_ROOT_HASH
+        && proof.code_hash == KECCAK_EMPTY;
+    let account = verification_json(verify_proof(
+        state_root,
+        Nibbles::unpack(keccak256(proof.address)),
+        (!empty).then(|| alloy_rlp::encode(account)),
+        &proof.account_proof,
+    ));
+
+    let storage: Vec<Value> = proof
+        .storage_proof
+        .iter()
+        .map(|slot| {
+            let key = slot.key.as_b256();
+            let mut result = verification_json(verify_proof(
+                proof.storage_hash,
+                Nibbles::unpack(keccak256(key)),
+                (!slot.value.is_zero()).then(|| alloy_rlp::encode(slot.value)),
+                &slot.proof,
+            ));
+            result["key"] = json!(key);
+            result
+        })
+        .collect();
+
+    let valid = account["valid"] == true && storage.iter().all(|slot| slot["valid"] == true);
+    json!({ "valid": valid, "account": account, "storage": storage })
+}
+
+fn verification_json(result: Result<(), ProofVerificationError>) -> Value {
+    match result {
+        Ok(()) => json!({ "valid": true }),
+        Err(e) => json!({ "valid": false, "error": e.to_string() }),
+    }
+}
+
+/// Scan non-empty storage with `debug_storageRangeAt`, at the state at the end of `block`.
+///
+/// The node reads the state before transaction `index` of the block it is given, and rejects an
+/// index past the last transaction, so the end of `block` is read as the state before the first
+/// transaction of its child. A block without a child yet, the chain head, is read as the end of
+/// its parent instead; `block_hash` reports the block whose end state was scanned.
+async fn storage_range(
+    provider: &RetryProvider,
+    address: Address,
+    block: BlockId,
+    start: U256,
+    max: usize,
+) -> Result<StorageOutput, ErrorData> {
+    let block = get_block(provider, block, false).await?;
+    let child = provider
+        .get_block_by_number((block.header.number + 1).into())
+        .await
+        .map_err(|e| rpc_error("Failed to get block", &e))?
+        .unwrap_or(block);
+    let scanned = child.header.parent_hash;
+
+    let range: Value = provider
+        .raw_request(
+            "debug_storageRangeAt".into(),
+            (child.header.hash, 0, address, B256::from(start), max),
+        )
+        .await
+        .map_err(|e| rpc_error("Failed to get storage range with debug_storageRangeAt", &e))?;
+
+    // Entries are keyed by the hashed slot; the slot itself is only known if the node kept its
+    // preimage.
+    let entries = range["storage"].as_object().cloned().unwrap_or_default();
+    let slots = entries
+        .into_iter()
+        .filter_map(|(hashed, entry)| {
+            let value: B256 = entry["value"].as_str()?.parse().ok()?;
+            let slot = entry["key"].as_str().and_then(|key| key.parse().ok());
+            Some(SlotValue {
+                hashed_slot: Some(hashed),
+                ..slot_value(slot, value)
+            })
+        })
+        .collect();
+    Ok(StorageOutput {
+        address: address.to_checksum(None),
+        block_hash: Some(scanned.to_string()),
+        slots,
+        next_key: Some(range["nextKey"].clone()),
+        proof: None,
+    })
+}
 
-        Ok(CallToolResult::success(vec![Content::text(
-            byte_size.to_string(),
-        )]))
+/// Read a storage slot and interpret it as an address. Empty slots, and slots holding more than
+/// 20 bytes, are `None`.
+async fn storage_address(
+    provider: &RetryProvider,
+    address: Address,
+    slot: B256,
+    block: BlockId,
+) -> Result<Option<Address>, ErrorData> {
+    let value = Cast::new(provider.clone())
+        .storage(address, slot, Some(block))
+        .await
+        .map_err(|e| upstream_error("Failed to get storage", &*e))?;
+    let value =
+        hex::decode(&value).map_err(|e| internal_error("Invalid storage value returned", e))?;
+    Ok(word_address(&value))
+}
+
+/// Interpret a 32-byte word as a non-zero, left-padded address.
+fn word_address(word: &[u8]) -> Option<Address> {
+    if word.len() != 32 || word[..12].iter().any(|b| *b != 0) {
+        return None;
     }
+    let address = Address::from_slice(&word[12..]);
+    (!address.is_zero()).then_some(address)
+}
+
+/// Call the address getter `signature` on `to`, as a proxy probe: a call that fails, or returns
+/// something other than a non-zero address, is `None`.
+async fn call_address(
+    provider: &RetryProvider,
+    to: Address,
+    signature: &str,
+    block: BlockId,
+) -> Option<Address> {
+    let address = call_function(provider, to, signature, &[], block)
+        .await
+        .ok()?;
+    let address = address.as_str()?.parse::<Address>().ok()?;
+    (!address.is_zero()).then_some(address)
 }
 
-fn format_balance(balance: U256) -> String {
-    // Convert wei to ether (1 ether = 10^18 wei)
-    let ether = balance.to::<u128>() as f64 / 1_000_000_000_000_000_000.0;
-    format!("{:.18}", ether)
+/// Decode the `(address, bytes4[])[]` an EIP-2535 diamond's `facets()` returns.
+fn decode_facets(data: &[u8]) -> Vec<Facet> {
+    let Ok(func) = abi::parse_function(FACETS) else {
+        return Vec::new();
+    };
+    let Ok(facets) = first_output(&func, data) else {
+        return Vec::new();
+    };
+    facets
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|facet| {
+            Some(Facet {
+                address: facet[0].as_str()?.to_string(),
+                selectors: facet[1]
+                    .as_array()?
+                    .iter()
+                    .filter_map(|selector| Some(selector.as_str()?.to_string()))
+                    .collect(),
+            })
+        })
+        .collect()
 }
 
 #[cfg(test)]
 mod tests {
     use std::sync::Arc;
 
+    use alloy_rpc_types::EIP1186StorageProof;
     use rmcp::handler::server::wrapper::Parameters;
 
     use super::*;
@@ -367,41 +1516,359 @@ mod tests {
     #[test]
     fn test_balance_args_default() {
         let args = BalanceArgs::default();
-        assert_eq!(args.rpc, "http://localhost:8545");
+        assert_eq!(args.endpoint.rpc, None);
         assert_eq!(args.block, None);
+        assert_eq!(args.unit, None);
         assert_eq!(args.ether, false);
     }
 
     #[test]
     fn test_nonce_args_default() {
         let args = NonceArgs::default();
-        assert_eq!(args.rpc, "http://localhost:8545");
+        assert_eq!(args.endpoint.rpc, None);
         assert_eq!(args.block, None);
     }
 
     #[test]
     fn test_code_args_default() {
         let args = CodeArgs::default();
-        assert_eq!(args.rpc, "http://localhost:8545");
+        assert_eq!(args.endpoint.rpc, None);
         assert_eq!(args.block, None);
         assert_eq!(args.disassemble, false);
+        assert_eq!(args.analyze, false);
     }
 
     #[test]
     fn test_storage_args_default() {
         let args = StorageArgs::default();
-        assert_eq!(args.rpc, "http://localhost:8545");
+        assert_eq!(args.endpoint.rpc, None);
         assert_eq!(args.block, None);
         assert_eq!(args.proof, false);
+        assert_eq!(args.verify_proof, false);
+        assert_eq!(args.slot, None);
+        assert!(args.slots.is_empty());
+        assert_eq!(args.range, false);
     }
 
     #[test]
     fn test_code_size_args_default() {
         let args = CodeSizeArgs::default();
-        assert_eq!(args.rpc, "http://localhost:8545");
+        assert_eq!(args.endpoint.rpc, None);
         assert_eq!(args.block, None);
     }
 
+    #[test]
+    fn test_lookup_address_args_default() {
+        let args = LookupAddressArgs::default();
+        assert_eq!(args.endpoint.rpc, None);
+        assert_eq!(args.verify, false);
+    }
+
+    #[tokio::test]
+    async fn test_invalid_checksum_is_rejected() {
+        let server = Server::new();
+        let args = BalanceArgs {
+            endpoint: EndpointArgs::from_rpc("http://localhost:8545"),
+            block: None,
+            who: "0xd8DA6BF26964aF9D7eEd9e03E53415D37aA96045".to_string(),
+            unit: None,
+            ether: false,
+            tokens: vec![],
+        };
+
+        let error = server.balance(Parameters(args)).await.unwrap_err();
+        assert_eq!(error.code, ErrorCode::INVALID_PARAMS);
+        assert_eq!(error.data.unwrap()["reason"], "invalid_checksum");
+    }
+
+    #[tokio::test]
+    async fn test_invalid_unit_is_rejected() {
+        let args = |unit: Value| {
+            serde_json::from_value::<BalanceArgs>(json!({
+                "rpc": "http://localhost:8545",
+                "who": "0xd8dA6BF26964aF9D7eEd9e03E53415D37aA96045",
+                "unit": unit,
+            }))
+        };
+        assert_eq!(
+            args(json!("gwei")).unwrap().unit,
+            Some(UnitSpec::Named(UnitName::Gwei))
+        );
+        assert!(args(json!("bitcoin")).is_err());
+
+        let server = Server::new();
+        let error = server
+            .balance(Parameters(args(json!(78)).unwrap()))
+            .await
+            .unwrap_err();
+        assert_eq!(error.code, ErrorCode::INVALID_PARAMS);
+        assert!(error.message.contains("78"));
+    }
+
+    #[test]
+    fn test_call_args_default() {
+        let args = CallArgs::default();
+        assert_eq!(args.sig, None);
+        assert!(args.args.is_empty());
+        assert_eq!(args.data, None);
+        assert_eq!(args.from, None);
+        assert_eq!(args.value, None);
+    }
+
+    #[tokio::test]
+    async fn test_call_requires_sig_or_data() {
+        let server = Server::new();
+        let args = CallArgs {
+            endpoint: EndpointArgs::from_rpc("http://localhost:8545"),
+            to: "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48".to_string(),
+            ..Default::default()
+        };
+
+        let error = server.call(Parameters(args)).await.unwrap_err();
+        assert_eq!(error.code, ErrorCode::INVALID_PARAMS);
+        assert_eq!(error.data.unwrap()["reason"], "missing");
+    }
+
+    #[tokio::test]
+    async fn test_call_rejects_data_with_args() {
+        let server = Server::new();
+        let args = CallArgs {
+            endpoint: EndpointArgs::from_rpc("http://localhost:8545"),
+            to: "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48".to_string(),
+            data: Some("0x18160ddd".to_string()),
+            args: vec!["1".to_string()],
+            ..Default::default()
+        };
+
+        let error = server.call(Parameters(args)).await.unwrap_err();
+        assert_eq!(error.data.unwrap()["reason"], "ambiguous");
+    }
+
+    #[tokio::test]
+    async fn test_call_checks_argument_count() {
+        let server = Server::new();
+        let args = CallArgs {
+            endpoint: EndpointArgs::from_rpc("http://localhost:8545"),
+            to: "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48".to_string(),
+            sig: Some("balanceOf(address)(uint256)".to_string()),
+            ..Default::default()
+        };
+
+        let error = server.call(Parameters(args)).await.unwrap_err();
+        assert_eq!(error.code, ErrorCode::INVALID_PARAMS);
+        assert_eq!(error.data.unwrap()["reason"], "argument_count");
+    }
+
+    #[test]
+    fn test_parse_slot() {
+        assert_eq!(parse_slot("5").unwrap(), U256::from(5));
+        assert_eq!(parse_slot("0x0a").unwrap(), U256::from(10));
+        let key = "0x360894a13ba1a3210667c828492db98dca3e2076cc3735a920a3ca505d382bbc";
+        assert_eq!(B256::from(parse_slot(key).unwrap()).to_string(), key);
+        assert!(parse_slot("slot").is_err());
+
+        assert!(consecutive_slots(U256::MAX, 2).is_err());
+        assert_eq!(
+            consecutive_slots(U256::from(3), 2).unwrap(),
+            vec![U256::from(3), U256::from(4)]
+        );
+        assert!(check_slot_count(0).is_err());
+        assert!(check_slot_count(257).is_err());
+    }
+
+    #[test]
+    fn test_slot_value() {
+        let owner = Address::repeat_byte(0x11);
+        let slot = slot_value(Some(B256::ZERO), owner.into_word());
+        assert_eq!(slot.decoded.address, Some(owner.to_checksum(None)));
+        assert_eq!(slot.decoded.bool, None);
+
+        let slot = slot_value(Some(B256::ZERO), B256::with_last_byte(1));
+        assert_eq!(slot.decoded.uint, "1");
+        assert_eq!(slot.decoded.bool, Some(true));
+        assert_eq!(slot.decoded.string, None);
+
+        // "USDC" stored in place: the bytes followed by twice the length.
+        let mut word = [0u8; 32];
+        word[..4].copy_from_slice(b"USDC");
+        word[31] = 8;
+        let slot = slot_value(None, B256::from(word));
+        assert_eq!(slot.slot, None);
+        assert_eq!(slot.decoded.string.as_deref(), Some("USDC"));
+    }
+
+    #[tokio::test]
+    async fn test_storage_checks_slot_arguments() {
+        let server = Server::new();
+        let args = StorageArgs {
+            endpoint: EndpointArgs::from_rpc("http://localhost:8545"),
+            address: "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48".to_string(),
+            ..Default::default()
+        };
+        let error = server.storage(Parameters(args.clone())).await.unwrap_err();
+        assert_eq!(error.data.unwrap()["reason"], "missing");
+
+        let error = server
+            .storage(Parameters(StorageArgs {
+                slot: Some("0".to_string()),
+                slots: vec!["1".to_string()],
+                ..args.clone()
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(error.data.unwrap()["reason"], "ambiguous");
+
+        let error = server
+            .storage(Parameters(StorageArgs {
+                count: Some(1000),
+                ..args
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(error.code, ErrorCode::INVALID_PARAMS);
+    }
+
+    #[tokio::test]
+    async fn test_storage_range_rejects_proof() {
+        let server = Server::new();
+        let args = StorageArgs {
+            endpoint: EndpointArgs::from_rpc("http://localhost:8545"),
+            address: "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48".to_string(),
+            range: true,
+            proof: true,
+            ..Default::default()
+        };
+        let error = server.storage(Parameters(args)).await.unwrap_err();
+        assert_eq!(error.data.unwrap()["reason"], "ambiguous");
+    }
+
+    #[test]
+    fn test_verify_account_proof() {
+        // An account that does not exist is proven absent from an empty state trie.
+        let mut proof = EIP1186AccountProofResponse {
+            address: Address::repeat_byte(0x11),
+            balance: U256::ZERO,
+            code_hash: KECCAK_EMPTY,
+            nonce: 0,
+            storage_hash: EMPTY_ROOT_HASH,
+            account_proof: vec![],
+            storage_proof: vec![],
+        };
+        let verification = verify_account_proof(EMPTY_ROOT_HASH, &proof);
+        assert_eq!(verification["valid"], true);
+
+        // A proof that claims the account exists must not verify against the same root.
+        proof.nonce = 1;
+        let verification = verify_account_proof(EMPTY_ROOT_HASH, &proof);
+        assert_eq!(verification["valid"], false);
+        assert!(verification["account"]["error"].is_string());
+    }
+
+    #[test]
+    fn test_check_proof_matches() {
+        let address = Address::repeat_byte(0x11);
+        let mut proof = EIP1186AccountProofResponse {
+            address,
+            balance: U256::ZERO,
+            code_hash: KECCAK_EMPTY,
+            nonce: 0,
+            storage_hash: EMPTY_ROOT_HASH,
+            account_proof: vec![],
+            storage_proof: vec![EIP1186StorageProof {
+                key: B256::with_last_byte(1).into(),
+                value: U256::ZERO,
+                proof: vec![],
+            }],
+        };
+        check_proof_matches(&proof, address, &[U256::from(1)]).unwrap();
+
+        let reason = |error: ErrorData| error.data.unwrap()["reason"].clone();
+        let error = check_proof_matches(&proof, address, &[U256::from(2)]).unwrap_err();
+        assert_eq!(reason(error), "proof_mismatch");
+        let error = check_proof_matches(&proof, address, &[]).unwrap_err();
+        assert_eq!(reason(error), "proof_mismatch");
+        proof.address = Address::repeat_byte(0x22);
+        let error = check_proof_matches(&proof, address, &[U256::from(1)]).unwrap_err();
+        assert_eq!(reason(error), "proof_mismatch");
+    }
+
+    #[test]
+    fn test_word_address() {
+        let mut word = [0u8; 32];
+        assert_eq!(word_address(&word), None);
+        word[31] = 1;
+        assert_eq!(word_address(&word), Some(Address::with_last_byte(1)));
+        word[0] = 1;
+        assert_eq!(word_address(&word), None);
+        assert_eq!(word_address(&[1; 20]), None);
+    }
+
+    #[test]
+    fn test_decode_facets() {
+        let facet = Address::repeat_byte(0x11);
+        let selectors = DynSolValue::Array(vec![DynSolValue::FixedBytes(
+            B256::right_padding_from(&[0xa9, 0x05, 0x9c, 0xbb]),
+            4,
+        )]);
+        let facets = DynSolValue::Array(vec![DynSolValue::Tuple(vec![
+            DynSolValue::Address(facet),
+            selectors,
+        ])]);
+        let data = DynSolValue::Tuple(vec![facets]).abi_encode_params();
+
+        let facets = decode_facets(&data);
+        assert_eq!(facets.len(), 1);
+        assert_eq!(facets[0].address, facet.to_checksum(None));
+        assert_eq!(facets[0].selectors, ["0xa9059cbb"]);
+        assert!(decode_facets(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_proxy_hop_json() {
+        let hop = ProxyHop {
+            address: Address::repeat_byte(0x11),
+            kind: ProxyKind::Eip1967Beacon,
+            implementation: Some(Address::repeat_byte(0x22)),
+            admin: None,
+            beacon: None,
+            facets: Vec::new(),
+        };
+        let json = serde_json::to_value(&hop).unwrap();
+        assert_eq!(json["kind"], "eip1967_beacon");
+        assert_eq!(
+            json["implementation"],
+            Address::repeat_byte(0x22).to_checksum(None)
+        );
+        assert!(json.get("admin").is_none());
+        assert!(json.get("facets").is_none());
+
+        // The schema lists the patterns.
+        let schema = serde_json::to_string(&schemars::schema_for!(ProxyHop)).unwrap();
+        assert!(schema.contains("\"eip1967_beacon\""));
+    }
+
+    #[tokio::test]
+    async fn test_proxy_info_rejects_invalid_address() {
+        let server = Server::new();
+        let args = ProxyInfoArgs {
+            endpoint: EndpointArgs::from_rpc("http://localhost:8545"),
+            block: None,
+            address: "0x1234".to_string(),
+        };
+
+        let error = server.proxy_info(Parameters(args)).await.unwrap_err();
+        assert_eq!(error.code, ErrorCode::INVALID_PARAMS);
+    }
+
+    #[tokio::test]
+    async fn test_code_requires_address_or_name() {
+        let server = Server::new();
+        let args = CodeArgs::default();
+
+        let error = server.code(Parameters(args)).await.unwrap_err();
+        assert_eq!(error.data.unwrap()["reason"], "missing");
+    }
+
     #[tokio::test]
     async fn test_account_tools_response_structure() {
         let server = Server::new();
@@ -411,10 +1878,12 @@ mod tests {
 
         // Test balance tool
         let balance_args = BalanceArgs {
-            rpc: "https://1rpc.io/eth".to_string(),
-            block: Some("latest".to_string()),
+            endpoint: EndpointArgs::from_rpc("https://1rpc.io/eth"),
+            block: Some("latest".into()),
             who: test_address.to_string(),
+            unit: None,
             ether: true,
+            tokens: vec![],
         };
         let balance_params = Parameters(balance_args);
 
@@ -449,8 +1918,8 @@ mod tests {
 
         // Test nonce tool
         let nonce_args = NonceArgs {
-            rpc: "https://1rpc.io/eth".to_string(),
-            block: Some("latest".to_string()),
+            endpoint: EndpointArgs::from_rpc("https://1rpc.io/eth"),
+            block: Some("latest".into()),
             who: test_address.to_string(),
         };
         let nonce_params = Parameters(nonce_args);
@@ -487,11 +1956,12 @@ mod tests {
 
         // Test code tool
         let code_args = CodeArgs {
-            rpc: "https://1rpc.io/eth".to_string(),
-            block: Some("latest".to_string()),
+            endpoint: EndpointArgs::from_rpc("https://1rpc.io/eth"),
+            block: Some("latest".into()),
             address: Some(test_address.to_string()),
             name: None,
             disassemble: false,
+            analyze: false,
         };
         let code_params = Parameters(code_args);
 
@@ -526,11 +1996,12 @@ mod tests {
 
         // Test storage tool (slot 0 is often used)
         let storage_args = StorageArgs {
-            rpc: "https://1rpc.io/eth".to_string(),
-            block: Some("latest".to_string()),
+            endpoint: EndpointArgs::from_rpc("https://1rpc.io/eth"),
+            block: Some("latest".into()),
             address: test_address.to_string(),
-            slot: "0x0".to_string(),
+            slot: Some("0x0".to_string()),
             proof: false,
+            ..Default::default()
         };
         let storage_params = Parameters(storage_args);
 
@@ -565,8 +2036,8 @@ mod tests {
 
         // Test code_size tool
         let code_size_args = CodeSizeArgs {
-            rpc: "https://1rpc.io/eth".to_string(),
-            block: Some("latest".to_string()),
+            endpoint: EndpointArgs::from_rpc("https://1rpc.io/eth"),
+            block: Some("latest".into()),
             address: Some(test_address.to_string()),
             name: None,
         };
@@ -604,8 +2075,8 @@ mod tests {
 
         // Test code_size tool
         let code_size_args = CodeSizeArgs {
-            rpc: "https://1rpc.io/eth".to_string(),
-            block: Some("latest".to_string()),
+            endpoint: EndpointArgs::from_rpc("https://1rpc.io/eth"),
+            block: Some("latest".into()),
             address: Some(test_address.to_string()),
             name: None,
         };
@@ -656,38 +2127,42 @@ mod tests {
                     match i % 4 {
                         0 => {
                             let args = BalanceArgs {
-                                rpc: "https://1rpc.io/eth".to_string(),
-                                block: Some("latest".to_string()),
+                                endpoint: EndpointArgs::from_rpc("https://1rpc.io/eth"),
+                                block: Some("latest".into()),
                                 who: addr,
+                                unit: None,
                                 ether: true,
+                                tokens: vec![],
                             };
                             server_clone.balance(Parameters(args)).await
                         }
                         1 => {
                             let args = NonceArgs {
-                                rpc: "https://1rpc.io/eth".to_string(),
-                                block: Some("latest".to_string()),
+                                endpoint: EndpointArgs::from_rpc("https://1rpc.io/eth"),
+                                block: Some("latest".into()),
                                 who: addr,
                             };
                             server_clone.nonce(Parameters(args)).await
                         }
                         2 => {
                             let args = CodeArgs {
-                                rpc: "https://1rpc.io/eth".to_string(),
-                                block: Some("latest".to_string()),
+                                endpoint: EndpointArgs::from_rpc("https://1rpc.io/eth"),
+                                block: Some("latest".into()),
                                 address: Some(addr),
                                 name: None,
                                 disassemble: false,
+                                analyze: false,
                             };
                             server_clone.code(Parameters(args)).await
                         }
                         3 => {
                             let args = StorageArgs {
-                                rpc: "https://1rpc.io/eth".to_string(),
-                                block: Some("latest".to_string()),
+                                endpoint: EndpointArgs::from_rpc("https://1rpc.io/eth"),
+                                block: Some("latest".into()),
                                 address: addr,
-                                slot: "0x0".to_string(),
+                                slot: Some("0x0".to_string()),
                                 proof: false,
+                                ..Default::default()
                             };
                             server_clone.storage(Parameters(args)).await
                         }
@@ -714,32 +2189,60 @@ mod tests {
     }
 
     #[test]
-    fn test_balance_formatting() {
-        // Test wei to ether conversion
-        let balance_wei = U256::from(1_000_000_000_000_000_000u128); // 1 ETH in wei
-        let formatted = format_balance(balance_wei);
-        assert!(formatted.contains("1."), "Should show 1.x ETH");
+    fn test_decode_token_symbol() {
+        // ABI encoded "USDC"
+        let mut data = vec![0u8; 96];
+        data[31] = 0x20;
+        data[63] = 4;
+        data[64..68].copy_from_slice(b"USDC");
+        assert_eq!(decode_symbol(&data).as_deref(), Some("USDC"));
+
+        // bytes32 encoded "MKR"
+        let mut data = vec![0u8; 32];
+        data[..3].copy_from_slice(b"MKR");
+        assert_eq!(decode_symbol(&data).as_deref(), Some("MKR"));
+
+        assert_eq!(decode_symbol(&[1, 2, 3]), None);
+    }
 
-        let small_balance = U256::from(1_000_000u128); // Very small amount
-        let small_formatted = format_balance(small_balance);
-        assert!(!small_formatted.is_empty(), "Should format small balances");
+    #[test]
+    fn test_first_output() {
+        let mut data = vec![0u8; 32];
+        data[31] = 18;
+        let func = abi::parse_function(DECIMALS).unwrap();
+        assert_eq!(first_output(&func, &data).unwrap(), "18");
+        assert!(first_output(&func, &[]).is_err());
+    }
+
+    #[test]
+    fn test_token_error_is_classified() {
+        let error = TokenError::from(tool_error(
+            ErrorKind::RateLimited,
+            "Failed to call balanceOf(address)",
+            Some(json!({ "detail": "https://eth.example/v2/secret-key" })),
+        ));
+        assert_eq!(error.kind, ErrorKind::RateLimited);
+        assert!(error.retryable);
+        assert!(!error.message.contains("secret"));
     }
 
     #[test]
     fn test_args_debug_format() {
         let balance_args = BalanceArgs {
-            rpc: "test-rpc".to_string(),
-            block: Some("latest".to_string()),
+            endpoint: EndpointArgs::from_rpc("test-rpc"),
+            block: Some("latest".into()),
             who: "0x1234".to_string(),
+            unit: None,
             ether: true,
+            tokens: vec![],
         };
         let debug_output = format!("{:?}", balance_args);
         assert!(debug_output.contains("BalanceArgs"));
         assert!(debug_output.contains("test-rpc"));
 
         let nonce_args = NonceArgs {
-            rpc: "test-rpc".to_string(),
-            block: Some("pending".to_string()),
+            endpoint: EndpointArgs::from_rpc("test-rpc"),
+            block: Some("pending".into()),
             who: "0x5678".to_string(),
         };
         let nonce_debug = format!("{:?}", nonce_args);
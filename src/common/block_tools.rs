@@ -1,29 +1,67 @@
+use std::time::Duration;
+
+use alloy_json_abi::Event;
+use alloy_primitives::{B256, Bytes};
+use alloy_provider::{
+    Provider,
+    network::{AnyNetwork, Network, ReceiptResponse},
+};
+use alloy_rpc_types::{BlockNumberOrTag, Filter, Log, Topic};
+use alloy_serde::WithOtherFields;
 use cast::Cast;
-use foundry_cli::{opts::RpcOpts, utils, utils::LoadConfig};
 use rmcp::{
     ErrorData, handler::server::wrapper::Parameters, model::*, schemars, tool, tool_router,
 };
 use serde_default::DefaultFromSerde;
-use serde_json::Value;
+use serde_json::{Map, Value, json};
+
+use crate::common::{
+    abi,
+    account_tools::{CallArgs, call_request},
+    common::*,
+    error::*,
+    provider::RetryProvider,
+    server::Server,
+};
+
+/// How long `receipt` waits for confirmations when no timeout is given.
+const DEFAULT_RECEIPT_TIMEOUT_SECS: u64 = 120;
+
+/// How often `receipt` polls the node while waiting for confirmations.
+const RECEIPT_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Blocks `fee_history` covers when no block count is given.
+const DEFAULT_FEE_HISTORY_BLOCKS: u64 = 10;
+
+/// The most blocks nodes return fee history for in one request.
+const MAX_FEE_HISTORY_BLOCKS: u64 = 1024;
+
+/// Priority fee percentiles `fee_history` returns when none are given.
+const DEFAULT_REWARD_PERCENTILES: [f64; 3] = [10.0, 50.0, 90.0];
+
+/// Blocks requested per `eth_getLogs` call when no chunk size is given.
+const DEFAULT_LOG_CHUNK_SIZE: u64 = 2_000;
+
+/// Logs returned per page when no limit is given.
+const DEFAULT_LOG_LIMIT: usize = 100;
 
-use crate::common::{common::*, server::Server};
+/// Chunks scanned by one `logs` call before it returns a cursor, so sparse filters over long
+/// ranges still answer promptly.
+const MAX_LOG_CHUNKS_PER_CALL: usize = 50;
 
 #[derive(Debug, Clone, serde::Deserialize, DefaultFromSerde, schemars::JsonSchema)]
 pub struct AgeArgs {
-    /// The RPC endpoint, default value is http://localhost:8545.
-    #[serde(default = "default_rpc")]
-    pub rpc: String,
+    #[serde(flatten)]
+    pub endpoint: EndpointArgs,
 
-    /// The block height to query at. Can also be the tags earliest, finalized, safe, latest, pending or block hash.
     #[serde(default)]
-    block: Option<String>,
+    block: Option<BlockParam>,
 }
 
 #[derive(Debug, Clone, serde::Deserialize, DefaultFromSerde, schemars::JsonSchema)]
 pub struct BlockArgs {
-    /// The RPC endpoint, default value is http://localhost:8545.
-    #[serde(default = "default_rpc")]
-    pub rpc: String,
+    #[serde(flatten)]
+    pub endpoint: EndpointArgs,
 
     /// If specified, only get the given field of the block.
     fields: Vec<String>,
@@ -34,184 +72,892 @@ pub struct BlockArgs {
     /// If true, get all fields.
     full: bool,
 
-    /// The block height to query at. Can also be the tags earliest, finalized, safe, latest, pending or block hash.
     #[serde(default)]
-    block: Option<String>,
+    block: Option<BlockParam>,
 }
 
 #[derive(Debug, Clone, serde::Deserialize, DefaultFromSerde, schemars::JsonSchema)]
 pub struct BlockNumberArgs {
-    /// The RPC endpoint, default value is http://localhost:8545.
-    #[serde(default = "default_rpc")]
-    pub rpc: String,
+    #[serde(flatten)]
+    pub endpoint: EndpointArgs,
 
-    /// The block height to query at. Can also be the tags earliest, finalized, safe, latest, pending or block hash.
     #[serde(default)]
-    pub block: Option<String>,
+    pub block: Option<BlockParam>,
 }
 
 #[derive(Debug, Clone, serde::Deserialize, DefaultFromSerde, schemars::JsonSchema)]
 pub struct GasPriceArgs {
-    /// The RPC endpoint, default value is http://localhost:8545.
-    #[serde(default = "default_rpc")]
-    pub rpc: String,
+    #[serde(flatten)]
+    pub endpoint: EndpointArgs,
+}
+
+#[derive(Debug, Clone, serde::Deserialize, DefaultFromSerde, schemars::JsonSchema)]
+pub struct FeeHistoryArgs {
+    #[serde(flatten)]
+    pub endpoint: EndpointArgs,
+
+    /// The number of blocks to return fees for, ending at `newest_block`. Defaults to 10, at most 1024.
+    #[serde(default)]
+    pub block_count: Option<u64>,
+
+    /// The newest block of the range, in any format the `block` parameter of other tools accepts. Defaults to latest.
+    #[serde(default)]
+    pub newest_block: Option<BlockParam>,
+
+    /// Percentiles (0 to 100, ascending) of the priority fees paid in each block to return. Defaults to [10, 50, 90].
+    #[serde(default)]
+    pub reward_percentiles: Option<Vec<f64>>,
+}
+
+#[derive(Debug, Clone, serde::Deserialize, DefaultFromSerde, schemars::JsonSchema)]
+pub struct TxArgs {
+    #[serde(flatten)]
+    pub endpoint: EndpointArgs,
+
+    /// The transaction hash.
+    pub hash: String,
+
+    /// If specified, only get the given fields of the transaction, e.g. from, to, value or input.
+    #[serde(default)]
+    pub fields: Vec<String>,
+
+    /// Return the raw RLP encoded transaction.
+    #[serde(default)]
+    pub raw: bool,
+}
+
+#[derive(Debug, Clone, serde::Deserialize, DefaultFromSerde, schemars::JsonSchema)]
+pub struct ReceiptArgs {
+    #[serde(flatten)]
+    pub endpoint: EndpointArgs,
+
+    /// The transaction hash.
+    pub hash: String,
+
+    /// If specified, only get the given fields of the receipt, e.g. status, gasUsed or logs.
+    #[serde(default)]
+    pub fields: Vec<String>,
+
+    /// Wait until the transaction has this many confirmations. 0 returns immediately, 1 waits until the transaction is mined.
+    #[serde(default)]
+    pub confirmations: u64,
+
+    /// How long to wait for confirmations, in seconds. Defaults to 120.
+    #[serde(default)]
+    pub timeout: Option<u64>,
+
+    /// Extra event signatures to decode logs with, e.g. Transfer(address indexed from, address indexed to, uint256 value). Common ERC-20, ERC-721, ERC-1155, WETH, Uniswap and proxy events are decoded without being listed.
+    #[serde(default)]
+    pub events: Vec<String>,
+}
+
+#[derive(Debug, Clone, serde::Deserialize, DefaultFromSerde, schemars::JsonSchema)]
+pub struct LogsArgs {
+    #[serde(flatten)]
+    pub endpoint: EndpointArgs,
+
+    /// Contract addresses or ENS names to get logs from. Empty matches any address.
+    #[serde(default)]
+    pub address: Vec<String>,
+
+    /// An event signature such as Transfer(address indexed from, address indexed to, uint256 value). Filters on the event's topic0 and decodes the matching logs.
+    #[serde(default)]
+    pub sig: Option<String>,
+
+    /// Topic filters. With `sig`, values for the event's indexed parameters in order; otherwise raw 32-byte topics starting at topic0. Use "" or "*" to match anything and separate alternatives with commas.
+    #[serde(default)]
+    pub topics: Vec<String>,
+
+    /// The first block to search, in any format the `block` parameter of other tools accepts. Defaults to latest.
+    #[serde(default)]
+    pub from_block: Option<BlockParam>,
+
+    /// The last block to search, in any format the `block` parameter of other tools accepts. Defaults to latest.
+    #[serde(default)]
+    pub to_block: Option<BlockParam>,
+
+    /// Blocks requested per eth_getLogs call. Halved whenever the provider rejects a range as too large or matching too many logs. Defaults to 2000.
+    #[serde(default)]
+    pub chunk_size: Option<u64>,
+
+    /// The maximum number of logs to return. Defaults to 100.
+    #[serde(default)]
+    pub limit: Option<usize>,
+
+    /// The `next_cursor` of a previous call with the same filter, to continue where it stopped.
+    #[serde(default)]
+    pub cursor: Option<String>,
+}
+
+#[derive(Debug, serde::Serialize, schemars::JsonSchema)]
+pub struct AgeOutput {
+    /// The block number.
+    pub number: u64,
+
+    /// The block timestamp in unix seconds.
+    pub timestamp: u64,
+
+    /// The block timestamp as an RFC 3339 date in UTC.
+    pub date: String,
+}
+
+#[derive(Debug, serde::Serialize, schemars::JsonSchema)]
+pub struct BlockNumberOutput {
+    /// The block number.
+    pub number: u64,
+}
+
+#[derive(Debug, serde::Serialize, schemars::JsonSchema)]
+pub struct GasPriceOutput {
+    /// The gas price in wei.
+    pub gas_price: String,
+}
+
+#[derive(Debug, serde::Serialize, schemars::JsonSchema)]
+pub struct EstimateOutput {
+    /// The estimated gas.
+    pub gas: u64,
+}
+
+#[derive(Debug, serde::Serialize, schemars::JsonSchema)]
+pub struct FeeHistoryOutput {
+    /// The first block of the range.
+    pub oldest_block: u64,
+
+    /// The base fee of each block in wei, plus the next block's.
+    pub base_fee_per_gas: Vec<String>,
+
+    /// The fraction of each block's gas limit that was used.
+    pub gas_used_ratio: Vec<f64>,
+
+    /// The blob base fee of each block in wei, plus the next block's. Empty before Cancun.
+    pub base_fee_per_blob_gas: Vec<String>,
+
+    /// The fraction of each block's blob gas limit that was used. Empty before Cancun.
+    pub blob_gas_used_ratio: Vec<f64>,
+
+    /// The percentiles the rewards are given at.
+    pub reward_percentiles: Vec<f64>,
+
+    /// The priority fees in wei paid in each block at each percentile.
+    pub reward: Vec<Vec<String>>,
+
+    /// The base fee of the next block in wei.
+    pub next_base_fee_per_gas: String,
+
+    /// The blob base fee of the next block in wei, if the chain has blobs.
+    pub next_base_fee_per_blob_gas: Option<String>,
+
+    /// Suggested EIP-1559 fees for a transaction sent now.
+    pub recommended: RecommendedFees,
+}
+
+#[derive(Debug, serde::Serialize, schemars::JsonSchema)]
+pub struct RecommendedFees {
+    /// The suggested maxFeePerGas in wei.
+    pub max_fee_per_gas: String,
+
+    /// The suggested maxPriorityFeePerGas in wei.
+    pub max_priority_fee_per_gas: String,
+}
+
+/// A block, transaction or receipt as the node returns it, or only the requested fields of it.
+#[derive(Debug, Default, serde::Serialize, schemars::JsonSchema)]
+pub struct RpcObjectOutput {
+    /// The raw RLP encoding, when it was requested.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub raw: Option<String>,
+
+    /// The fields of the object, keyed by their camelCase RPC names.
+    #[serde(flatten)]
+    pub fields: Map<String, Value>,
+}
+
+/// A log, with the fields eth_getLogs returns under their camelCase RPC names.
+#[derive(Debug, serde::Serialize, schemars::JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct LogEntry {
+    /// The contract that emitted the log.
+    pub address: String,
+
+    /// The topics, the event signature hash first unless the event is anonymous.
+    pub topics: Vec<String>,
+
+    /// The non-indexed data.
+    pub data: String,
+
+    /// The block the log is in. Null for logs of pending transactions, as are the fields below.
+    pub block_number: Option<u64>,
+
+    pub block_hash: Option<String>,
+
+    /// The block timestamp, when the node includes it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub block_timestamp: Option<u64>,
+
+    pub transaction_hash: Option<String>,
+
+    /// The position of the transaction in the block.
+    pub transaction_index: Option<u64>,
+
+    /// The position of the log in the block.
+    pub log_index: Option<u64>,
+
+    /// Whether the log was removed by a reorg.
+    pub removed: bool,
+
+    /// The log decoded as a known event.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub decoded: Option<abi::DecodedLog>,
+}
+
+impl LogEntry {
+    fn new(log: &Log, events: &[Event]) -> Self {
+        Self {
+            address: log.address().to_checksum(None),
+            topics: log.topics().iter().map(ToString::to_string).collect(),
+            data: log.data().data.to_string(),
+            block_number: log.block_number,
+            block_hash: log.block_hash.map(|hash| hash.to_string()),
+            block_timestamp: log.block_timestamp,
+            transaction_hash: log.transaction_hash.map(|hash| hash.to_string()),
+            transaction_index: log.transaction_index,
+            log_index: log.log_index,
+            removed: log.removed,
+            decoded: abi::decode_log(events, log.topics(), &log.data().data),
+        }
+    }
+}
+
+#[derive(Debug, serde::Serialize, schemars::JsonSchema)]
+pub struct LogsOutput {
+    /// The first block of the searched range.
+    pub from_block: u64,
+
+    /// The last block of the searched range.
+    pub to_block: u64,
+
+    /// The matching logs, with a `decoded` field for logs of known events.
+    pub logs: Vec<LogEntry>,
+
+    /// Pass as `cursor` to continue after the last returned log. Absent once the range is exhausted.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
 }
 
 #[tool_router(router = block_router, vis = "pub")]
 impl Server {
-    #[tool(description = "Get the timestamp of a block. ")]
+    #[tool(
+        description = "Get the timestamp of a block. ",
+        output_schema = output_schema::<AgeOutput>()
+    )]
     async fn age(
         &self,
         Parameters(args): Parameters<AgeArgs>,
     ) -> Result<CallToolResult, ErrorData> {
-        let rpc = RpcOpts {
-            url: Some(args.rpc.clone()),
-            accept_invalid_certs: false,
-            no_proxy: false,
-            flashbots: false,
-            jwt_secret: None,
-            rpc_timeout: None,
-            rpc_headers: None,
-            curl: false,
-        };
-        let config = rpc.load_config().map_err(|e| {
-            ErrorData::parse_error("Invalid RPC URL", Some(Value::String(e.to_string())))
-        })?;
-        let provider = utils::get_provider(&config).map_err(|e| {
-            ErrorData::internal_error("Failed to get provider", Some(Value::String(e.to_string())))
-        })?;
-
-        let age = Cast::new(provider)
-            .age(get_block_id(args.block))
-            .await
-            .map_err(|e| {
-                ErrorData::internal_error(
-                    "Failed to get block age",
-                    Some(Value::String(e.to_string())),
+        let provider = self.provider(&args.endpoint)?;
+
+        let block = resolve_block_id(&provider, args.block).await?;
+        let block = get_block(&provider, block, false).await?;
+        let header = &block.header;
+        let date = i64::try_from(header.timestamp)
+            .ok()
+            .and_then(|timestamp| chrono::DateTime::from_timestamp(timestamp, 0))
+            .ok_or_else(|| {
+                tool_error(
+                    ErrorKind::Internal,
+                    "Block timestamp is out of range",
+                    Some(json!({ "timestamp": header.timestamp })),
                 )
-            })?;
-
-        Ok(CallToolResult::success(vec![Content::text(age)]))
+            })?
+            .to_rfc3339();
+
+        structured(
+            date.clone(),
+            &AgeOutput {
+                number: header.number,
+                timestamp: header.timestamp,
+                date,
+            },
+        )
     }
 
-    #[tool(description = "Get the timestamp of a block.")]
+    #[tool(
+        description = "Get a block, optionally only some of its fields or its raw RLP encoded header",
+        output_schema = output_schema::<RpcObjectOutput>()
+    )]
     async fn block(
         &self,
         Parameters(args): Parameters<BlockArgs>,
     ) -> Result<CallToolResult, ErrorData> {
-        let rpc = RpcOpts {
-            url: Some(args.rpc.clone()),
-            accept_invalid_certs: false,
-            no_proxy: false,
-            flashbots: false,
-            jwt_secret: None,
-            rpc_timeout: None,
-            rpc_headers: None,
-            curl: false,
-        };
-        let config = rpc.load_config().map_err(|e| {
-            ErrorData::parse_error("Invalid RPC URL", Some(Value::String(e.to_string())))
-        })?;
-        let provider = utils::get_provider(&config).map_err(|e| {
-            ErrorData::internal_error("Failed to get provider", Some(Value::String(e.to_string())))
-        })?;
+        let provider = self.provider(&args.endpoint)?;
+
+        let block_id = resolve_block_id(&provider, args.block).await?;
+        if args.raw || args.fields.contains(&"raw".into()) {
+            let raw = Cast::new(provider)
+                .block(block_id, false, Vec::new(), true)
+                .await
+                .map_err(|e| upstream_error("Failed to get block", &*e))?;
+            let output = RpcObjectOutput {
+                raw: Some(raw.clone()),
+                ..Default::default()
+            };
+            return structured(raw, &output);
+        }
 
-        let raw = args.raw || args.fields.contains(&"raw".into());
-        let block_id = get_block_id(args.block);
-        let res = Cast::new(provider)
-            .block(block_id, args.full, args.fields, raw)
-            .await
-            .map_err(|e| {
-                println!("Error: {}", e.to_string());
-                ErrorData::internal_error("Failed to get block", Some(Value::String(e.to_string())))
-            })?;
+        let block = get_block(&provider, block_id, args.full).await?;
+        let block = serde_json::to_value(&block)
+            .map_err(|e| internal_error("Failed to serialize block", e))?;
 
-        Ok(CallToolResult::success(vec![Content::text(res)]))
+        select_fields(block, &args.fields)
     }
 
-    #[tool(description = "Get the latest block number")]
+    #[tool(
+        description = "Get the latest block number",
+        output_schema = output_schema::<BlockNumberOutput>()
+    )]
     async fn block_number(
         &self,
         Parameters(args): Parameters<BlockNumberArgs>,
     ) -> Result<CallToolResult, ErrorData> {
-        let rpc = RpcOpts {
-            url: Some(args.rpc.clone()),
-            accept_invalid_certs: false,
-            no_proxy: false,
-            flashbots: false,
-            jwt_secret: None,
-            rpc_timeout: None,
-            rpc_headers: None,
-            curl: false,
-        };
-        let config = rpc.load_config().map_err(|e| {
-            ErrorData::parse_error("Invalid RPC URL", Some(Value::String(e.to_string())))
-        })?;
-        let provider = utils::get_provider(&config).map_err(|e| {
-            ErrorData::internal_error("Failed to get provider", Some(Value::String(e.to_string())))
-        })?;
+        let provider = self.provider(&args.endpoint)?;
 
-        // Use Cast to get block information and extract the number
-        let block_data = Cast::new(provider)
-            .block(
-                get_block_id(args.block),
-                false,
-                vec!["number".to_string()],
-                false,
-            )
+        let number = resolve_block_number(&provider, args.block).await?;
+
+        structured(number.to_string(), &BlockNumberOutput { number })
+    }
+
+    #[tool(
+        description = "Get the current gas price",
+        output_schema = output_schema::<GasPriceOutput>()
+    )]
+    async fn gas_price(
+        &self,
+        Parameters(args): Parameters<GasPriceArgs>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let provider = self.provider(&args.endpoint)?;
+
+        let price = Cast::new(provider)
+            .gas_price()
             .await
-            .map_err(|e| {
-                ErrorData::internal_error(
-                    "Failed to get block information",
-                    Some(Value::String(e.to_string())),
-                )
-            })?;
+            .map_err(|e| upstream_error("Failed to get gas price", &*e))?;
 
-        // Parse the block number from the response
-        let block_number = if block_data.contains("number") {
-            // Extract number from JSON response
-            block_data
-        } else {
-            // If it's just the number, use it directly
-            block_data
+        let gas_price = price.to_string();
+        structured(gas_price.clone(), &GasPriceOutput { gas_price })
+    }
+
+    #[tool(
+        description = "Estimate the gas a contract call would use (eth_estimateGas), taking the same arguments as call",
+        output_schema = output_schema::<EstimateOutput>()
+    )]
+    async fn estimate(
+        &self,
+        Parameters(args): Parameters<CallArgs>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let provider = self.provider(&args.endpoint)?;
+
+        let func = args.sig.as_deref().map(abi::parse_function).transpose()?;
+        let (tx, _) = call_request(&provider, &args, func.as_ref()).await?;
+        let mut estimate = provider.estimate_gas(WithOtherFields::new(tx));
+        if args.block.is_some() {
+            estimate = estimate.block(resolve_block_id(&provider, args.block).await?);
+        }
+        let gas = estimate
+            .await
+            .map_err(|e| rpc_error("Failed to estimate gas", &e))?;
+
+        structured(gas.to_string(), &EstimateOutput { gas })
+    }
+
+    #[tool(
+        description = "Get EIP-1559 fee history: base fees, priority fee percentiles and blob base fees over recent blocks, with a recommended maxFeePerGas and maxPriorityFeePerGas",
+        output_schema = output_schema::<FeeHistoryOutput>()
+    )]
+    async fn fee_history(
+        &self,
+        Parameters(args): Parameters<FeeHistoryArgs>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let provider = self.provider(&args.endpoint)?;
+
+        let block_count = args.block_count.unwrap_or(DEFAULT_FEE_HISTORY_BLOCKS);
+        if block_count == 0 || block_count > MAX_FEE_HISTORY_BLOCKS {
+            return Err(invalid_input(
+                format!("block_count must be between 1 and {MAX_FEE_HISTORY_BLOCKS}"),
+                Some(json!({ "input": block_count, "reason": "out_of_range" })),
+            ));
+        }
+        let percentiles = args
+            .reward_percentiles
+            .unwrap_or_else(|| DEFAULT_REWARD_PERCENTILES.to_vec());
+        validate_percentiles(&percentiles)?;
+        let newest = match args.newest_block {
+            Some(block) => {
+                BlockNumberOrTag::Number(resolve_block_number(&provider, Some(block)).await?)
+            }
+            None => BlockNumberOrTag::Latest,
         };
 
-        Ok(CallToolResult::success(vec![Content::text(block_number)]))
+        let history = provider
+            .get_fee_history(block_count, newest, &percentiles)
+            .await
+            .map_err(|e| rpc_error("Failed to get fee history", &e))?;
+
+        let next_base_fee = history.next_block_base_fee().unwrap_or_default();
+        let priority_fee = match median_reward(history.reward.as_deref(), percentiles.len()) {
+            Some(fee) => fee,
+            None => provider
+                .get_max_priority_fee_per_gas()
+                .await
+                .map_err(|e| rpc_error("Failed to get max priority fee", &e))?,
+        };
+        // Doubling the base fee keeps the transaction includable through six full blocks.
+        let max_fee = next_base_fee.saturating_mul(2).saturating_add(priority_fee);
+        let strings = |values: &[u128]| values.iter().map(u128::to_string).collect::<Vec<_>>();
+
+        json_output(&FeeHistoryOutput {
+            oldest_block: history.oldest_block,
+            base_fee_per_gas: strings(&history.base_fee_per_gas),
+            gas_used_ratio: history.gas_used_ratio.clone(),
+            base_fee_per_blob_gas: strings(&history.base_fee_per_blob_gas),
+            blob_gas_used_ratio: history.blob_gas_used_ratio.clone(),
+            reward: history
+                .reward
+                .as_deref()
+                .unwrap_or_default()
+                .iter()
+                .map(|rewards| strings(rewards))
+                .collect(),
+            reward_percentiles: percentiles,
+            next_base_fee_per_gas: next_base_fee.to_string(),
+            next_base_fee_per_blob_gas: history.next_block_blob_base_fee().map(|f| f.to_string()),
+            recommended: RecommendedFees {
+                max_fee_per_gas: max_fee.to_string(),
+                max_priority_fee_per_gas: priority_fee.to_string(),
+            },
+        })
     }
 
-    #[tool(description = "Get the current gas price")]
-    async fn gas_price(
+    #[tool(
+        description = "Get a transaction by hash, optionally only some of its fields or its raw RLP encoding",
+        output_schema = output_schema::<RpcObjectOutput>()
+    )]
+    async fn tx(&self, Parameters(args): Parameters<TxArgs>) -> Result<CallToolResult, ErrorData> {
+        let provider = self.provider(&args.endpoint)?;
+        let hash = parse_tx_hash(&args.hash)?;
+
+        if args.raw || args.fields.contains(&"raw".into()) {
+            let raw = provider
+                .get_raw_transaction_by_hash(hash)
+                .await
+                .map_err(|e| rpc_error("Failed to get transaction", &e))?
+                .ok_or_else(|| tx_not_found(hash))?;
+            let output = RpcObjectOutput {
+                raw: Some(raw.to_string()),
+                ..Default::default()
+            };
+            return structured(raw.to_string(), &output);
+        }
+
+        let tx = provider
+            .get_transaction_by_hash(hash)
+            .await
+            .map_err(|e| rpc_error("Failed to get transaction", &e))?
+            .ok_or_else(|| tx_not_found(hash))?;
+        let tx = serde_json::to_value(&tx)
+            .map_err(|e| internal_error("Failed to serialize transaction", e))?;
+
+        select_fields(tx, &args.fields)
+    }
+
+    #[tool(
+        description = "Get a transaction receipt by hash, optionally waiting for confirmations. Logs of known events are decoded",
+        output_schema = output_schema::<RpcObjectOutput>()
+    )]
+    async fn receipt(
         &self,
-        Parameters(args): Parameters<GasPriceArgs>,
+        Parameters(args): Parameters<ReceiptArgs>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let provider = self.provider(&args.endpoint)?;
+        let hash = parse_tx_hash(&args.hash)?;
+        let events = abi::parse_events(&args.events)?;
+
+        let timeout = args.timeout.unwrap_or(DEFAULT_RECEIPT_TIMEOUT_SECS);
+        let receipt = tokio::time::timeout(
+            Duration::from_secs(timeout),
+            wait_for_receipt(&provider, hash, args.confirmations),
+        )
+        .await
+        .map_err(|_| {
+            tool_error(
+                ErrorKind::Timeout,
+                "Timed out waiting for transaction receipt",
+                Some(json!({
+                    "hash": hash.to_string(),
+                    "confirmations": args.confirmations,
+                    "timeout": timeout,
+                })),
+            )
+        })??;
+
+        let mut receipt = serde_json::to_value(&receipt)
+            .map_err(|e| internal_error("Failed to serialize receipt", e))?;
+        if let Some(logs) = receipt.get_mut("logs").and_then(Value::as_array_mut) {
+            for log in logs {
+                decode_log_json(log, &events);
+            }
+        }
+
+        select_fields(receipt, &args.fields)
+    }
+
+    #[tool(
+        description = "Get event logs filtered by address, event signature or topics over a block range. Large ranges are queried in chunks and results are paginated with a cursor. Logs of known events are decoded",
+        output_schema = output_schema::<LogsOutput>()
+    )]
+    async fn logs(
+        &self,
+        Parameters(args): Parameters<LogsArgs>,
     ) -> Result<CallToolResult, ErrorData> {
-        let rpc = RpcOpts {
-            url: Some(args.rpc.clone()),
-            accept_invalid_certs: false,
-            no_proxy: false,
-            flashbots: false,
-            jwt_secret: None,
-            rpc_timeout: None,
-            rpc_headers: None,
-            curl: false,
+        let provider = self.provider(&args.endpoint)?;
+
+        let event = args.sig.as_deref().map(abi::parse_event).transpose()?;
+        let mut filter = Filter::new();
+        filter.topics = log_topics(&provider, event.as_ref(), &args.topics).await?;
+        let mut addresses = Vec::with_capacity(args.address.len());
+        for address in &args.address {
+            addresses.push(resolve_address(&provider, address).await?);
+        }
+        filter = filter.address(addresses);
+
+        let from = resolve_block_number(&provider, args.from_block).await?;
+        let to = resolve_block_number(&provider, args.to_block).await?;
+        if from > to {
+            return Err(invalid_input(
+                format!("from_block {from} is after to_block {to}"),
+                Some(json!({ "from_block": from, "to_block": to, "reason": "empty_range" })),
+            ));
+        }
+
+        let (mut start, mut skip) = match &args.cursor {
+            Some(cursor) => parse_log_cursor(cursor, from, to)?,
+            None => (from, 0),
         };
-        let config = rpc.load_config().map_err(|e| {
-            ErrorData::parse_error("Invalid RPC URL", Some(Value::String(e.to_string())))
-        })?;
-        let provider = utils::get_provider(&config).map_err(|e| {
-            ErrorData::internal_error("Failed to get provider", Some(Value::String(e.to_string())))
-        })?;
+        let limit = args.limit.unwrap_or(DEFAULT_LOG_LIMIT).max(1);
+        let mut chunk_size = args.chunk_size.unwrap_or(DEFAULT_LOG_CHUNK_SIZE).max(1);
+        let events: Vec<Event> = event.into_iter().collect();
+
+        let mut logs = Vec::new();
+        let mut next_cursor = None;
+        // The block of the last returned log and how many logs of that block have been returned,
+        // counting those returned by earlier pages.
+        let mut position = (start, skip);
+        let mut chunks = 0;
+        'scan: while start <= to {
+            if chunks == MAX_LOG_CHUNKS_PER_CALL {
+                next_cursor = Some(format!("{start}:{skip}"));
+                break;
+            }
+            let end = to.min(start.saturating_add(chunk_size - 1));
+            let range = filter.clone().from_block(start).to_block(end);
+            let batch = match provider.get_logs(&range).await {
+                Ok(batch) => batch,
+                // Providers cap the range or result count of eth_getLogs; retry with a smaller range.
+                // Other errors, such as an unreachable endpoint, would only fail again.
+                Err(e) if chunk_size > 1 && exceeds_result_limit(&e) => {
+                    chunk_size /= 2;
+                    continue;
+                }
+                Err(e) => return Err(rpc_error("Failed to get logs", &e)),
+            };
+            chunks += 1;
+
+            let skipped = batch
+                .iter()
+                .take_while(|log| log.block_number == Some(start))
+                .count()
+                .min(skip);
+            for log in batch.into_iter().skip(skipped) {
+                let block = log.block_number.unwrap_or(start);
+                if logs.len() == limit {
+                    let seen = if position.0 == block { position.1 } else { 0 };
+                    next_cursor = Some(format!("{block}:{seen}"));
+                    break 'scan;
+                }
+                position = if position.0 == block {
+                    (block, position.1 + 1)
+                } else {
+                    (block, 1)
+                };
+
+                logs.push(LogEntry::new(&log, &events));
+            }
+            start = end + 1;
+            skip = 0;
+        }
+
+        json_output(&LogsOutput {
+            from_block: from,
+            to_block: to,
+            logs,
+            next_cursor,
+        })
+    }
+}
+
+fn validate_percentiles(percentiles: &[f64]) -> Result<(), ErrorData> {
+    let in_range = percentiles.iter().all(|p| (0.0..=100.0).contains(p));
+    let ascending = percentiles.windows(2).all(|w| w[0] <= w[1]);
+    if in_range && ascending {
+        return Ok(());
+    }
+    Err(invalid_input(
+        "reward_percentiles must be ascending values between 0 and 100",
+        Some(json!({ "input": percentiles, "reason": "invalid_percentiles" })),
+    ))
+}
+
+/// The median across blocks of the middle requested percentile, used as the recommended
+/// priority fee. Blocks without transactions report zero rewards and are left out.
+fn median_reward(rewards: Option<&[Vec<u128>]>, percentiles: usize) -> Option<u128> {
+    if percentiles == 0 {
+        return None;
+    }
+    let middle = percentiles / 2;
+    let mut fees: Vec<u128> = rewards?
+        .iter()
+        .filter_map(|block| block.get(middle).copied())
+        .filter(|fee| *fee > 0)
+        .collect();
+    if fees.is_empty() {
+        return None;
+    }
+    fees.sort_unstable();
+    Some(fees[fees.len() / 2])
+}
+
+/// Build the topic filters of a `logs` call. With an event, topic0 is its selector and `topics`
+/// filter its indexed parameters; otherwise `topics` are raw topics starting at topic0.
+async fn log_topics(
+    provider: &RetryProvider,
+    event: Option<&Event>,
+    topics: &[String],
+) -> Result<[Topic; 4], ErrorData> {
+    let mut filter: [Topic; 4] = Default::default();
+    let offset = usize::from(event.is_some());
+    if let Some(event) = event {
+        filter[0] = event.selector().into();
+    }
+    if topics.len() + offset > filter.len() {
+        return Err(invalid_input(
+            format!("At most {} topics can be filtered", filter.len() - offset),
+            Some(json!({ "got": topics.len(), "reason": "too_many_topics" })),
+        ));
+    }
+
+    let indexed: Vec<_> = event
+        .map(|event| event.inputs.iter().filter(|p| p.indexed).collect())
+        .unwrap_or_default();
+    for (index, input) in topics.iter().enumerate() {
+        let mut values = Vec::new();
+        for alternative in input.split(',').map(str::trim) {
+            if alternative.is_empty() || alternative == "*" {
+                continue;
+            }
+            let topic = match event {
+                Some(event) => {
+                    let param = indexed.get(index).ok_or_else(|| {
+                        invalid_input(
+                            format!(
+                                "{} has {} indexed parameters, got a filter for parameter {index}",
+                                event.name,
+                                indexed.len()
+                            ),
+                            Some(json!({ "reason": "too_many_topics" })),
+                        )
+                    })?;
+                    abi::indexed_topic(provider, param, index, alternative).await?
+                }
+                None => alternative.parse::<B256>().map_err(|_| {
+                    invalid_input(
+                        format!("Invalid topic: {alternative}"),
+                        Some(json!({
+                            "input": alternative,
+                            "expected": "a 0x-prefixed 32-byte hex topic",
+                        })),
+                    )
+                })?,
+            };
+            values.push(topic);
+        }
+        if !values.is_empty() {
+            filter[index + offset] = values.into();
+        }
+    }
+    Ok(filter)
+}
 
-        let price = Cast::new(provider).gas_price().await.map_err(|e| {
-            ErrorData::internal_error(
-                "Failed to get gas price",
-                Some(Value::String(e.to_string())),
+/// Parse a `logs` cursor: the block to resume at and how many of its logs were already returned.
+fn parse_log_cursor(cursor: &str, from: u64, to: u64) -> Result<(u64, usize), ErrorData> {
+    let invalid = || {
+        invalid_input(
+            format!("Invalid cursor: {cursor}"),
+            Some(json!({ "input": cursor, "reason": "invalid_cursor" })),
+        )
+    };
+    let (block, skip) = cursor.trim().split_once(':').ok_or_else(invalid)?;
+    let block: u64 = block.parse().map_err(|_| invalid())?;
+    let skip: usize = skip.parse().map_err(|_| invalid())?;
+    if block < from || block > to {
+        return Err(invalid());
+    }
+    Ok((block, skip))
+}
+
+fn parse_tx_hash(input: &str) -> Result<B256, ErrorData> {
+    input.trim().parse().map_err(|_| {
+        invalid_input(
+            format!("Invalid transaction hash: {input}"),
+            Some(json!({ "input": input, "expected": "a 0x-prefixed 32-byte hex hash" })),
+        )
+    })
+}
+
+fn tx_not_found(hash: B256) -> ErrorData {
+    not_found(
+        format!("Transaction not found: {hash}"),
+        Some(json!({ "hash": hash.to_string(), "reason": "not_found" })),
+    )
+}
+
+fn tx_pending(hash: B256) -> ErrorData {
+    not_found(
+        format!("Transaction {hash} is pending and has no receipt yet"),
+        Some(json!({ "hash": hash.to_string(), "reason": "pending" })),
+    )
+}
+
+/// Poll for the receipt of `hash` until it has `confirmations` confirmations. With zero
+/// confirmations a missing receipt is an error rather than something to wait for, telling a
+/// pending transaction from one the node does not know.
+///
+/// `Cast::receipt` is not used: it renders the receipt as text, and skips the confirmations wait
+/// once the transaction is mined.
+async fn wait_for_receipt(
+    provider: &RetryProvider,
+    hash: B256,
+    confirmations: u64,
+) -> Result<<AnyNetwork as Network>::ReceiptResponse, ErrorData> {
+    loop {
+        let receipt = provider
+            .get_transaction_receipt(hash)
+            .await
+            .map_err(|e| rpc_error("Failed to get receipt", &e))?;
+        match receipt {
+            Some(receipt) if confirmations <= 1 => return Ok(receipt),
+            Some(receipt) => {
+                let latest = provider
+                    .get_block_number()
+                    .await
+                    .map_err(|e| rpc_error("Failed to get block number", &e))?;
+                let mined = receipt.block_number().unwrap_or(latest);
+                if latest.saturating_sub(mined) + 1 >= confirmations {
+                    return Ok(receipt);
+                }
+            }
+            None if confirmations == 0 => {
+                let tx = provider
+                    .get_transaction_by_hash(hash)
+                    .await
+                    .map_err(|e| rpc_error("Failed to get transaction", &e))?;
+                return Err(match tx {
+                    Some(_) => tx_pending(hash),
+                    None => tx_not_found(hash),
+                });
+            }
+            None => {}
+        }
+        tokio::time::sleep(RECEIPT_POLL_INTERVAL).await;
+    }
+}
+
+/// Add a `decoded` entry to a JSON log when its topics match a known event.
+fn decode_log_json(log: &mut Value, events: &[Event]) {
+    let topics = log
+        .get("topics")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .map(|topic| topic.as_str()?.parse::<B256>().ok())
+        .collect::<Option<Vec<_>>>();
+    let data = log
+        .get("data")
+        .and_then(Value::as_str)
+        .and_then(|data| data.parse::<Bytes>().ok());
+
+    if let (Some(topics), Some(data)) = (topics, data) {
+        if let Some(decoded) = abi::decode_log(events, &topics, &data) {
+            log["decoded"] = json!(decoded);
+        }
+    }
+}
+
+/// Return the whole object as JSON, a single field as text, or several fields as a JSON object, with
+/// the selected fields as structured content.
+/// Field names may be given in camelCase or snake_case.
+fn select_fields(value: Value, fields: &[String]) -> Result<CallToolResult, ErrorData> {
+    let fields: Vec<&String> = fields.iter().filter(|f| f.as_str() != "raw").collect();
+    if fields.is_empty() {
+        let fields = match value {
+            Value::Object(fields) => fields,
+            _ => Map::new(),
+        };
+        return json_output(&RpcObjectOutput { raw: None, fields });
+    }
+
+    let mut selected = Map::new();
+    for field in &fields {
+        let key = to_camel_case(field);
+        let field_value = value.get(&key).cloned().ok_or_else(|| {
+            let available: Vec<&String> = value
+                .as_object()
+                .map(|o| o.keys().collect())
+                .unwrap_or_default();
+            invalid_input(
+                format!("Unknown field: {field}"),
+                Some(json!({ "input": field, "available": available })),
             )
         })?;
+        selected.insert(key, field_value);
+    }
 
-        Ok(CallToolResult::success(vec![Content::text(
-            price.to_string(),
-        )]))
+    let output = RpcObjectOutput {
+        raw: None,
+        fields: selected,
+    };
+    if let [field] = fields.as_slice() {
+        let text = match &output.fields[&to_camel_case(field)] {
+            Value::String(s) => s.clone(),
+            other => other.to_string(),
+        };
+        return structured(text, &output);
     }
+    json_output(&output)
+}
+
+fn to_camel_case(field: &str) -> String {
+    let mut out = String::with_capacity(field.len());
+    let mut upper = false;
+    for c in field.trim().chars() {
+        if c == '_' {
+            upper = true;
+        } else if upper {
+            out.extend(c.to_uppercase());
+            upper = false;
+        } else {
+            out.push(c);
+        }
+    }
+    out
 }
 
 #[cfg(test)]
@@ -225,18 +971,18 @@ mod tests {
     #[test]
     fn test_age_args_default() {
         let args = AgeArgs::default();
-        assert_eq!(args.rpc, "http://localhost:8545");
+        assert_eq!(args.endpoint.rpc, None);
         assert_eq!(args.block, None);
     }
 
     #[test]
     fn test_age_args_clone() {
         let original = AgeArgs {
-            rpc: "https://test.com".to_string(),
-            block: Some("latest".to_string()),
+            endpoint: EndpointArgs::from_rpc("https://test.com"),
+            block: Some("latest".into()),
         };
         let cloned = original.clone();
-        assert_eq!(original.rpc, cloned.rpc);
+        assert_eq!(original.endpoint.rpc, cloned.endpoint.rpc);
         assert_eq!(original.block, cloned.block);
     }
 
@@ -248,8 +994,8 @@ mod tests {
 
         for tag in block_tags {
             let args = AgeArgs {
-                rpc: "https://1rpc.io/eth".to_string(),
-                block: Some(tag.to_string()),
+                endpoint: EndpointArgs::from_rpc("https://1rpc.io/eth"),
+                block: Some(tag.into()),
             };
             let params = Parameters(args);
 
@@ -298,8 +1044,8 @@ mod tests {
 
         // Test with localhost (may succeed or fail, but we check structure)
         let args = AgeArgs {
-            rpc: "https://1rpc.io/eth".to_string(),
-            block: Some("latest".to_string()),
+            endpoint: EndpointArgs::from_rpc("https://1rpc.io/eth"),
+            block: Some("latest".into()),
         };
         let params = Parameters(args);
 
@@ -368,8 +1114,8 @@ mod tests {
                 let server_clone = Arc::clone(&server);
                 tokio::spawn(async move {
                     let args = AgeArgs {
-                        rpc: "http://localhost:8545".to_string(),
-                        block: block_opt.map(|s| s.to_string()),
+                        endpoint: EndpointArgs::from_rpc("http://localhost:8545"),
+                        block: block_opt.map(BlockParam::from),
                     };
                     let params = Parameters(args);
 
@@ -411,8 +1157,8 @@ mod tests {
     #[test]
     fn test_age_args_debug_format() {
         let args = AgeArgs {
-            rpc: "test-rpc".to_string(),
-            block: Some("12345".to_string()),
+            endpoint: EndpointArgs::from_rpc("test-rpc"),
+            block: Some("12345".into()),
         };
         let debug_output = format!("{:?}", args);
         assert!(debug_output.contains("AgeArgs"));
@@ -424,25 +1170,25 @@ mod tests {
     fn test_age_args_partial_default() {
         // Test that individual fields can be set while others use defaults
         let args = AgeArgs {
-            rpc: "https://custom-rpc.com".to_string(),
+            endpoint: EndpointArgs::from_rpc("https://custom-rpc.com"),
             block: None,
         };
-        assert_eq!(args.rpc, "https://custom-rpc.com");
+        assert_eq!(args.endpoint.rpc.as_deref(), Some("https://custom-rpc.com"));
         assert_eq!(args.block, None);
 
         let args2 = AgeArgs {
-            rpc: "http://localhost:8545".to_string(), // default
-            block: Some("earliest".to_string()),
+            endpoint: EndpointArgs::from_rpc("http://localhost:8545"), // default
+            block: Some("earliest".into()),
         };
-        assert_eq!(args2.rpc, "http://localhost:8545");
-        assert_eq!(args2.block, Some("earliest".to_string()));
+        assert_eq!(args2.endpoint.rpc.as_deref(), Some("http://localhost:8545"));
+        assert_eq!(args2.block, Some("earliest".into()));
     }
 
     // BlockArgs tests
     #[test]
     fn test_block_args_default() {
         let args = BlockArgs::default();
-        assert_eq!(args.rpc, "http://localhost:8545");
+        assert_eq!(args.endpoint.rpc, None);
         assert_eq!(args.fields, Vec::<String>::new());
         assert_eq!(args.raw, false);
         assert_eq!(args.full, false);
@@ -452,14 +1198,14 @@ mod tests {
     #[test]
     fn test_block_args_clone() {
         let original = BlockArgs {
-            rpc: "https://test.com".to_string(),
+            endpoint: EndpointArgs::from_rpc("https://test.com"),
             fields: vec!["number".to_string(), "hash".to_string()],
             raw: true,
             full: false,
-            block: Some("latest".to_string()),
+            block: Some("latest".into()),
         };
         let cloned = original.clone();
-        assert_eq!(original.rpc, cloned.rpc);
+        assert_eq!(original.endpoint.rpc, cloned.endpoint.rpc);
         assert_eq!(original.fields, cloned.fields);
         assert_eq!(original.raw, cloned.raw);
         assert_eq!(original.full, cloned.full);
@@ -469,11 +1215,11 @@ mod tests {
     #[test]
     fn test_block_args_debug_format() {
         let args = BlockArgs {
-            rpc: "test-rpc".to_string(),
+            endpoint: EndpointArgs::from_rpc("test-rpc"),
             fields: vec!["timestamp".to_string()],
             raw: false,
             full: true,
-            block: Some("12345".to_string()),
+            block: Some("12345".into()),
         };
         let debug_output = format!("{:?}", args);
         assert!(debug_output.contains("BlockArgs"));
@@ -486,26 +1232,26 @@ mod tests {
     #[test]
     fn test_block_number_args_default() {
         let args = BlockNumberArgs::default();
-        assert_eq!(args.rpc, "http://localhost:8545");
+        assert_eq!(args.endpoint.rpc, None);
         assert_eq!(args.block, None);
     }
 
     #[test]
     fn test_block_number_args_clone() {
         let original = BlockNumberArgs {
-            rpc: "https://test.com".to_string(),
-            block: Some("latest".to_string()),
+            endpoint: EndpointArgs::from_rpc("https://test.com"),
+            block: Some("latest".into()),
         };
         let cloned = original.clone();
-        assert_eq!(original.rpc, cloned.rpc);
+        assert_eq!(original.endpoint.rpc, cloned.endpoint.rpc);
         assert_eq!(original.block, cloned.block);
     }
 
     #[test]
     fn test_block_number_args_debug_format() {
         let args = BlockNumberArgs {
-            rpc: "test-rpc".to_string(),
-            block: Some("latest".to_string()),
+            endpoint: EndpointArgs::from_rpc("test-rpc"),
+            block: Some("latest".into()),
         };
         let debug_output = format!("{:?}", args);
         assert!(debug_output.contains("BlockNumberArgs"));
@@ -518,11 +1264,11 @@ mod tests {
         let server = Server::new();
 
         let args = BlockArgs {
-            rpc: "invalid-url".to_string(),
+            endpoint: EndpointArgs::from_rpc("invalid-url"),
             fields: Vec::new(),
             raw: false,
             full: false,
-            block: Some("latest".to_string()),
+            block: Some("latest".into()),
         };
         let params = Parameters(args);
 
@@ -548,35 +1294,35 @@ mod tests {
         let test_cases = vec![
             // Basic case
             BlockArgs {
-                rpc: "https://1rpc.io/eth".to_string(),
+                endpoint: EndpointArgs::from_rpc("https://1rpc.io/eth"),
                 fields: Vec::new(),
                 raw: false,
                 full: false,
-                block: Some("latest".to_string()),
+                block: Some("latest".into()),
             },
             // Full block
             BlockArgs {
-                rpc: "https://1rpc.io/eth".to_string(),
+                endpoint: EndpointArgs::from_rpc("https://1rpc.io/eth"),
                 fields: Vec::new(),
                 raw: false,
                 full: true,
-                block: Some("latest".to_string()),
+                block: Some("latest".into()),
             },
             // Raw block
             BlockArgs {
-                rpc: "https://1rpc.io/eth".to_string(),
+                endpoint: EndpointArgs::from_rpc("https://1rpc.io/eth"),
                 fields: vec!["raw".to_string()],
                 raw: false, // This should be overridden by fields containing "raw"
                 full: false,
-                block: Some("latest".to_string()),
+                block: Some("latest".into()),
             },
             // Specific fields
             BlockArgs {
-                rpc: "https://1rpc.io/eth".to_string(),
+                endpoint: EndpointArgs::from_rpc("https://1rpc.io/eth"),
                 fields: vec!["number".to_string(), "timestamp".to_string()],
                 raw: false,
                 full: false,
-                block: Some("latest".to_string()),
+                block: Some("latest".into()),
             },
         ];
 
@@ -634,11 +1380,11 @@ mod tests {
 
         for tag in block_tags {
             let args = BlockArgs {
-                rpc: "https://1rpc.io/eth".to_string(),
+                endpoint: EndpointArgs::from_rpc("https://1rpc.io/eth"),
                 fields: vec!["number".to_string()],
                 raw: false,
                 full: false,
-                block: Some(tag.to_string()),
+                block: Some(tag.into()),
             };
             let params = Parameters(args);
 
@@ -704,11 +1450,11 @@ mod tests {
                 let server_clone = Arc::clone(&server);
                 tokio::spawn(async move {
                     let args = BlockArgs {
-                        rpc: "https://1rpc.io/eth".to_string(),
+                        endpoint: EndpointArgs::from_rpc("https://1rpc.io/eth"),
                         fields,
                         raw,
                         full,
-                        block: block_opt.map(|s| s.to_string()),
+                        block: block_opt.map(BlockParam::from),
                     };
                     let params = Parameters(args);
 
@@ -762,8 +1508,8 @@ mod tests {
 
         for block_opt in test_cases {
             let args = BlockNumberArgs {
-                rpc: "https://1rpc.io/eth".to_string(),
-                block: block_opt.map(|s| s.to_string()),
+                endpoint: EndpointArgs::from_rpc("https://1rpc.io/eth"),
+                block: block_opt.map(BlockParam::from),
             };
             let params = Parameters(args);
 
@@ -840,8 +1586,8 @@ mod tests {
                 let server_clone = Arc::clone(&server);
                 tokio::spawn(async move {
                     let args = BlockNumberArgs {
-                        rpc: "https://1rpc.io/eth".to_string(),
-                        block: block_opt.map(|s| s.to_string()),
+                        endpoint: EndpointArgs::from_rpc("https://1rpc.io/eth"),
+                        block: block_opt.map(BlockParam::from),
                     };
                     let params = Parameters(args);
 
@@ -894,7 +1640,7 @@ mod tests {
 
         for rpc_url in test_cases {
             let args = GasPriceArgs {
-                rpc: rpc_url.to_string(),
+                endpoint: EndpointArgs::from_rpc(rpc_url),
             };
             let params = Parameters(args);
 
@@ -970,7 +1716,7 @@ mod tests {
                 let server_clone = Arc::clone(&server);
                 tokio::spawn(async move {
                     let args = GasPriceArgs {
-                        rpc: rpc_url.to_string(),
+                        endpoint: EndpointArgs::from_rpc(rpc_url),
                     };
                     let params = Parameters(args);
 
@@ -1009,4 +1755,238 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn test_tx_args_default() {
+        let args = TxArgs::default();
+        assert_eq!(args.endpoint.rpc, None);
+        assert!(args.fields.is_empty());
+        assert!(!args.raw);
+    }
+
+    #[test]
+    fn test_receipt_args_default() {
+        let args = ReceiptArgs::default();
+        assert_eq!(args.confirmations, 0);
+        assert_eq!(args.timeout, None);
+        assert!(args.events.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_receipt_rejects_invalid_hash() {
+        let server = Server::new();
+        let args = ReceiptArgs {
+            endpoint: EndpointArgs::from_rpc("http://localhost:8545"),
+            hash: "0x1234".to_string(),
+            ..Default::default()
+        };
+
+        let error = server.receipt(Parameters(args)).await.unwrap_err();
+        assert_eq!(error.code, ErrorCode::INVALID_PARAMS);
+        assert!(error.message.contains("0x1234"));
+    }
+
+    #[test]
+    fn test_to_camel_case() {
+        assert_eq!(to_camel_case("block_number"), "blockNumber");
+        assert_eq!(to_camel_case("blockNumber"), "blockNumber");
+        assert_eq!(to_camel_case("from"), "from");
+    }
+
+    #[test]
+    fn test_select_fields() {
+        let value = json!({ "from": "0xabc", "blockNumber": "0x10", "status": "0x1" });
+
+        let single = select_fields(value.clone(), &["block_number".to_string()]).unwrap();
+        assert_eq!(single.content[0].raw.as_text().unwrap().text, "0x10");
+        assert_eq!(
+            single.structured_content,
+            Some(json!({ "blockNumber": "0x10" }))
+        );
+
+        let several =
+            select_fields(value.clone(), &["from".to_string(), "status".to_string()]).unwrap();
+        let text = &several.content[0].raw.as_text().unwrap().text;
+        let selected: Value = serde_json::from_str(text).unwrap();
+        assert_eq!(selected, json!({ "from": "0xabc", "status": "0x1" }));
+        assert_eq!(several.structured_content, Some(selected));
+
+        let whole = select_fields(value.clone(), &[]).unwrap();
+        assert_eq!(whole.structured_content, Some(value.clone()));
+
+        let error = select_fields(value, &["nope".to_string()]).unwrap_err();
+        assert_eq!(error.code, ErrorCode::INVALID_PARAMS);
+    }
+
+    #[test]
+    fn test_decode_log_json() {
+        let mut log = json!({
+            "address": "0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48",
+            "topics": [
+                "0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef",
+                "0x000000000000000000000000d8da6bf26964af9d7eed9e03e53415d37aa96045",
+                "0x000000000000000000000000a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48"
+            ],
+            "data": "0x00000000000000000000000000000000000000000000000000000000000003e8"
+        });
+        decode_log_json(&mut log, &[]);
+        assert_eq!(log["decoded"]["name"], "Transfer");
+        assert_eq!(log["decoded"]["params"][2]["value"], "1000");
+
+        let mut unknown = json!({ "topics": ["0x01"], "data": "0x" });
+        decode_log_json(&mut unknown, &[]);
+        assert!(unknown.get("decoded").is_none());
+    }
+
+    fn local_provider() -> RetryProvider {
+        Server::new()
+            .provider(&EndpointArgs::from_rpc("http://localhost:8545"))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_logs_args_default() {
+        let args = LogsArgs::default();
+        assert!(args.address.is_empty());
+        assert_eq!(args.sig, None);
+        assert!(args.topics.is_empty());
+        assert_eq!(args.chunk_size, None);
+        assert_eq!(args.limit, None);
+        assert_eq!(args.cursor, None);
+    }
+
+    #[tokio::test]
+    async fn test_logs_rejects_reversed_range() {
+        let server = Server::new();
+        let args = LogsArgs {
+            endpoint: EndpointArgs::from_rpc("http://localhost:8545"),
+            from_block: Some("200".into()),
+            to_block: Some("100".into()),
+            ..Default::default()
+        };
+
+        let error = server.logs(Parameters(args)).await.unwrap_err();
+        assert_eq!(error.data.unwrap()["reason"], "empty_range");
+    }
+
+    #[tokio::test]
+    async fn test_log_topics_from_event() {
+        let event =
+            abi::parse_event("Transfer(address indexed, address indexed, uint256)").unwrap();
+        let topics = log_topics(
+            &local_provider(),
+            Some(&event),
+            &[
+                "".to_string(),
+                "0xd8dA6BF26964aF9D7eEd9e03E53415D37aA96045".to_string(),
+            ],
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(topics[0], Topic::from(event.selector()));
+        assert_eq!(topics[1], Topic::default());
+        let to: alloy_primitives::Address = "0xd8dA6BF26964aF9D7eEd9e03E53415D37aA96045"
+            .parse()
+            .unwrap();
+        assert_eq!(topics[2], Topic::from(to));
+        assert_eq!(topics[3], Topic::default());
+    }
+
+    #[tokio::test]
+    async fn test_log_topics_raw_alternatives() {
+        let a = B256::with_last_byte(1);
+        let b = B256::with_last_byte(2);
+        let topics = log_topics(&local_provider(), None, &[format!("{a}, {b}")])
+            .await
+            .unwrap();
+        assert_eq!(topics[0], Topic::from(vec![a, b]));
+    }
+
+    #[tokio::test]
+    async fn test_log_topics_errors() {
+        let provider = local_provider();
+        let error = log_topics(&provider, None, &["0x1234".to_string()])
+            .await
+            .unwrap_err();
+        assert_eq!(error.code, ErrorCode::INVALID_PARAMS);
+
+        let event =
+            abi::parse_event("Approval(address indexed, address indexed, uint256)").unwrap();
+        let error = log_topics(
+            &provider,
+            Some(&event),
+            &["*", "*", "*", "*"].map(String::from),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(error.data.unwrap()["reason"], "too_many_topics");
+    }
+
+    #[test]
+    fn test_parse_log_cursor() {
+        assert_eq!(parse_log_cursor("150:3", 100, 200).unwrap(), (150, 3));
+        for cursor in ["", "150", "x:1", "150:-1", "99:0", "201:0"] {
+            let error = parse_log_cursor(cursor, 100, 200).unwrap_err();
+            assert_eq!(error.data.unwrap()["reason"], "invalid_cursor");
+        }
+    }
+
+    #[test]
+    fn test_fee_history_args_default() {
+        let args = FeeHistoryArgs::default();
+        assert_eq!(args.block_count, None);
+        assert_eq!(args.newest_block, None);
+        assert_eq!(args.reward_percentiles, None);
+    }
+
+    #[tokio::test]
+    async fn test_fee_history_rejects_block_count() {
+        let server = Server::new();
+        for block_count in [0, MAX_FEE_HISTORY_BLOCKS + 1] {
+            let args = FeeHistoryArgs {
+                endpoint: EndpointArgs::from_rpc("http://localhost:8545"),
+                block_count: Some(block_count),
+                ..Default::default()
+            };
+            let error = server.fee_history(Parameters(args)).await.unwrap_err();
+            assert_eq!(error.data.unwrap()["reason"], "out_of_range");
+        }
+    }
+
+    #[test]
+    fn test_validate_percentiles() {
+        assert!(validate_percentiles(&[]).is_ok());
+        assert!(validate_percentiles(&[10.0, 50.0, 90.0]).is_ok());
+        assert!(validate_percentiles(&[50.0, 10.0]).is_err());
+        assert!(validate_percentiles(&[-1.0]).is_err());
+        assert!(validate_percentiles(&[101.0]).is_err());
+    }
+
+    #[test]
+    fn test_median_reward() {
+        let rewards = vec![
+            vec![1, 10, 100],
+            vec![0, 0, 0],
+            vec![2, 30, 200],
+            vec![3, 20, 300],
+        ];
+        assert_eq!(median_reward(Some(&rewards), 3), Some(20));
+        assert_eq!(median_reward(Some(&rewards[1..2]), 3), None);
+        assert_eq!(median_reward(None, 3), None);
+        assert_eq!(median_reward(Some(&rewards), 0), None);
+    }
+
+    #[tokio::test]
+    async fn test_estimate_requires_sig_or_data() {
+        let server = Server::new();
+        let args = CallArgs {
+            endpoint: EndpointArgs::from_rpc("http://localhost:8545"),
+            to: "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48".to_string(),
+            ..Default::default()
+        };
+
+        let error = server.estimate(Parameters(args)).await.unwrap_err();
+        assert_eq!(error.data.unwrap()["reason"], "missing");
+    }
 }
@@ -0,0 +1,608 @@
+use alloy_primitives::{Address, hex, keccak256};
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use crate::common::signatures::{self, SelectorKind};
+
+/// The runtime code of an EIP-1167 minimal proxy, around its implementation address.
+const EIP1167_PREFIX: [u8; 10] = hex!("363d3d373d3d3d363d73");
+const EIP1167_SUFFIX: [u8; 15] = hex!("5af43d82803e903d91602b57fd5bf3");
+
+/// The code of an EIP-7702 delegated account, before its delegate's address.
+const EIP7702_PREFIX: [u8; 3] = hex!("ef0100");
+
+const PUSH4: u8 = 0x63;
+const PUSH32: u8 = 0x7f;
+const EQ: u8 = 0x14;
+
+/// A decoded instruction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Instruction<'a> {
+    pub pc: usize,
+    pub opcode: u8,
+    /// The immediate bytes of a PUSH, cut short if the code ends first.
+    pub push: &'a [u8],
+}
+
+impl Instruction<'_> {
+    /// The mnemonic of the opcode, or its hex value when it is not assigned.
+    pub fn name(&self) -> String {
+        opcode_name(self.opcode)
+            .map(str::to_string)
+            .unwrap_or_else(|| format!("0x{:02x}", self.opcode))
+    }
+}
+
+/// An instruction, as listed in an analysis.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, JsonSchema)]
+pub struct Opcode {
+    pub pc: usize,
+    /// The mnemonic, or the hex value of an unassigned opcode.
+    pub op: String,
+    /// The immediate bytes of a PUSH.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub push: Option<String>,
+}
+
+impl From<&Instruction<'_>> for Opcode {
+    fn from(instruction: &Instruction<'_>) -> Self {
+        Self {
+            pc: instruction.pc,
+            op: instruction.name(),
+            push: (push_size(instruction.opcode) > 0)
+                .then(|| hex::encode_prefixed(instruction.push)),
+        }
+    }
+}
+
+/// Split `code` into instructions, skipping over PUSH immediates.
+pub fn disassemble(code: &[u8]) -> Vec<Instruction<'_>> {
+    let mut instructions = Vec::new();
+    let mut pc = 0;
+    while pc < code.len() {
+        let opcode = code[pc];
+        let end = (pc + 1 + push_size(opcode)).min(code.len());
+        instructions.push(Instruction {
+            pc,
+            opcode,
+            push: &code[pc + 1..end],
+        });
+        pc += 1 + push_size(opcode);
+    }
+    instructions
+}
+
+fn push_size(opcode: u8) -> usize {
+    match opcode {
+        0x60..=0x7f => usize::from(opcode - 0x5f),
+        _ => 0,
+    }
+}
+
+fn opcode_name(opcode: u8) -> Option<&'static str> {
+    const PUSH: [&str; 32] = [
+        "PUSH1", "PUSH2", "PUSH3", "PUSH4", "PUSH5", "PUSH6", "PUSH7", "PUSH8", "PUSH9", "PUSH10",
+        "PUSH11", "PUSH12", "PUSH13", "PUSH14", "PUSH15", "PUSH16", "PUSH17", "PUSH18", "PUSH19",
+        "PUSH20", "PUSH21", "PUSH22", "PUSH23", "PUSH24", "PUSH25", "PUSH26", "PUSH27", "PUSH28",
+        "PUSH29", "PUSH30", "PUSH31", "PUSH32",
+    ];
+    const DUP: [&str; 16] = [
+        "DUP1", "DUP2", "DUP3", "DUP4", "DUP5", "DUP6", "DUP7", "DUP8", "DUP9", "DUP10", "DUP11",
+        "DUP12", "DUP13", "DUP14", "DUP15", "DUP16",
+    ];
+    const SWAP: [&str; 16] = [
+        "SWAP1", "SWAP2", "SWAP3", "SWAP4", "SWAP5", "SWAP6", "SWAP7", "SWAP8", "SWAP9", "SWAP10",
+        "SWAP11", "SWAP12", "SWAP13", "SWAP14", "SWAP15", "SWAP16",
+    ];
+    const LOG: [&str; 5] = ["LOG0", "LOG1", "LOG2", "LOG3", "LOG4"];
+
+    let name = match opcode {
+        0x00 => "STOP",
+        0x01 => "ADD",
+        0x02 => "MUL",
+        0x03 => "SUB",
+        0x04 => "DIV",
+        0x05 => "SDIV",
+        0x06 => "MOD",
+        0x07 => "SMOD",
+        0x08 => "ADDMOD",
+        0x09 => "MULMOD",
+        0x0a => "EXP",
+        0x0b => "SIGNEXTEND",
+        0x10 => "LT",
+        0x11 => "GT",
+        0x12 => "SLT",
+        0x13 => "SGT",
+        0x14 => "EQ",
+        0x15 => "ISZERO",
+        0x16 => "AND",
+        0x17 => "OR",
+        0x18 => "XOR",
+        0x19 => "NOT",
+        0x1a => "BYTE",
+        0x1b => "SHL",
+        0x1c => "SHR",
+        0x1d => "SAR",
+        0x20 => "KECCAK256",
+        0x30 => "ADDRESS",
+        0x31 => "BALANCE",
+        0x32 => "ORIGIN",
+        0x33 => "CALLER",
+        0x34 => "CALLVALUE",
+        0x35 => "CALLDATALOAD",
+        0x36 => "CALLDATASIZE",
+        0x37 => "CALLDATACOPY",
+        0x38 => "CODESIZE",
+        0x39 => "CODECOPY",
+        0x3a => "GASPRICE",
+        0x3b => "EXTCODESIZE",
+        0x3c => "EXTCODECOPY",
+        0x3d => "RETURNDATASIZE",
+        0x3e => "RETURNDATACOPY",
+        0x3f => "EXTCODEHASH",
+        0x40 => "BLOCKHASH",
+        0x41 => "COINBASE",
+        0x42 => "TIMESTAMP",
+        0x43 => "NUMBER",
+        0x44 => "PREVRANDAO",
+        0x45 => "GASLIMIT",
+        0x46 => "CHAINID",
+        0x47 => "SELFBALANCE",
+        0x48 => "BASEFEE",
+        0x49 => "BLOBHASH",
+        0x4a => "BLOBBASEFEE",
+        0x50 => "POP",
+        0x51 => "MLOAD",
+        0x52 => "MSTORE",
+        0x53 => "MSTORE8",
+        0x54 => "SLOAD",
+        0x55 => "SSTORE",
+        0x56 => "JUMP",
+        0x57 => "JUMPI",
+        0x58 => "PC",
+        0x59 => "MSIZE",
+        0x5a => "GAS",
+        0x5b => "JUMPDEST",
+        0x5c => "TLOAD",
+        0x5d => "TSTORE",
+        0x5e => "MCOPY",
+        0x5f => "PUSH0",
+        0x60..=0x7f => PUSH[usize::from(opcode - 0x60)],
+        0x80..=0x8f => DUP[usize::from(opcode - 0x80)],
+        0x90..=0x9f => SWAP[usize::from(opcode - 0x90)],
+        0xa0..=0xa4 => LOG[usize::from(opcode - 0xa0)],
+        0xf0 => "CREATE",
+        0xf1 => "CALL",
+        0xf2 => "CALLCODE",
+        0xf3 => "RETURN",
+        0xf4 => "DELEGATECALL",
+        0xf5 => "CREATE2",
+        0xfa => "STATICCALL",
+        0xfd => "REVERT",
+        0xfe => "INVALID",
+        0xff => "SELFDESTRUCT",
+        _ => return None,
+    };
+    Some(name)
+}
+
+/// The CBOR metadata solc appends to runtime code, followed by its 2-byte length.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, JsonSchema)]
+pub struct Metadata {
+    /// Where the metadata starts. Code from here on is not executable.
+    pub offset: usize,
+    /// The length of the metadata, including its 2-byte length suffix.
+    pub length: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub solc: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ipfs: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bzzr0: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bzzr1: Option<String>,
+    pub experimental: bool,
+}
+
+/// Parse the metadata at the end of `code`, if it ends with a CBOR map of the expected length.
+pub fn parse_metadata(code: &[u8]) -> Option<Metadata> {
+    let len = code.len();
+    if len < 2 {
+        return None;
+    }
+    let cbor_len = usize::from(u16::from_be_bytes([code[len - 2], code[len - 1]]));
+    let offset = len.checked_sub(cbor_len + 2)?;
+    let mut reader = CborReader {
+        data: &code[offset..len - 2],
+        pos: 0,
+    };
+    let (major, entries) = reader.header()?;
+    if major != 5 {
+        return None;
+    }
+
+    let mut metadata = Metadata {
+        offset,
+        length: cbor_len + 2,
+        ..Default::default()
+    };
+    for _ in 0..entries {
+        let Cbor::Text(key) = reader.value()? else {
+            return None;
+        };
+        match (key, reader.value()?) {
+            ("solc", Cbor::Bytes([major, minor, patch])) => {
+                metadata.solc = Some(format!("{major}.{minor}.{patch}"));
+            }
+            // Prerelease compilers store their full version string.
+            ("solc", Cbor::Text(version)) => metadata.solc = Some(version.to_string()),
+            ("ipfs", Cbor::Bytes(hash)) => metadata.ipfs = Some(base58(hash)),
+            ("bzzr0", Cbor::Bytes(hash)) => metadata.bzzr0 = Some(hex::encode_prefixed(hash)),
+            ("bzzr1", Cbor::Bytes(hash)) => metadata.bzzr1 = Some(hex::encode_prefixed(hash)),
+            ("experimental", Cbor::Bool(experimental)) => metadata.experimental = experimental,
+            _ => {}
+        }
+    }
+    (reader.pos == reader.data.len()).then_some(metadata)
+}
+
+/// The CBOR items solc metadata is made of.
+enum Cbor<'a> {
+    Uint,
+    Bytes(&'a [u8]),
+    Text(&'a str),
+    Bool(bool),
+}
+
+struct CborReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> CborReader<'a> {
+    fn take(&mut self, n: usize) -> Option<&'a [u8]> {
+        let bytes = self.data.get(self.pos..self.pos.checked_add(n)?)?;
+        self.pos += n;
+        Some(bytes)
+    }
+
+    /// Read an item header: its major type and argument.
+    fn header(&mut self) -> Option<(u8, u64)> {
+        let initial = self.take(1)?[0];
+        let argument = match initial & 0x1f {
+            info @ 0..=23 => u64::from(info),
+            24 => u64::from(self.take(1)?[0]),
+            25 => u64::from(u16::from_be_bytes(self.take(2)?.try_into().ok()?)),
+            26 => u64::from(u32::from_be_bytes(self.take(4)?.try_into().ok()?)),
+            27 => u64::from_be_bytes(self.take(8)?.try_into().ok()?),
+            _ => return None,
+        };
+        Some((initial >> 5, argument))
+    }
+
+    fn value(&mut self) -> Option<Cbor<'a>> {
+        match self.header()? {
+            (0, _) => Some(Cbor::Uint),
+            (2, len) => Some(Cbor::Bytes(self.take(usize::try_from(len).ok()?)?)),
+            (3, len) => {
+                let bytes = self.take(usize::try_from(len).ok()?)?;
+                Some(Cbor::Text(std::str::from_utf8(bytes).ok()?))
+            }
+            (7, 20) => Some(Cbor::Bool(false)),
+            (7, 21) => Some(Cbor::Bool(true)),
+            _ => None,
+        }
+    }
+}
+
+/// Encode bytes in base58 with the bitcoin alphabet, as IPFS CIDv0 hashes are written.
+fn base58(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 58] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+    // Little-endian base58 digits.
+    let mut digits: Vec<u8> = Vec::new();
+    for &byte in bytes {
+        let mut carry = u32::from(byte);
+        for digit in &mut digits {
+            carry += u32::from(*digit) << 8;
+            *digit = (carry % 58) as u8;
+            carry /= 58;
+        }
+        while carry > 0 {
+            digits.push((carry % 58) as u8);
+            carry /= 58;
+        }
+    }
+    let zeros = bytes.iter().take_while(|&&byte| byte == 0).count();
+    std::iter::repeat_n('1', zeros)
+        .chain(
+            digits
+                .iter()
+                .rev()
+                .map(|&digit| char::from(ALPHABET[digit as usize])),
+        )
+        .collect()
+}
+
+/// Code that only forwards to another address.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Delegation {
+    /// An EIP-1167 minimal proxy, delegating calls to its implementation.
+    Eip1167(Address),
+    /// An EIP-7702 delegated account, running its delegate's code.
+    Eip7702(Address),
+}
+
+/// The standard a delegation follows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DelegationKind {
+    /// An EIP-1167 minimal proxy.
+    Eip1167,
+    /// An EIP-7702 delegated account.
+    Eip7702,
+}
+
+impl Delegation {
+    pub fn kind(&self) -> DelegationKind {
+        match self {
+            Self::Eip1167(_) => DelegationKind::Eip1167,
+            Self::Eip7702(_) => DelegationKind::Eip7702,
+        }
+    }
+
+    pub fn address(&self) -> Address {
+        match self {
+            Self::Eip1167(address) | Self::Eip7702(address) => *address,
+        }
+    }
+}
+
+/// A delegation, as reported in an analysis.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, JsonSchema)]
+pub struct DelegationTarget {
+    pub kind: DelegationKind,
+    /// The address calls are forwarded to.
+    pub address: String,
+}
+
+impl From<Delegation> for DelegationTarget {
+    fn from(delegation: Delegation) -> Self {
+        Self {
+            kind: delegation.kind(),
+            address: delegation.address().to_checksum(None),
+        }
+    }
+}
+
+/// Recognize an EIP-1167 minimal proxy or an EIP-7702 delegation.
+pub fn delegation(code: &[u8]) -> Option<Delegation> {
+    if code.len() == 45 && code.starts_with(&EIP1167_PREFIX) && code.ends_with(&EIP1167_SUFFIX) {
+        return Some(Delegation::Eip1167(Address::from_slice(&code[10..30])));
+    }
+    if code.len() == 23 && code.starts_with(&EIP7702_PREFIX) {
+        return Some(Delegation::Eip7702(Address::from_slice(&code[3..])));
+    }
+    None
+}
+
+/// Selectors compared against in a dispatcher, as `PUSH4 selector EQ`, with an optional `DUPn`
+/// between the two.
+fn dispatcher_selectors<'a>(instructions: &[Instruction<'a>]) -> Vec<&'a [u8]> {
+    let mut selectors: Vec<&[u8]> = Vec::new();
+    for (i, instruction) in instructions.iter().enumerate() {
+        if instruction.opcode != PUSH4 || instruction.push.len() != 4 {
+            continue;
+        }
+        let compared = match instructions.get(i + 1).map(|next| next.opcode) {
+            Some(EQ) => true,
+            Some(0x80..=0x8f) => instructions
+                .get(i + 2)
+                .is_some_and(|next| next.opcode == EQ),
+            _ => false,
+        };
+        if compared && !selectors.contains(&instruction.push) {
+            selectors.push(instruction.push);
+        }
+    }
+    selectors
+}
+
+/// What runtime code does, read from the code alone.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct BytecodeAnalysis {
+    /// The code size in bytes.
+    pub size: usize,
+    /// The keccak256 hash of the code.
+    pub code_hash: String,
+    /// Where the code forwards calls, for EIP-1167 minimal proxies and EIP-7702 delegations.
+    pub delegation: Option<DelegationTarget>,
+    /// The solc metadata at the end of the code.
+    pub metadata: Option<Metadata>,
+    /// The number of instructions before the metadata.
+    pub instruction_count: usize,
+    /// The selectors the dispatcher matches, with their known signatures.
+    pub selectors: Vec<DispatchedSelector>,
+    /// Every PUSH4 constant, selectors of called contracts included.
+    pub push4: Vec<String>,
+    /// Every PUSH32 operand: 32-byte constants such as hashes, storage slots and masks, and the
+    /// immutables solc embeds the same way.
+    pub push32: Vec<Push32>,
+    /// The instructions, when asked for.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub opcodes: Option<Vec<Opcode>>,
+}
+
+/// A selector the dispatcher matches.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, JsonSchema)]
+pub struct DispatchedSelector {
+    pub selector: String,
+    /// The bundled signatures with this selector.
+    pub signatures: Vec<String>,
+}
+
+/// A PUSH32 operand.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, JsonSchema)]
+pub struct Push32 {
+    /// Where the operand starts in the code.
+    pub offset: usize,
+    pub value: String,
+}
+
+/// Analyze runtime code: what it delegates to, its metadata, the selectors its dispatcher
+/// matches, its PUSH4 constants and its PUSH32 operands. Instructions are included when
+/// `opcodes` is set, as they are large.
+pub fn analyze(code: &[u8], opcodes: bool) -> BytecodeAnalysis {
+    let metadata = parse_metadata(code);
+    let executable = &code[..metadata.as_ref().map_or(code.len(), |m| m.offset)];
+    let instructions = disassemble(executable);
+
+    let selectors = dispatcher_selectors(&instructions)
+        .into_iter()
+        .map(|selector| {
+            let selector = hex::encode_prefixed(selector);
+            let signatures = signatures::bundled(&selector, SelectorKind::Function);
+            DispatchedSelector {
+                signatures: signatures.unwrap_or_default().to_vec(),
+                selector,
+            }
+        })
+        .collect();
+
+    let mut push4: Vec<String> = Vec::new();
+    for instruction in &instructions {
+        if instruction.opcode == PUSH4 && instruction.push.len() == 4 {
+            let constant = hex::encode_prefixed(instruction.push);
+            if !push4.contains(&constant) {
+                push4.push(constant);
+            }
+        }
+    }
+
+    // Runtime code alone does not tell immutables from other 32-byte constants, so the operands
+    // are listed without a guess at which is which.
+    let push32 = instructions
+        .iter()
+        .filter(|instruction| instruction.opcode == PUSH32 && instruction.push.len() == 32)
+        .map(|instruction| Push32 {
+            offset: instruction.pc + 1,
+            value: hex::encode_prefixed(instruction.push),
+        })
+        .collect();
+
+    BytecodeAnalysis {
+        size: code.len(),
+        code_hash: keccak256(code).to_string(),
+        delegation: delegation(code).map(DelegationTarget::from),
+        metadata,
+        instruction_count: instructions.len(),
+        selectors,
+        push4,
+        push32,
+        opcodes: opcodes.then(|| instructions.iter().map(Opcode::from).collect()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{Value, json};
+
+    use super::*;
+
+    fn analyze_json(code: &[u8], opcodes: bool) -> Value {
+        serde_json::to_value(analyze(code, opcodes)).unwrap()
+    }
+
+    /// A dispatcher matching transfer(address,uint256), a PUSH32 constant and solc metadata.
+    fn contract() -> Vec<u8> {
+        let mut code = hex!("6080604052").to_vec();
+        code.extend(hex!("60003560e01c"));
+        code.extend(hex!("8063a9059cbb14610030575b"));
+        code.push(PUSH32);
+        code.extend([0x11; 32]);
+        code.extend(hex!("50fe"));
+        code.extend(hex!("a2646970667358221220"));
+        code.extend([0x22; 32]);
+        code.extend(hex!("64736f6c63430008140033"));
+        code
+    }
+
+    #[test]
+    fn test_disassemble() {
+        let instructions = disassemble(&hex!("6080604052610102"));
+        assert_eq!(instructions.len(), 4);
+        assert_eq!(instructions[1].pc, 2);
+        assert_eq!(instructions[1].name(), "PUSH1");
+        assert_eq!(instructions[1].push, [0x40]);
+        assert_eq!(instructions[2].name(), "MSTORE");
+        assert_eq!(
+            serde_json::to_value(Opcode::from(&instructions[3])).unwrap(),
+            json!({ "pc": 5, "op": "PUSH2", "push": "0x0102" })
+        );
+
+        // PUSH immediates cut short by the end of the code are kept as they are.
+        let instructions = disassemble(&hex!("0c6101"));
+        assert_eq!(instructions[0].name(), "0x0c");
+        assert_eq!(instructions[1].push, [0x01]);
+    }
+
+    #[test]
+    fn test_parse_metadata() {
+        let code = contract();
+        let metadata = parse_metadata(&code).unwrap();
+        assert_eq!(metadata.offset, code.len() - 53);
+        assert_eq!(metadata.length, 53);
+        assert_eq!(metadata.solc.as_deref(), Some("0.8.20"));
+        assert!(metadata.ipfs.unwrap().starts_with("Qm"));
+        assert!(!metadata.experimental);
+
+        assert_eq!(parse_metadata(&hex!("6080604052")), None);
+        assert_eq!(parse_metadata(&[]), None);
+    }
+
+    #[test]
+    fn test_base58() {
+        assert_eq!(base58(b"hello world"), "StV1DL6CwTryKyV");
+        assert_eq!(base58(&[0, 0, 1]), "112");
+    }
+
+    #[test]
+    fn test_analyze() {
+        let analysis = analyze_json(&contract(), false);
+        assert_eq!(analysis["delegation"], Value::Null);
+        assert_eq!(analysis["metadata"]["solc"], "0.8.20");
+        assert_eq!(analysis["selectors"][0]["selector"], "0xa9059cbb");
+        assert_eq!(
+            analysis["selectors"][0]["signatures"][0],
+            "transfer(address,uint256)"
+        );
+        assert_eq!(analysis["push4"], json!(["0xa9059cbb"]));
+        assert_eq!(analysis["push32"][0]["offset"], 24);
+        assert_eq!(
+            analysis["push32"][0]["value"],
+            format!("0x{}", "11".repeat(32))
+        );
+        assert!(analysis.get("opcodes").is_none());
+
+        // The metadata is not disassembled.
+        let analysis = analyze_json(&contract(), true);
+        let opcodes = analysis["opcodes"].as_array().unwrap();
+        assert_eq!(opcodes.last().unwrap()["op"], "INVALID");
+    }
+
+    #[test]
+    fn test_analyze_delegation() {
+        let implementation = Address::repeat_byte(0xbe).to_checksum(None);
+        let mut proxy = EIP1167_PREFIX.to_vec();
+        proxy.extend([0xbe; 20]);
+        proxy.extend(EIP1167_SUFFIX);
+        let analysis = analyze_json(&proxy, false);
+        assert_eq!(analysis["delegation"]["kind"], "eip1167");
+        assert_eq!(analysis["delegation"]["address"], implementation);
+
+        let mut delegated = EIP7702_PREFIX.to_vec();
+        delegated.extend([0xbe; 20]);
+        let analysis = analyze_json(&delegated, false);
+        assert_eq!(analysis["delegation"]["kind"], "eip7702");
+        assert_eq!(analysis["delegation"]["address"], implementation);
+    }
+}
@@ -0,0 +1,741 @@
+//! Caching of RPC responses that can never change: state and blocks read at a block hash, or at a
+//! block number the chain has finalized. Reads at `latest`, `pending` and other tags always go
+//! upstream, as do reads at blocks that could still be reorged out.
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, OnceLock},
+    task::{Context, Poll},
+    time::{Duration, Instant, SystemTime},
+};
+
+use alloy_json_rpc::{
+    Id, Request, RequestPacket, Response, ResponsePacket, ResponsePayload, SerializedRequest,
+};
+use alloy_primitives::{B256, U64, keccak256};
+use alloy_transport::{TransportError, TransportFut, TransportResult};
+use serde::de::DeserializeOwned;
+use serde_json::{Value, json, value::RawValue};
+use tower::{Layer, Service};
+
+use crate::common::meta::record_cache_hit;
+
+/// How long a looked up finalized block number is trusted before a newer block number makes it
+/// look up again. Roughly one slot on mainnet.
+const FINALIZED_REFRESH: Duration = Duration::from_secs(12);
+
+struct Entry {
+    response: Arc<str>,
+    last_used: u64,
+}
+
+#[derive(Default)]
+struct Entries {
+    map: HashMap<B256, Entry>,
+    tick: u64,
+}
+
+/// Responses to immutable requests, shared by every provider. Entries are held in memory up to
+/// `max_entries`, the least recently used one being dropped first, and are also written to `path`
+/// if set, so they outlive the server. Past `max_files` files on disk, the oldest tenth of them
+/// is removed; a `max_files` of `0` keeps nothing on disk. A `max_entries` of `0` disables the
+/// cache.
+pub struct ResponseCache {
+    entries: Mutex<Entries>,
+    max_entries: usize,
+    path: Option<PathBuf>,
+    max_files: usize,
+    /// The number of files on disk, counted at the first write.
+    files: Mutex<Option<usize>>,
+}
+
+impl ResponseCache {
+    pub fn new(max_entries: usize, path: Option<PathBuf>, max_files: usize) -> Self {
+        Self {
+            entries: Mutex::new(Entries::default()),
+            max_entries,
+            path: path.filter(|_| max_files > 0),
+            max_files,
+            files: Mutex::new(None),
+        }
+    }
+
+    /// Return the cached response for `key`, reading it from disk if it is not in memory.
+    pub async fn get(&self, key: &B256) -> Option<Arc<str>> {
+        if let Some(response) = self.get_in_memory(key) {
+            return Some(response);
+        }
+        let path = self.file(key)?;
+        let response: Arc<str> = tokio::fs::read_to_string(path).await.ok()?.into();
+        self.insert_in_memory(*key, response.clone());
+        Some(response)
+    }
+
+    /// Cache `response` under `key`, in memory and on disk.
+    pub async fn insert(&self, key: B256, response: &str) {
+        self.insert_in_memory(key, response.into());
+        let Some(path) = self.file(&key) else {
+            return;
+        };
+        if let Some(dir) = path.parent()
+            && let Err(e) = tokio::fs::create_dir_all(dir).await
+        {
+            tracing::debug!("Failed to create response cache directory: {e}");
+            return;
+        }
+        match tokio::fs::write(&path, response).await {
+            Ok(()) => self.count_file().await,
+            Err(e) => tracing::debug!("Failed to write cached response: {e}"),
+        }
+    }
+
+    /// Drop every cached response, returning how many were held in memory and on disk.
+    pub async fn clear(&self) -> (usize, usize) {
+        let entries = std::mem::take(&mut self.lock().map).len();
+        let mut files = 0;
+        if let Some(path) = &self.path {
+            for (file, _) in json_files(path).await {
+                if tokio::fs::remove_file(&file).await.is_ok() {
+                    files += 1;
+                }
+            }
+        }
+        *self.files_lock() = None;
+        (entries, files)
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.max_entries > 0
+    }
+
+    pub fn len(&self) -> usize {
+        self.lock().map.len()
+    }
+
+    fn get_in_memory(&self, key: &B256) -> Option<Arc<str>> {
+        let mut entries = self.lock();
+        entries.tick += 1;
+        let tick = entries.tick;
+        let entry = entries.map.get_mut(key)?;
+        entry.last_used = tick;
+        Some(entry.response.clone())
+    }
+
+    fn insert_in_memory(&self, key: B256, response: Arc<str>) {
+        let mut entries = self.lock();
+        entries.tick += 1;
+        let last_used = entries.tick;
+        if entries.map.len() >= self.max_entries
+            && !entries.map.contains_key(&key)
+            && let Some(oldest) = entries
+                .map
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key, _)| *key)
+        {
+            entries.map.remove(&oldest);
+        }
+        entries.map.insert(
+            key,
+            Entry {
+                response,
+                last_used,
+            },
+        );
+    }
+
+    /// Count a file written to disk, removing the oldest tenth of the files once there are more
+    /// than `max_files`, so that the next removal is some writes away.
+    async fn count_file(&self) {
+        let Some(dir) = &self.path else {
+            return;
+        };
+        let counted = self.files_lock().as_mut().map(|files| {
+            *files += 1;
+            *files
+        });
+        let files = match counted {
+            Some(files) => files,
+            None => {
+                let files = json_files(dir).await.len();
+                *self.files_lock() = Some(files);
+                files
+            }
+        };
+        if files <= self.max_files {
+            return;
+        }
+        let mut files = json_files(dir).await;
+        files.sort_by_key(|(_, modified)| *modified);
+        let excess = files
+            .len()
+            .saturating_sub(self.max_files - self.max_files / 10);
+        let mut removed = 0;
+        for (file, _) in &files[..excess] {
+            match tokio::fs::remove_file(file).await {
+                Ok(()) => removed += 1,
+                Err(e) => tracing::debug!("Failed to remove cached response: {e}"),
+            }
+        }
+        *self.files_lock() = Some(files.len() - removed);
+    }
+
+    fn file(&self, key: &B256) -> Option<PathBuf> {
+        Some(self.path.as_ref()?.join(format!("{key:x}.json")))
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Entries> {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn files_lock(&self) -> std::sync::MutexGuard<'_, Option<usize>> {
+        self.files.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// The cached responses in `dir`, with when each was written.
+async fn json_files(dir: &Path) -> Vec<(PathBuf, SystemTime)> {
+    let mut files = Vec::new();
+    let Ok(mut entries) = tokio::fs::read_dir(dir).await else {
+        return files;
+    };
+    while let Ok(Some(entry)) = entries.next_entry().await {
+        let path = entry.path();
+        if path.extension().is_some_and(|ext| ext == "json") {
+            let modified = entry.metadata().await.and_then(|m| m.modified());
+            files.push((path, modified.unwrap_or(SystemTime::UNIX_EPOCH)));
+        }
+    }
+    files
+}
+
+/// A [`Layer`] answering immutable requests from a [`ResponseCache`], for the provider over
+/// `urls`.
+#[derive(Clone)]
+pub struct CacheLayer {
+    cache: Arc<ResponseCache>,
+    endpoints: String,
+}
+
+impl CacheLayer {
+    pub fn new(cache: Arc<ResponseCache>, urls: &[String]) -> Self {
+        Self {
+            cache,
+            endpoints: urls.join(" "),
+        }
+    }
+}
+
+impl<S> Layer<S> for CacheLayer {
+    type Service = CacheService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        CacheService {
+            inner,
+            cache: self.cache.clone(),
+            chain: Arc::new(ChainState {
+                endpoints: self.endpoints.clone(),
+                identity: OnceLock::new(),
+                finalized: Mutex::new(None),
+            }),
+        }
+    }
+}
+
+/// What the cache knows of the chain behind one provider. The endpoints, chain id and genesis
+/// block hash are part of every key, so neither other networks nor a devnet reusing a chain id,
+/// or restarted behind the same URL, are answered with this chain's responses.
+struct ChainState {
+    endpoints: String,
+    /// The chain id and genesis block hash.
+    identity: OnceLock<(u64, B256)>,
+    /// The last finalized block number looked up, and when.
+    finalized: Mutex<Option<(u64, Instant)>>,
+}
+
+/// A service answering immutable requests from the cache before sending them upstream.
+#[derive(Clone)]
+pub struct CacheService<S> {
+    inner: S,
+    cache: Arc<ResponseCache>,
+    chain: Arc<ChainState>,
+}
+
+impl<S> Service<RequestPacket> for CacheService<S>
+where
+    S: Service<RequestPacket, Response = ResponsePacket, Error = TransportError>
+        + Clone
+        + Send
+        + Sync
+        + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = ResponsePacket;
+    type Error = TransportError;
+    type Future = TransportFut<'static>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: RequestPacket) -> Self::Future {
+        let this = self.clone();
+        Box::pin(this.send(request))
+    }
+}
+
+impl<S> CacheService<S>
+where
+    S: Service<RequestPacket, Response = ResponsePacket, Error = TransportError> + Clone,
+{
+    async fn send(mut self, request: RequestPacket) -> TransportResult<ResponsePacket> {
+        let key = match &request {
+            RequestPacket::Single(single) if self.cache.is_enabled() => {
+                self.key(single).await.map(|key| (key, single.id().clone()))
+            }
+            _ => None,
+        };
+        let Some((key, id)) = key else {
+            return self.inner.call(request).await;
+        };
+
+        if let Some(cached) = self.cache.get(&key).await
+            && let Ok(payload) = RawValue::from_string(cached.to_string())
+        {
+            record_cache_hit();
+            return Ok(ResponsePacket::Single(Response {
+                id,
+                payload: ResponsePayload::Success(payload),
+            }));
+        }
+
+        let response = self.inner.call(request).await?;
+        // A block or state that is not known yet comes back as `null`, and may exist later.
+        if let ResponsePacket::Single(Response {
+            payload: ResponsePayload::Success(payload),
+            ..
+        }) = &response
+            && payload.get() != "null"
+        {
+            self.cache.insert(key, payload.get()).await;
+        }
+        Ok(response)
+    }
+
+    /// The cache key of `request`, or `None` if its response may change.
+    async fn key(&mut self, request: &SerializedRequest) -> Option<B256> {
+        let params: Vec<Value> = serde_json::from_str(request.params()?.get()).ok()?;
+        let block = block_param(request.method(), &params)?;
+        let immutable = match block_ref(block) {
+            BlockRef::Hash => true,
+            BlockRef::Number(number) => self.is_finalized(number).await,
+            BlockRef::Tag => false,
+        };
+        if !immutable {
+            return None;
+        }
+        let (chain_id, genesis) = self.identity().await?;
+        Some(keccak256(format!(
+            "{}:{chain_id}:{genesis:x}:{}:{}",
+            self.chain.endpoints,
+            request.method(),
+            request.params()?.get()
+        )))
+    }
+
+    async fn identity(&mut self) -> Option<(u64, B256)> {
+        if let Some(identity) = self.chain.identity.get() {
+            return Some(*identity);
+        }
+        let chain_id = self
+            .internal::<U64>("eth_chainId", json!([]))
+            .await?
+            .to::<u64>();
+        let genesis = self
+            .internal::<Value>("eth_getBlockByNumber", json!(["0x0", false]))
+            .await?;
+        let genesis = genesis.get("hash")?.as_str()?.parse::<B256>().ok()?;
+        Some(*self.chain.identity.get_or_init(|| (chain_id, genesis)))
+    }
+
+    /// Whether block `number` is finalized, looking up the finalized block number again if the
+    /// last lookup is stale. Nodes that know no finalized block never finalize anything.
+    async fn is_finalized(&mut self, number: u64) -> bool {
+        let known = *self
+            .chain
+            .finalized
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        match known {
+            Some((finalized, _)) if number <= finalized => return true,
+            Some((_, at)) if at.elapsed() < FINALIZED_REFRESH => return false,
+            _ => {}
+        }
+        let finalized = self
+            .internal::<Value>("eth_getBlockByNumber", json!(["finalized", false]))
+            .await
+            .and_then(|block| parse_quantity(block.get("number")?.as_str()?))
+            .unwrap_or(0);
+        *self
+            .chain
+            .finalized
+            .lock()
+            .unwrap_or_else(|e| e.into_inner()) = Some((finalized, Instant::now()));
+        number <= finalized
+    }
+
+    /// Send a request of the cache's own upstream, returning its result if it succeeded.
+    async fn internal<T: DeserializeOwned>(
+        &mut self,
+        method: &'static str,
+        params: Value,
+    ) -> Option<T> {
+        let request = Request::new(method, Id::String(format!("cache-{method}")), params)
+            .serialize()
+            .ok()?;
+        match self.inner.call(RequestPacket::Single(request)).await.ok()? {
+            ResponsePacket::Single(Response {
+                payload: ResponsePayload::Success(payload),
+                ..
+            }) => serde_json::from_str(payload.get()).ok(),
+            _ => None,
+        }
+    }
+}
+
+/// The block parameter of a cacheable method, or `None` if the method is not cached or the block
+/// is left to the node's default of `latest`.
+fn block_param<'a>(method: &str, params: &'a [Value]) -> Option<&'a Value> {
+    let index = match method {
+        "eth_getBlockByNumber" | "eth_getBlockByHash" | "debug_storageRangeAt" => 0,
+        "eth_getBalance" | "eth_getCode" | "eth_getTransactionCount" | "eth_call" => 1,
+        "eth_getStorageAt" | "eth_getProof" => 2,
+        _ => return None,
+    };
+    params.get(index)
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum BlockRef {
+    Hash,
+    Number(u64),
+    Tag,
+}
+
+/// Read a block parameter, either a bare hash, number or tag, or an EIP-1898 object.
+fn block_ref(block: &Value) -> BlockRef {
+    match block {
+        Value::String(s) if s.len() == 66 && s.starts_with("0x") => BlockRef::Hash,
+        Value::String(s) => parse_quantity(s).map_or(BlockRef::Tag, BlockRef::Number),
+        Value::Object(object) if object.contains_key("blockHash") => BlockRef::Hash,
+        Value::Object(object) => object.get("blockNumber").map_or(BlockRef::Tag, block_ref),
+        _ => BlockRef::Tag,
+    }
+}
+
+fn parse_quantity(s: &str) -> Option<u64> {
+    u64::from_str_radix(s.strip_prefix("0x")?, 16).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    use super::*;
+    use crate::common::meta::track;
+
+    fn request(method: &'static str, params: Value) -> RequestPacket {
+        RequestPacket::Single(
+            Request::new(method, Id::Number(7), params)
+                .serialize()
+                .unwrap(),
+        )
+    }
+
+    fn result(packet: &ResponsePacket) -> Value {
+        match packet {
+            ResponsePacket::Single(Response {
+                payload: ResponsePayload::Success(payload),
+                ..
+            }) => serde_json::from_str(payload.get()).unwrap(),
+            _ => panic!("unexpected response: {packet:?}"),
+        }
+    }
+
+    /// A node on chain 1 whose genesis block hash repeats `genesis` and whose finalized block is
+    /// 100. Balances are `0x2a`, and blocks above 1000 do not exist yet.
+    #[derive(Clone, Default)]
+    struct Node {
+        genesis: u8,
+        reads: Arc<AtomicU32>,
+    }
+
+    impl Service<RequestPacket> for Node {
+        type Response = ResponsePacket;
+        type Error = TransportError;
+        type Future = TransportFut<'static>;
+
+        fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, request: RequestPacket) -> Self::Future {
+            let RequestPacket::Single(request) = request else {
+                panic!("the cache only sends single requests");
+            };
+            let params: Value = serde_json::from_str(request.params().unwrap().get()).unwrap();
+            let result = match (request.method(), &params[0]) {
+                ("eth_chainId", _) => json!("0x1"),
+                ("eth_getBlockByNumber", Value::String(tag)) if tag == "finalized" => {
+                    json!({ "number": "0x64" })
+                }
+                ("eth_getBlockByNumber", Value::String(number)) if number == "0x0" => {
+                    json!({ "number": "0x0", "hash": B256::repeat_byte(self.genesis) })
+                }
+                ("eth_getBlockByNumber", Value::String(number)) => {
+                    self.reads.fetch_add(1, Ordering::SeqCst);
+                    match parse_quantity(number) {
+                        Some(n) if n <= 1000 => json!({ "number": number }),
+                        _ => Value::Null,
+                    }
+                }
+                _ => {
+                    self.reads.fetch_add(1, Ordering::SeqCst);
+                    json!("0x2a")
+                }
+            };
+            let payload = RawValue::from_string(result.to_string()).unwrap();
+            let id = request.id().clone();
+            Box::pin(async move {
+                Ok(ResponsePacket::Single(Response {
+                    id,
+                    payload: ResponsePayload::Success(payload),
+                }))
+            })
+        }
+    }
+
+    fn service(node: &Node) -> CacheService<Node> {
+        service_at(
+            node,
+            Arc::new(ResponseCache::new(16, None, 0)),
+            "http://node",
+        )
+    }
+
+    fn service_at(node: &Node, cache: Arc<ResponseCache>, url: &str) -> CacheService<Node> {
+        CacheLayer::new(cache, &[url.to_string()]).layer(node.clone())
+    }
+
+    #[tokio::test]
+    async fn test_caches_reads_at_block_hash() {
+        let node = Node::default();
+        let hash = format!("0x{}", "ab".repeat(32));
+        let params = json!(["0x0000000000000000000000000000000000000001", { "blockHash": hash }]);
+        let service = service(&node);
+        for i in 0..3 {
+            let (response, meta) = track(
+                service
+                    .clone()
+                    .send(request("eth_getBalance", params.clone())),
+            )
+            .await;
+            assert_eq!(result(&response.unwrap()), "0x2a");
+            assert_eq!(meta.cache_hits, usize::from(i > 0));
+        }
+        assert_eq!(node.reads.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_skips_mutable_blocks() {
+        let node = Node::default();
+        let service = service(&node);
+        let address = "0x0000000000000000000000000000000000000001";
+        for block in [
+            json!("latest"),
+            json!("pending"),
+            json!("0x65"),
+            json!("finalized"),
+        ] {
+            service
+                .clone()
+                .send(request("eth_getBalance", json!([address, block])))
+                .await
+                .unwrap();
+            service
+                .clone()
+                .send(request("eth_getBalance", json!([address, block])))
+                .await
+                .unwrap();
+        }
+        service
+            .clone()
+            .send(request("eth_getBalance", json!([address])))
+            .await
+            .unwrap();
+        service
+            .clone()
+            .send(request("eth_getBalance", json!([address])))
+            .await
+            .unwrap();
+        assert_eq!(node.reads.load(Ordering::SeqCst), 10);
+        assert_eq!(service.cache.len(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_caches_finalized_blocks() {
+        let node = Node::default();
+        let service = service(&node);
+        for _ in 0..2 {
+            let (response, _) = track(
+                service
+                    .clone()
+                    .send(request("eth_getBlockByNumber", json!(["0x64", false]))),
+            )
+            .await;
+            assert_eq!(result(&response.unwrap())["number"], "0x64");
+        }
+        let (_, meta) = track(
+            service
+                .clone()
+                .send(request("eth_getBlockByNumber", json!(["0x64", false]))),
+        )
+        .await;
+        assert_eq!(meta.cache_hits, 1);
+        assert_eq!(node.reads.load(Ordering::SeqCst), 1);
+
+        // A different request at the same block is a different entry.
+        service
+            .clone()
+            .send(request("eth_getBlockByNumber", json!(["0x64", true])))
+            .await
+            .unwrap();
+        assert_eq!(node.reads.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_does_not_cache_missing_results() {
+        let node = Node::default();
+        let service = service(&node);
+        let missing = request("eth_getBlockByNumber", json!(["0x10000", false]));
+        service.clone().send(missing.clone()).await.unwrap();
+        service.clone().send(missing).await.unwrap();
+        assert_eq!(node.reads.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_disabled_cache() {
+        let node = Node::default();
+        let service = service_at(
+            &node,
+            Arc::new(ResponseCache::new(0, None, 0)),
+            "http://node",
+        );
+        let hash = format!("0x{}", "ab".repeat(32));
+        let balance = request(
+            "eth_getBalance",
+            json!(["0x0000000000000000000000000000000000000001", hash]),
+        );
+        service.clone().send(balance.clone()).await.unwrap();
+        service.clone().send(balance).await.unwrap();
+        assert_eq!(node.reads.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_keys_by_endpoints_and_genesis() {
+        let cache = Arc::new(ResponseCache::new(16, None, 0));
+        let hash = format!("0x{}", "ab".repeat(32));
+        let balance = request(
+            "eth_getBalance",
+            json!(["0x0000000000000000000000000000000000000001", hash]),
+        );
+        let mainnet = Node::default();
+        let devnet = Node {
+            genesis: 1,
+            ..Node::default()
+        };
+        for (node, url) in [
+            (&mainnet, "http://mainnet"),
+            (&mainnet, "http://other"),
+            (&devnet, "http://mainnet"),
+        ] {
+            let (_, meta) = track(service_at(node, cache.clone(), url).send(balance.clone())).await;
+            assert_eq!(meta.cache_hits, 0);
+        }
+        let (_, meta) =
+            track(service_at(&mainnet, cache.clone(), "http://mainnet").send(balance)).await;
+        assert_eq!(meta.cache_hits, 1);
+        assert_eq!(cache.len(), 3);
+    }
+
+    #[test]
+    fn test_block_ref() {
+        let hash = format!("0x{}", "ab".repeat(32));
+        assert_eq!(block_ref(&json!(hash)), BlockRef::Hash);
+        assert_eq!(
+            block_ref(&json!({ "blockHash": hash, "requireCanonical": true })),
+            BlockRef::Hash
+        );
+        assert_eq!(block_ref(&json!("0x10")), BlockRef::Number(16));
+        assert_eq!(
+            block_ref(&json!({ "blockNumber": "0x10" })),
+            BlockRef::Number(16)
+        );
+        assert_eq!(block_ref(&json!("latest")), BlockRef::Tag);
+        assert_eq!(block_ref(&json!("safe")), BlockRef::Tag);
+        assert_eq!(block_ref(&Value::Null), BlockRef::Tag);
+    }
+
+    #[tokio::test]
+    async fn test_cache_evicts_least_recently_used() {
+        let cache = ResponseCache::new(2, None, 0);
+        let (a, b, c) = (
+            B256::repeat_byte(1),
+            B256::repeat_byte(2),
+            B256::repeat_byte(3),
+        );
+        cache.insert(a, "1").await;
+        cache.insert(b, "2").await;
+        cache.get(&a).await.unwrap();
+        cache.insert(c, "3").await;
+        assert_eq!(cache.len(), 2);
+        assert!(cache.get(&a).await.is_some());
+        assert!(cache.get(&b).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_cache_persists_to_disk() {
+        let path = std::env::temp_dir().join(format!("cast-mcp-cache-{}", uuid::Uuid::new_v4()));
+        let key = B256::repeat_byte(1);
+        ResponseCache::new(4, Some(path.clone()), 4)
+            .insert(key, "\"0x2a\"")
+            .await;
+
+        let cache = ResponseCache::new(4, Some(path.clone()), 4);
+        assert_eq!(cache.get(&key).await.as_deref(), Some("\"0x2a\""));
+        assert_eq!(cache.clear().await, (1, 1));
+        assert!(cache.get(&key).await.is_none());
+        std::fs::remove_dir_all(path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_disk_is_bounded() {
+        let path = std::env::temp_dir().join(format!("cast-mcp-cache-{}", uuid::Uuid::new_v4()));
+        let cache = ResponseCache::new(4, Some(path.clone()), 10);
+        for i in 0..11 {
+            cache.insert(B256::repeat_byte(i), "\"0x2a\"").await;
+        }
+        // The oldest tenth went, leaving nine of the eleven files.
+        assert_eq!(json_files(&path).await.len(), 9);
+        cache.insert(B256::repeat_byte(11), "\"0x2a\"").await;
+        assert_eq!(json_files(&path).await.len(), 10);
+        std::fs::remove_dir_all(&path).unwrap();
+
+        // No files at all keeps the cache in memory.
+        let cache = ResponseCache::new(4, Some(path.clone()), 0);
+        cache.insert(B256::repeat_byte(1), "\"0x2a\"").await;
+        assert!(cache.get(&B256::repeat_byte(1)).await.is_some());
+        assert!(!path.exists());
+    }
+}
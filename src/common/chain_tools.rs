@@ -1,118 +1,96 @@
 use alloy_provider::Provider;
 use cast::Cast;
-use foundry_cli::{opts::RpcOpts, utils, utils::LoadConfig};
 use rmcp::{
     ErrorData, handler::server::wrapper::Parameters, model::*, schemars, tool, tool_router,
 };
 use serde_default::DefaultFromSerde;
-use serde_json::Value;
 
-use crate::common::{common::*, server::Server};
+use crate::common::{
+    common::*,
+    error::{rpc_error, upstream_error},
+    server::Server,
+};
 
 #[derive(Debug, Clone, serde::Deserialize, DefaultFromSerde, schemars::JsonSchema)]
 pub struct ChainArgs {
-    /// The RPC endpoint, default value is http://localhost:8545.
-    #[serde(default = "default_rpc")]
-    pub rpc: String,
+    #[serde(flatten)]
+    pub endpoint: EndpointArgs,
+}
+
+#[derive(Debug, serde::Serialize, schemars::JsonSchema)]
+pub struct ChainOutput {
+    /// The symbolic name of the chain, e.g. ethmainnet, or unknown.
+    pub chain: String,
+}
+
+#[derive(Debug, serde::Serialize, schemars::JsonSchema)]
+pub struct ChainIdOutput {
+    /// The EIP-155 chain ID.
+    pub chain_id: u64,
+}
+
+#[derive(Debug, serde::Serialize, schemars::JsonSchema)]
+pub struct ClientOutput {
+    /// The client version string reported by web3_clientVersion.
+    pub version: String,
 }
 
 #[tool_router(router = chain_router, vis = "pub")]
 impl Server {
-    #[tool(description = "Get the symbolic name of the current chain ")]
+    #[tool(
+        description = "Get the symbolic name of the current chain ",
+        output_schema = output_schema::<ChainOutput>()
+    )]
     async fn chain(
         &self,
-        Parameters(ChainArgs { rpc: rpc_url }): Parameters<ChainArgs>,
+        Parameters(args): Parameters<ChainArgs>,
     ) -> Result<CallToolResult, ErrorData> {
-        let rpc = RpcOpts {
-            url: Some(rpc_url),
-            accept_invalid_certs: false,
-            no_proxy: false,
-            flashbots: false,
-            jwt_secret: None,
-            rpc_timeout: None,
-            rpc_headers: None,
-            curl: false,
-        };
-        let config = rpc.load_config().map_err(|e| {
-            ErrorData::parse_error("Invalid RPC URL", Some(Value::String(e.to_string())))
-        })?;
-
-        let provider = utils::get_provider(&config).map_err(|e| {
-            ErrorData::internal_error("Failed to get provider", Some(Value::String(e.to_string())))
-        })?;
+        let provider = self.provider(&args.endpoint)?;
 
         let cli = Cast::new(provider);
-        let chain = cli.chain().await.map_err(|e| {
-            ErrorData::internal_error("Failed to get chain", Some(Value::String(e.to_string())))
-        })?;
+        let chain = cli
+            .chain()
+            .await
+            .map_err(|e| upstream_error("Failed to get chain", &*e))?;
 
-        Ok(CallToolResult::success(vec![Content::text(chain)]))
+        structured(chain.clone(), &ChainOutput { chain })
     }
 
-    #[tool(description = "Get the chain ID of the current chain")]
+    #[tool(
+        description = "Get the chain ID of the current chain",
+        output_schema = output_schema::<ChainIdOutput>()
+    )]
     async fn chain_id(
         &self,
-        Parameters(ChainArgs { rpc: rpc_url }): Parameters<ChainArgs>,
+        Parameters(args): Parameters<ChainArgs>,
     ) -> Result<CallToolResult, ErrorData> {
-        let rpc = RpcOpts {
-            url: Some(rpc_url),
-            accept_invalid_certs: false,
-            no_proxy: false,
-            flashbots: false,
-            jwt_secret: None,
-            rpc_timeout: None,
-            rpc_headers: None,
-            curl: false,
-        };
-        let config = rpc.load_config().map_err(|e| {
-            ErrorData::parse_error("Invalid RPC URL", Some(Value::String(e.to_string())))
-        })?;
-
-        let provider = utils::get_provider(&config).map_err(|e| {
-            ErrorData::internal_error("Failed to get provider", Some(Value::String(e.to_string())))
-        })?;
+        let provider = self.provider(&args.endpoint)?;
 
         let cli = Cast::new(provider);
-        let chain_id = cli.chain_id().await.map_err(|e| {
-            ErrorData::internal_error("Failed to get chain", Some(Value::String(e.to_string())))
-        })?;
+        let chain_id = cli
+            .chain_id()
+            .await
+            .map_err(|e| upstream_error("Failed to get chain", &*e))?;
 
-        Ok(CallToolResult::success(vec![Content::text(
-            chain_id.to_string(),
-        )]))
+        structured(chain_id.to_string(), &ChainIdOutput { chain_id })
     }
 
-    #[tool(description = "Get the current client version. ")]
+    #[tool(
+        description = "Get the current client version. ",
+        output_schema = output_schema::<ClientOutput>()
+    )]
     async fn client(
         &self,
-        Parameters(ChainArgs { rpc: rpc_url }): Parameters<ChainArgs>,
+        Parameters(args): Parameters<ChainArgs>,
     ) -> Result<CallToolResult, ErrorData> {
-        let rpc = RpcOpts {
-            url: Some(rpc_url),
-            accept_invalid_certs: false,
-            no_proxy: false,
-            flashbots: false,
-            jwt_secret: None,
-            rpc_timeout: None,
-            rpc_headers: None,
-            curl: false,
-        };
-        let config = rpc.load_config().map_err(|e| {
-            ErrorData::parse_error("Invalid RPC URL", Some(Value::String(e.to_string())))
-        })?;
-
-        let provider = utils::get_provider(&config).map_err(|e| {
-            ErrorData::internal_error("Failed to get provider", Some(Value::String(e.to_string())))
-        })?;
-
-        let version = provider.get_client_version().await.map_err(|e| {
-            ErrorData::internal_error(
-                "Failed to get client version",
-                Some(Value::String(e.to_string())),
-            )
-        })?;
-
-        Ok(CallToolResult::success(vec![Content::text(version)]))
+        let provider = self.provider(&args.endpoint)?;
+
+        let version = provider
+            .get_client_version()
+            .await
+            .map_err(|e| rpc_error("Failed to get client version", &e))?;
+
+        structured(version.clone(), &ClientOutput { version })
     }
 }
 
@@ -127,16 +105,16 @@ mod tests {
     #[test]
     fn test_chain_args_default() {
         let args = ChainArgs::default();
-        assert_eq!(args.rpc, "http://localhost:8545");
+        assert_eq!(args.endpoint.rpc, None);
     }
 
     #[test]
     fn test_chain_args_clone() {
         let original = ChainArgs {
-            rpc: "https://test.com".to_string(),
+            endpoint: EndpointArgs::from_rpc("https://test.com"),
         };
         let cloned = original.clone();
-        assert_eq!(original.rpc, cloned.rpc);
+        assert_eq!(original.endpoint.rpc, cloned.endpoint.rpc);
     }
 
     #[tokio::test]
@@ -145,7 +123,7 @@ mod tests {
 
         // Test with localhost (may succeed or fail, but we check structure)
         let args = ChainArgs {
-            rpc: "https://mainnet.infura.io".to_string(),
+            endpoint: EndpointArgs::from_rpc("https://mainnet.infura.io"),
         };
         let params = Parameters(args);
 
@@ -252,7 +230,7 @@ mod tests {
                 let server_clone = Arc::clone(&server);
                 tokio::spawn(async move {
                     let args = ChainArgs {
-                        rpc: "https://mainnet.infura.io".to_string(),
+                        endpoint: EndpointArgs::from_rpc("https://mainnet.infura.io"),
                     };
                     let params = Parameters(args);
 
@@ -286,7 +264,7 @@ mod tests {
     #[test]
     fn test_chain_args_serialization() {
         let args = ChainArgs {
-            rpc: "https://mainnet.infura.io".to_string(),
+            endpoint: EndpointArgs::from_rpc("https://mainnet.infura.io"),
         };
 
         // Test that args implements the required traits
@@ -299,7 +277,7 @@ mod tests {
     #[test]
     fn test_chain_args_debug_format() {
         let args = ChainArgs {
-            rpc: "test-rpc".to_string(),
+            endpoint: EndpointArgs::from_rpc("test-rpc"),
         };
         let debug_output = format!("{:?}", args);
         assert!(debug_output.contains("ChainArgs"));
@@ -1,13 +1,102 @@
-use std::io;
+use std::{io, str::FromStr, sync::Arc};
 
 use alloy_ens::ProviderEnsExt;
-use alloy_primitives::{Address, B256, Keccak256, address, hex};
-use alloy_rpc_types::{BlockId, BlockNumberOrTag::Latest};
+use alloy_primitives::{
+    Address, B256, Keccak256, U256, address, hex,
+    utils::{ParseUnits, Unit},
+};
+use alloy_provider::{
+    Provider,
+    network::{AnyNetwork, Network},
+};
+use alloy_rpc_types::{BlockId, BlockNumberOrTag, RpcBlockHash};
+use rmcp::{
+    ErrorData,
+    model::{CallToolResult, Content, JsonObject},
+};
+use serde::Serialize;
+use serde_json::{Value, json};
+
+use crate::common::{
+    config::redact_headers,
+    error::{
+        ErrorKind, internal_error, invalid_input, not_found, rpc_error, tool_error, transport_error,
+    },
+    provider::RetryProvider,
+};
 
 //use alloy_ens::contract::EnsError;
 pub fn default_rpc() -> String {
     "http://localhost:8545".to_string()
 }
+
+/// The endpoint a tool talks to, flattened into every tool's arguments.
+#[derive(Clone, Default, serde::Deserialize, schemars::JsonSchema)]
+pub struct EndpointArgs {
+    /// The RPC endpoint URL. Takes precedence over `network`.
+    #[serde(default)]
+    pub rpc: Option<String>,
+
+    /// A network configured on the server, e.g. mainnet, sepolia or local. Defaults to the server's default network, or http://localhost:8545 if none is configured.
+    #[serde(default)]
+    pub network: Option<String>,
+
+    /// Headers to send with every RPC request, as `Name: value`. Replaces the network's headers.
+    #[serde(default)]
+    pub rpc_headers: Option<Vec<String>>,
+
+    /// The hex encoded JWT secret used to authenticate with the endpoint. Replaces the network's secret.
+    #[serde(default)]
+    pub jwt_secret: Option<String>,
+
+    /// Timeout for RPC requests in seconds. Replaces the network's timeout.
+    #[serde(default)]
+    pub rpc_timeout: Option<u64>,
+
+    /// Accept invalid TLS certificates from the endpoint.
+    #[serde(default)]
+    pub accept_invalid_certs: Option<bool>,
+
+    /// Bypass proxies configured in the environment.
+    #[serde(default)]
+    pub no_proxy: Option<bool>,
+}
+
+impl std::fmt::Debug for EndpointArgs {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EndpointArgs")
+            .field("rpc", &self.rpc)
+            .field("network", &self.network)
+            .field(
+                "rpc_headers",
+                &self.rpc_headers.as_deref().map(redact_headers),
+            )
+            .field(
+                "jwt_secret",
+                &self.jwt_secret.as_ref().map(|_| "<redacted>"),
+            )
+            .field("rpc_timeout", &self.rpc_timeout)
+            .field("accept_invalid_certs", &self.accept_invalid_certs)
+            .field("no_proxy", &self.no_proxy)
+            .finish()
+    }
+}
+
+impl EndpointArgs {
+    pub fn from_rpc(url: impl Into<String>) -> Self {
+        Self {
+            rpc: Some(url.into()),
+            ..Default::default()
+        }
+    }
+
+    pub fn from_network(name: impl Into<String>) -> Self {
+        Self {
+            network: Some(name.into()),
+            ..Default::default()
+        }
+    }
+}
 pub fn default_int() -> String {
     "int256".to_string()
 }
@@ -16,53 +105,720 @@ pub fn default_uint() -> String {
     "uint256".to_string()
 }
 
-pub fn get_block_id(block: Option<String>) -> BlockId {
+pub fn default_unit() -> UnitSpec {
+    UnitSpec::Named(UnitName::Ether)
+}
+
+pub fn default_base() -> Base {
+    Base::Hex
+}
+
+/// A denomination, by name or by its number of decimals.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize, schemars::JsonSchema)]
+#[serde(untagged)]
+pub enum UnitSpec {
+    Named(UnitName),
+    Decimals(#[schemars(range(max = 77))] u8),
+}
+
+impl UnitSpec {
+    /// The unit, or an invalid input error for more than 77 decimals.
+    pub fn unit(self) -> Result<Unit, ErrorData> {
+        parse_unit(&self.to_string())
+    }
+}
+
+impl std::fmt::Display for UnitSpec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Named(name) => f.write_str(name.as_str()),
+            Self::Decimals(decimals) => write!(f, "{decimals}"),
+        }
+    }
+}
+
+/// The named denominations of ether.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize, schemars::JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum UnitName {
+    Wei,
+    Kwei,
+    Mwei,
+    Gwei,
+    #[serde(alias = "twei")]
+    Szabo,
+    #[serde(alias = "pwei")]
+    Finney,
+    #[serde(alias = "eth")]
+    Ether,
+}
+
+impl UnitName {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Wei => "wei",
+            Self::Kwei => "kwei",
+            Self::Mwei => "mwei",
+            Self::Gwei => "gwei",
+            Self::Szabo => "szabo",
+            Self::Finney => "finney",
+            Self::Ether => "ether",
+        }
+    }
+}
+
+/// An integer base. The numbers 2, 8, 10 and 16 are accepted as strings too.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize, schemars::JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Base {
+    #[serde(alias = "2")]
+    Bin,
+    #[serde(alias = "8")]
+    Oct,
+    #[serde(alias = "10")]
+    Dec,
+    #[serde(alias = "16")]
+    Hex,
+}
+
+impl Base {
+    /// The spelling `SimpleCast` expects.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Bin => "bin",
+            Self::Oct => "oct",
+            Self::Dec => "dec",
+            Self::Hex => "hex",
+        }
+    }
+}
+
+/// The deterministic deployment proxy most CREATE2 factories deploy through.
+pub fn default_create2_deployer() -> String {
+    "0x4e59b44847b379578588920cA78FbF26c0B4956C".to_string()
+}
+
+const BLOCK_FORMATS: &str = "a block number (decimal or 0x-prefixed hex), a 32-byte block hash, one of the tags earliest, finalized, safe, latest or pending, a tag with an offset such as latest-10, an EIP-1898 object such as {\"blockHash\": \"0x...\", \"requireCanonical\": true}, a unix timestamp such as @1700000000, or an ISO 8601 date";
+
+/// The block to query at: a number (decimal or 0x hex), a block hash, a tag (earliest, finalized, safe, latest, pending), a tag with an offset like latest-10, an EIP-1898 object, a unix timestamp like @1700000000 or an ISO 8601 date. Defaults to latest.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Deserialize, schemars::JsonSchema)]
+#[schemars(inline)]
+pub struct BlockParam(pub String);
+
+impl From<&str> for BlockParam {
+    fn from(block: &str) -> Self {
+        Self(block.to_string())
+    }
+}
+
+/// A block reference as given in a tool call, before it is resolved against a node.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BlockSpec {
+    Id(BlockId),
+    /// A tag minus a number of blocks, e.g. `latest-10`.
+    Offset(BlockNumberOrTag, u64),
+    /// A unix timestamp in seconds, resolved to the block closest to it.
+    Timestamp(u64),
+}
+
+/// Parse a block identifier, rejecting anything it does not understand instead of falling back
+/// to `latest`.
+pub fn parse_block_spec(input: &str) -> Result<BlockSpec, ErrorData> {
+    let trimmed = input.trim();
+    let lower = trimmed.to_lowercase();
+
+    if let Some(tag) = parse_block_tag(&lower) {
+        return Ok(BlockSpec::Id(BlockId::Number(tag)));
+    }
+
+    if trimmed.starts_with('{') {
+        return serde_json::from_str::<BlockId>(trimmed)
+            .map(BlockSpec::Id)
+            .map_err(|e| invalid_block(input, Some(e.to_string())));
+    }
+
+    if let Some((tag, offset)) = lower.split_once('-') {
+        if let Some(tag) = parse_block_tag(tag.trim()) {
+            let offset = offset
+                .trim()
+                .parse::<u64>()
+                .map_err(|_| invalid_block(input, Some("invalid block offset".to_string())))?;
+            return Ok(BlockSpec::Offset(tag, offset));
+        }
+    }
+
+    if let Some(timestamp) = trimmed.strip_prefix('@') {
+        return timestamp
+            .parse::<u64>()
+            .map(BlockSpec::Timestamp)
+            .map_err(|_| invalid_block(input, Some("invalid unix timestamp".to_string())));
+    }
+
+    if let Some(digits) = lower.strip_prefix("0x") {
+        if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(invalid_block(input, Some("invalid hex".to_string())));
+        }
+        if digits.len() == 64 {
+            let hash: B256 = trimmed
+                .parse()
+                .map_err(|_| invalid_block(input, Some("invalid block hash".to_string())))?;
+            return Ok(BlockSpec::Id(BlockId::Hash(RpcBlockHash::from_hash(
+                hash, None,
+            ))));
+        }
+        if digits.len() <= 16 {
+            let num = u64::from_str_radix(digits, 16).expect("validated hex digits");
+            return Ok(BlockSpec::Id(BlockId::Number(num.into())));
+        }
+        return Err(invalid_block(
+            input,
+            Some("hex input is neither a block number nor a 32-byte block hash".to_string()),
+        ));
+    }
+
+    if !trimmed.is_empty() && trimmed.chars().all(|c| c.is_ascii_digit()) {
+        return trimmed
+            .parse::<u64>()
+            .map(|num| BlockSpec::Id(BlockId::Number(num.into())))
+            .map_err(|_| invalid_block(input, Some("block number out of range".to_string())));
+    }
+
+    if let Some(timestamp) = parse_date(trimmed) {
+        return Ok(BlockSpec::Timestamp(timestamp));
+    }
+
+    Err(invalid_block(input, None))
+}
+
+fn parse_block_tag(input: &str) -> Option<BlockNumberOrTag> {
+    match input {
+        "latest" => Some(BlockNumberOrTag::Latest),
+        "earliest" => Some(BlockNumberOrTag::Earliest),
+        "pending" => Some(BlockNumberOrTag::Pending),
+        "safe" => Some(BlockNumberOrTag::Safe),
+        "finalized" => Some(BlockNumberOrTag::Finalized),
+        _ => None,
+    }
+}
+
+/// Parse an RFC 3339 date time or a plain `YYYY-MM-DD` date (midnight UTC) to a unix timestamp.
+fn parse_date(input: &str) -> Option<u64> {
+    if let Ok(datetime) = chrono::DateTime::parse_from_rfc3339(input) {
+        return u64::try_from(datetime.timestamp()).ok();
+    }
+    if let Ok(datetime) = chrono::NaiveDateTime::parse_from_str(input, "%Y-%m-%dT%H:%M:%S") {
+        return u64::try_from(datetime.and_utc().timestamp()).ok();
+    }
+    if let Ok(date) = chrono::NaiveDate::parse_from_str(input, "%Y-%m-%d") {
+        return u64::try_from(date.and_hms_opt(0, 0, 0)?.and_utc().timestamp()).ok();
+    }
+    None
+}
+
+fn invalid_block(input: &str, reason: Option<String>) -> ErrorData {
+    let mut data = json!({ "input": input, "expected": BLOCK_FORMATS });
+    if let Some(reason) = reason {
+        data["reason"] = Value::String(reason);
+    }
+    tool_error(
+        ErrorKind::InvalidBlock,
+        format!("Invalid block identifier: {input}"),
+        Some(data),
+    )
+}
+
+/// Resolve an optional block identifier against the node, defaulting to `latest`.
+pub async fn resolve_block_id(
+    provider: &RetryProvider,
+    block: Option<BlockParam>,
+) -> Result<BlockId, ErrorData> {
+    let Some(BlockParam(block)) = block else {
+        return Ok(BlockId::Number(BlockNumberOrTag::Latest));
+    };
+
+    match parse_block_spec(&block)? {
+        BlockSpec::Id(id) => Ok(id),
+        BlockSpec::Offset(tag, offset) => {
+            let (base, _) = block_header(provider, tag).await?;
+            let num = base.checked_sub(offset).ok_or_else(|| {
+                invalid_block(
+                    &block,
+                    Some(format!(
+                        "offset {offset} is before genesis (base block {base})"
+                    )),
+                )
+            })?;
+            Ok(BlockId::Number(num.into()))
+        }
+        BlockSpec::Timestamp(timestamp) => {
+            let num = block_at_timestamp(provider, timestamp).await?;
+            Ok(BlockId::Number(num.into()))
+        }
+    }
+}
+
+/// Pin `latest`, `safe` and `finalized` to the block number they point at now, so several reads
+/// see the same block.
+pub async fn pin_block(provider: &RetryProvider, block: BlockId) -> Result<BlockId, ErrorData> {
     match block {
-        Some(block_str) => {
-            let trimmed = block_str.trim();
-
-            match trimmed.to_lowercase().as_str() {
-                "latest" => return BlockId::Number(Latest),
-                "earliest" => return BlockId::Number(alloy_rpc_types::BlockNumberOrTag::Earliest),
-                "pending" => return BlockId::Number(alloy_rpc_types::BlockNumberOrTag::Pending),
-                "safe" => return BlockId::Number(alloy_rpc_types::BlockNumberOrTag::Safe),
-                "finalized" => {
-                    return BlockId::Number(alloy_rpc_types::BlockNumberOrTag::Finalized);
-                }
-                _ => {}
-            }
+        BlockId::Number(
+            tag @ (BlockNumberOrTag::Latest | BlockNumberOrTag::Safe | BlockNumberOrTag::Finalized),
+        ) => Ok(BlockId::Number(block_header(provider, tag).await?.0.into())),
+        block => Ok(block),
+    }
+}
 
-            if trimmed.starts_with("0x") && trimmed.len() == 66 {
-                if let Ok(hash_bytes) = hex::decode(&trimmed[2..]) {
-                    if let Ok(hash) = alloy_primitives::B256::try_from(hash_bytes.as_slice()) {
-                        return BlockId::Hash(alloy_rpc_types::RpcBlockHash::from_hash(hash, None));
-                    }
-                }
-            }
+/// Resolve an optional block identifier to a block number, defaulting to `latest`.
+pub async fn resolve_block_number(
+    provider: &RetryProvider,
+    block: Option<BlockParam>,
+) -> Result<u64, ErrorData> {
+    match resolve_block_id(provider, block).await? {
+        BlockId::Number(BlockNumberOrTag::Number(num)) => Ok(num),
+        BlockId::Number(tag) => Ok(block_header(provider, tag).await?.0),
+        BlockId::Hash(hash) => {
+            let block = provider
+                .get_block_by_hash(hash.block_hash)
+                .await
+                .map_err(|e| rpc_error("Failed to get block", &e))?
+                .ok_or_else(|| {
+                    not_found(
+                        "Block not found",
+                        Some(json!({ "block": hash.block_hash.to_string() })),
+                    )
+                })?;
+            Ok(block.header.number)
+        }
+    }
+}
 
-            if let Ok(num) = trimmed.parse::<u64>() {
-                return BlockId::Number(num.into());
-            }
+/// Fetch the number and timestamp of a block.
+async fn block_header(
+    provider: &RetryProvider,
+    block: BlockNumberOrTag,
+) -> Result<(u64, u64), ErrorData> {
+    let block = provider
+        .get_block_by_number(block)
+        .await
+        .map_err(|e| rpc_error("Failed to get block", &e))?
+        .ok_or_else(|| {
+            not_found(
+                "Block not found",
+                Some(json!({ "block": block.to_string() })),
+            )
+        })?;
+    Ok((block.header.number, block.header.timestamp))
+}
+
+/// Fetch a block, with full transactions if `full` is set.
+pub async fn get_block(
+    provider: &RetryProvider,
+    block: BlockId,
+    full: bool,
+) -> Result<<AnyNetwork as Network>::BlockResponse, ErrorData> {
+    let mut request = provider.get_block(block);
+    if full {
+        request = request.full();
+    }
+    request
+        .await
+        .map_err(|e| rpc_error("Failed to get block", &e))?
+        .ok_or_else(|| {
+            not_found(
+                "Block not found",
+                Some(json!({ "block": block.to_string() })),
+            )
+        })
+}
+
+/// Binary search the chain for the block whose timestamp is closest to `target`.
+async fn block_at_timestamp(provider: &RetryProvider, target: u64) -> Result<u64, ErrorData> {
+    let (latest, latest_ts) = block_header(provider, BlockNumberOrTag::Latest).await?;
+    if target >= latest_ts {
+        return Ok(latest);
+    }
+    let (genesis, genesis_ts) = block_header(provider, BlockNumberOrTag::Earliest).await?;
+    if target <= genesis_ts {
+        return Ok(genesis);
+    }
 
-            BlockId::Number(Latest)
+    // Invariant: lo_ts <= target < hi_ts.
+    let (mut lo, mut lo_ts, mut hi, mut hi_ts) = (genesis, genesis_ts, latest, latest_ts);
+    while hi - lo > 1 {
+        let mid = lo + (hi - lo) / 2;
+        let (_, mid_ts) = block_header(provider, mid.into()).await?;
+        if mid_ts <= target {
+            (lo, lo_ts) = (mid, mid_ts);
+        } else {
+            (hi, hi_ts) = (mid, mid_ts);
         }
-        None => BlockId::Number(Latest),
     }
+
+    Ok(if target - lo_ts <= hi_ts - target {
+        lo
+    } else {
+        hi
+    })
+}
+
+/// Parse a hex address, with or without the `0x` prefix. Mixed-case input must carry a valid
+/// EIP-55 checksum; all-lowercase or all-uppercase input is accepted as is.
+pub fn parse_address(input: &str) -> Result<Address, ErrorData> {
+    let trimmed = input.trim();
+    let digits = trimmed.strip_prefix("0x").unwrap_or(trimmed);
+
+    if digits.len() != 40 {
+        return Err(address_error(input, "invalid_length", None));
+    }
+    if !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(address_error(input, "invalid_hex", None));
+    }
+
+    let address = Address::from_slice(&hex::decode(digits).expect("validated hex digits"));
+
+    let has_lower = digits.chars().any(|c| c.is_ascii_lowercase());
+    let has_upper = digits.chars().any(|c| c.is_ascii_uppercase());
+    if has_lower && has_upper {
+        let checksummed = address.to_checksum(None);
+        if checksummed[2..] != *digits {
+            return Err(address_error(input, "invalid_checksum", Some(checksummed)));
+        }
+    }
+    Ok(address)
 }
 
+fn address_error(input: &str, reason: &str, expected: Option<String>) -> ErrorData {
+    let mut data = json!({ "input": input, "reason": reason });
+    if let Some(expected) = expected {
+        data["expected"] = Value::String(expected);
+    }
+    tool_error(ErrorKind::InvalidAddress, "Invalid address", Some(data))
+}
+
+/// Resolve an ENS name to the address it points to.
+pub async fn resolve_name<N: alloy_provider::Network, P: alloy_provider::Provider<N>>(
+    provider: &P,
+    name: &str,
+) -> Result<Address, ErrorData> {
+    provider.resolve_name(name.trim()).await.map_err(|e| {
+        if let Some(e) = transport_error(&e) {
+            return rpc_error("Failed to resolve ENS name", e);
+        }
+        tool_error(
+            ErrorKind::InvalidAddress,
+            "Failed to resolve ENS name",
+            Some(
+                json!({ "input": name, "reason": "ens_resolution_failed", "cause": e.to_string() }),
+            ),
+        )
+    })
+}
+
+/// Look up the primary ENS name of an address. With `verify`, the name must resolve back to the
+/// same address.
+pub async fn lookup_address<N: alloy_provider::Network, P: alloy_provider::Provider<N>>(
+    provider: &P,
+    address: Address,
+    verify: bool,
+) -> Result<String, ErrorData> {
+    let name = provider.lookup_address(&address).await.map_err(|e| {
+        if let Some(e) = transport_error(&e) {
+            return rpc_error("Failed to look up ENS name", e);
+        }
+        not_found(
+            "Failed to look up ENS name",
+            Some(json!({
+                "input": address.to_checksum(None),
+                "reason": "ens_lookup_failed",
+                "cause": e.to_string(),
+            })),
+        )
+    })?;
+
+    if verify {
+        let resolved = resolve_name(provider, &name).await?;
+        if resolved != address {
+            return Err(not_found(
+                "Reverse record does not resolve back to the address",
+                Some(json!({
+                    "input": address.to_checksum(None),
+                    "name": name,
+                    "resolved": resolved.to_checksum(None),
+                    "reason": "ens_mismatch",
+                })),
+            ));
+        }
+    }
+    Ok(name)
+}
+
+/// Resolve a hex address or an ENS name.
+pub async fn resolve_address<N: alloy_provider::Network, P: alloy_provider::Provider<N>>(
+    provider: &P,
+    input: &str,
+) -> Result<Address, ErrorData> {
+    let trimmed = input.trim();
+    if trimmed.starts_with("0x") || !trimmed.contains('.') {
+        parse_address(trimmed)
+    } else {
+        resolve_name(provider, trimmed).await
+    }
+}
+
+/// Resolve the address a tool call names, given either an address or an ENS name but not both.
 pub async fn resolve<N: alloy_provider::Network, P: alloy_provider::Provider<N>>(
     provider: &P,
     name: Option<String>,
     address: Option<String>,
-) -> Result<Address, String> {
-    if let Some(name) = name {
-        provider
-            .resolve_name(&name)
-            .await
-            .map_err(|e| e.to_string())?;
+) -> Result<Address, ErrorData> {
+    match (name, address) {
+        (Some(name), None) => resolve_name(provider, &name).await,
+        (None, Some(address)) => resolve_address(provider, &address).await,
+        (Some(_), Some(_)) => Err(invalid_input(
+            "Specify either address or name, not both",
+            Some(json!({ "reason": "ambiguous" })),
+        )),
+        (None, None) => Err(invalid_input(
+            "Either address or name is required",
+            Some(json!({ "reason": "missing" })),
+        )),
     }
-    if let Some(address) = address {
-        Address::from_slice(hex::decode(address).unwrap().as_slice());
+}
+
+const UNIT_FORMATS: &str =
+    "wei, gwei, ether (or any other named denomination), or a number of decimals from 0 to 77";
+
+/// Parse a unit name such as `wei`, `gwei` or `ether`, or a decimals count such as `6`.
+pub fn parse_unit(input: &str) -> Result<Unit, ErrorData> {
+    Unit::from_str(input.trim()).map_err(|e| {
+        invalid_input(
+            format!("Invalid unit: {input}"),
+            Some(json!({ "input": input, "expected": UNIT_FORMATS, "reason": e.to_string() })),
+        )
+    })
+}
+
+/// Format `value` in `unit` exactly, without trailing zeros: 1.5 ether is `1.5`, not
+/// `1.500000000000000000`.
+pub fn format_amount(value: U256, unit: Unit) -> String {
+    let formatted = ParseUnits::U256(value).format_units(unit);
+    match formatted.split_once('.') {
+        Some((int, frac)) => {
+            let frac = frac.trim_end_matches('0');
+            if frac.is_empty() {
+                int.to_string()
+            } else {
+                format!("{int}.{frac}")
+            }
+        }
+        None => formatted,
+    }
+}
+
+/// The output schema of a tool returning `T`, advertised in the tool list so clients can rely on
+/// the shape of its structured content.
+pub fn output_schema<T: schemars::JsonSchema>() -> Arc<JsonObject> {
+    match serde_json::to_value(schemars::schema_for!(T)) {
+        Ok(Value::Object(schema)) => Arc::new(schema),
+        _ => unreachable!("output schemas are JSON objects"),
+    }
+}
+
+/// Return `output` as structured content, keeping `text` as the human readable content.
+pub fn structured<T: Serialize>(
+    text: impl Into<String>,
+    output: &T,
+) -> Result<CallToolResult, ErrorData> {
+    let mut result = CallToolResult::success(vec![Content::text(text)]);
+    result.structured_content = Some(to_structured(output)?);
+    Ok(result)
+}
+
+/// Return `output` as structured content, with its JSON as the text content.
+pub fn json_output<T: Serialize>(output: &T) -> Result<CallToolResult, ErrorData> {
+    Ok(CallToolResult::structured(to_structured(output)?))
+}
+
+fn to_structured<T: Serialize>(output: &T) -> Result<Value, ErrorData> {
+    serde_json::to_value(output).map_err(|e| internal_error("Failed to serialize result", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VITALIK: &str = "0xd8dA6BF26964aF9D7eEd9e03E53415D37aA96045";
+
+    #[test]
+    fn test_parse_address_checksummed() {
+        let address = parse_address(VITALIK).unwrap();
+        assert_eq!(address.to_checksum(None), VITALIK);
+    }
+
+    #[test]
+    fn test_parse_address_lowercase_and_unprefixed() {
+        let lower = VITALIK.to_lowercase();
+        assert_eq!(
+            parse_address(&lower).unwrap(),
+            parse_address(VITALIK).unwrap()
+        );
+        assert_eq!(
+            parse_address(&lower[2..]).unwrap(),
+            parse_address(VITALIK).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_parse_address_bad_checksum() {
+        let bad = VITALIK.replace("d8dA", "d8DA");
+        let err = parse_address(&bad).unwrap_err();
+        let data = err.data.unwrap();
+        assert_eq!(data["reason"], "invalid_checksum");
+        assert_eq!(data["expected"], VITALIK);
+    }
+
+    fn block_id(input: &str) -> BlockId {
+        match parse_block_spec(input).unwrap() {
+            BlockSpec::Id(id) => id,
+            other => panic!("expected a block id, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_block_tags() {
+        assert_eq!(
+            block_id("latest"),
+            BlockId::Number(BlockNumberOrTag::Latest)
+        );
+        assert_eq!(
+            block_id(" Finalized "),
+            BlockId::Number(BlockNumberOrTag::Finalized)
+        );
+        assert_eq!(
+            block_id("earliest"),
+            BlockId::Number(BlockNumberOrTag::Earliest)
+        );
+    }
+
+    #[test]
+    fn test_parse_block_numbers() {
+        assert_eq!(block_id("68943"), BlockId::Number(68943.into()));
+        assert_eq!(block_id("0x10d4f"), BlockId::Number(68943.into()));
+    }
+
+    #[test]
+    fn test_parse_block_hash() {
+        let hash = "0x88df016429689c079f3b2f6ad39fa052532c56795b733da78a91ebe6a713944b";
+        assert_eq!(
+            block_id(hash),
+            BlockId::Hash(RpcBlockHash::from_hash(hash.parse().unwrap(), None))
+        );
+
+        let canonical = format!(r#"{{"blockHash": "{hash}", "requireCanonical": true}}"#);
+        assert_eq!(
+            block_id(&canonical),
+            BlockId::Hash(RpcBlockHash::from_hash(hash.parse().unwrap(), Some(true)))
+        );
+    }
+
+    #[test]
+    fn test_parse_block_offsets_and_dates() {
+        assert_eq!(
+            parse_block_spec("latest-10").unwrap(),
+            BlockSpec::Offset(BlockNumberOrTag::Latest, 10)
+        );
+        assert_eq!(
+            parse_block_spec("finalized - 2").unwrap(),
+            BlockSpec::Offset(BlockNumberOrTag::Finalized, 2)
+        );
+        assert_eq!(
+            parse_block_spec("@1700000000").unwrap(),
+            BlockSpec::Timestamp(1700000000)
+        );
+        assert_eq!(
+            parse_block_spec("2023-11-14T22:13:20Z").unwrap(),
+            BlockSpec::Timestamp(1700000000)
+        );
+        assert_eq!(
+            parse_block_spec("2024-01-01").unwrap(),
+            BlockSpec::Timestamp(1704067200)
+        );
+    }
+
+    #[test]
+    fn test_parse_block_rejects_garbage() {
+        for input in [
+            "lates",
+            "0x88df016429689c079f3b2f6ad39fa052532c56795b733da78a91ebe6a71394",
+            "0xzz",
+            "latest-x",
+            "",
+            "-5",
+        ] {
+            let err = parse_block_spec(input).unwrap_err();
+            assert_eq!(err.code, rmcp::model::ErrorCode::INVALID_PARAMS);
+            assert!(err.message.contains(input), "{}", err.message);
+        }
+    }
+
+    #[test]
+    fn test_block_param_schema() {
+        let block: BlockParam = serde_json::from_value(json!("latest-10")).unwrap();
+        assert_eq!(block, BlockParam::from("latest-10"));
+
+        let schema = serde_json::to_value(schemars::schema_for!(BlockParam)).unwrap();
+        assert_eq!(schema["type"], "string");
+        assert!(
+            schema["description"]
+                .as_str()
+                .unwrap()
+                .contains("latest-10")
+        );
+    }
+
+    #[test]
+    fn test_parse_unit() {
+        assert_eq!(parse_unit("ether").unwrap(), Unit::ETHER);
+        assert_eq!(parse_unit("GWEI").unwrap(), Unit::GWEI);
+        assert_eq!(parse_unit("wei").unwrap(), Unit::WEI);
+        assert_eq!(parse_unit("6").unwrap().get(), 6);
+
+        for input in ["", "bitcoin", "78", "-1"] {
+            let err = parse_unit(input).unwrap_err();
+            assert_eq!(err.code, rmcp::model::ErrorCode::INVALID_PARAMS);
+        }
+    }
+
+    #[test]
+    fn test_format_amount_is_exact() {
+        assert_eq!(format_amount(U256::ZERO, Unit::ETHER), "0");
+        assert_eq!(
+            format_amount(U256::from(1_500_000_000_000_000_000u128), Unit::ETHER),
+            "1.5"
+        );
+        assert_eq!(
+            format_amount(U256::from(1u8), Unit::ETHER),
+            "0.000000000000000001"
+        );
+        assert_eq!(format_amount(U256::from(1234u32), Unit::WEI), "1234");
+        assert_eq!(
+            format_amount(U256::from(1_234_567u32), Unit::new(6).unwrap()),
+            "1.234567"
+        );
+
+        // Values above u128::MAX keep every digit.
+        assert_eq!(
+            format_amount(U256::MAX, Unit::ETHER),
+            "115792089237316195423570985008687907853269984665640564039457.584007913129639935"
+        );
+    }
+
+    #[test]
+    fn test_parse_address_invalid() {
+        let err = parse_address("0x1234").unwrap_err();
+        assert_eq!(err.data.unwrap()["reason"], "invalid_length");
+
+        let err = parse_address("0xzz8da6bf26964af9d7eed9e03e53415d37aa9604").unwrap_err();
+        assert_eq!(err.data.unwrap()["reason"], "invalid_hex");
     }
-    Err("address is empty".to_string())
 }
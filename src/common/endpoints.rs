@@ -0,0 +1,681 @@
+//! Networks served by several RPC endpoints. Requests go to the endpoints according to the
+//! network's [`Strategy`], and the endpoints that answered are reported with the tool result.
+
+use std::{
+    fmt,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use alloy_json_rpc::{
+    Id, Request, RequestPacket, Response, ResponsePacket, ResponsePayload, SerializedRequest,
+};
+use alloy_transport::{
+    BoxTransport, RpcError, TransportError, TransportErrorKind, TransportFut, TransportResult,
+};
+use futures::{StreamExt, future::join_all, stream::FuturesUnordered};
+use serde::Deserialize;
+use serde_json::Value;
+use tower::Service;
+
+use crate::common::{error::classify, meta::record_endpoint};
+
+/// How long an endpoint that failed is tried only after the healthy ones.
+const UNHEALTHY_COOLDOWN: Duration = Duration::from_secs(30);
+
+/// How requests are spread over a network's endpoints.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Strategy {
+    /// Send to the first healthy endpoint, moving on to the next when one fails.
+    #[default]
+    Failover,
+    /// Send to every healthy endpoint at once and take the first answer.
+    Fastest,
+    /// Send state reads to every endpoint and answer once `quorum` of them agree. Reads of the
+    /// `latest` block are pinned to the lowest head among the endpoints first.
+    Quorum,
+}
+
+/// One endpoint of a network. `label` names it in results, so it must not carry secrets.
+pub struct Endpoint {
+    label: String,
+    transport: BoxTransport,
+    unhealthy_until: Mutex<Option<Instant>>,
+}
+
+impl Endpoint {
+    pub fn new(label: String, transport: BoxTransport) -> Self {
+        Self {
+            label,
+            transport,
+            unhealthy_until: Mutex::new(None),
+        }
+    }
+
+    fn healthy(&self, now: Instant) -> bool {
+        let until = self
+            .unhealthy_until
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        until.is_none_or(|until| now >= until)
+    }
+
+    async fn send(&self, request: RequestPacket) -> TransportResult<ResponsePacket> {
+        let result = self.transport.clone().call(request).await;
+        let mut until = self
+            .unhealthy_until
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        *until = failed(&result).then(|| Instant::now() + UNHEALTHY_COOLDOWN);
+        result
+    }
+}
+
+/// A transport over a network's endpoints.
+#[derive(Clone)]
+pub struct EndpointSet {
+    endpoints: Arc<[Endpoint]>,
+    strategy: Strategy,
+    quorum: usize,
+}
+
+impl EndpointSet {
+    /// `quorum` defaults to a majority of the endpoints.
+    pub fn new(endpoints: Vec<Endpoint>, strategy: Strategy, quorum: Option<usize>) -> Self {
+        assert!(!endpoints.is_empty(), "a network has at least one endpoint");
+        let quorum = quorum
+            .unwrap_or(endpoints.len() / 2 + 1)
+            .clamp(1, endpoints.len());
+        Self {
+            endpoints: endpoints.into(),
+            strategy,
+            quorum,
+        }
+    }
+
+    async fn send(self, request: RequestPacket) -> TransportResult<ResponsePacket> {
+        if self.endpoints.len() == 1 {
+            return self.failover(request).await;
+        }
+        match self.strategy {
+            Strategy::Failover => self.failover(request).await,
+            Strategy::Fastest => self.fastest(request).await,
+            Strategy::Quorum if needs_agreement(&request) => self.quorum(request).await,
+            Strategy::Quorum => self.failover(request).await,
+        }
+    }
+
+    /// The endpoints in the order to try them: healthy ones first, each in configured order.
+    fn ordered(&self) -> Vec<&Endpoint> {
+        let now = Instant::now();
+        let (mut healthy, unhealthy): (Vec<_>, Vec<_>) =
+            self.endpoints.iter().partition(|e| e.healthy(now));
+        healthy.extend(unhealthy);
+        healthy
+    }
+
+    async fn failover(&self, request: RequestPacket) -> TransportResult<ResponsePacket> {
+        let mut last = None;
+        for endpoint in self.ordered() {
+            let result = endpoint.send(request.clone()).await;
+            if !failed(&result) {
+                record_endpoint(&endpoint.label);
+                return result;
+            }
+            last = Some(result);
+        }
+        last.expect("a network has at least one endpoint")
+    }
+
+    async fn fastest(&self, request: RequestPacket) -> TransportResult<ResponsePacket> {
+        let now = Instant::now();
+        let mut racing: Vec<_> = self.endpoints.iter().filter(|e| e.healthy(now)).collect();
+        if racing.is_empty() {
+            racing = self.endpoints.iter().collect();
+        }
+        let mut pending: FuturesUnordered<_> = racing
+            .into_iter()
+            .map(|endpoint| {
+                let request = request.clone();
+                async move { (endpoint, endpoint.send(request).await) }
+            })
+            .collect();
+
+        let mut last = None;
+        while let Some((endpoint, result)) = pending.next().await {
+            if !failed(&result) {
+                record_endpoint(&endpoint.label);
+                return result;
+            }
+            last = Some(result);
+        }
+        last.expect("a network has at least one endpoint")
+    }
+
+    async fn quorum(&self, request: RequestPacket) -> TransportResult<ResponsePacket> {
+        let request = self.pin_latest(request).await?;
+        let mut pending: FuturesUnordered<_> = self
+            .endpoints
+            .iter()
+            .map(|endpoint| {
+                let request = request.clone();
+                async move { (endpoint, endpoint.send(request).await) }
+            })
+            .collect();
+
+        // Each distinct answer with the endpoints that gave it.
+        let mut answers: Vec<(Value, Vec<&str>)> = Vec::new();
+        let mut last_failure = None;
+        while let Some((endpoint, result)) = pending.next().await {
+            match result {
+                Ok(packet) if !failed_packet(&packet) => {
+                    let answer = answer(&packet);
+                    let index = match answers.iter().position(|(a, _)| *a == answer) {
+                        Some(index) => index,
+                        None => {
+                            answers.push((answer, Vec::new()));
+                            answers.len() - 1
+                        }
+                    };
+                    let agreeing = &mut answers[index].1;
+                    agreeing.push(&endpoint.label);
+                    if agreeing.len() >= self.quorum {
+                        agreeing.iter().for_each(|label| record_endpoint(label));
+                        return Ok(packet);
+                    }
+                }
+                failure => last_failure = Some(failure),
+            }
+            let best = answers.iter().map(|(_, agreeing)| agreeing.len()).max();
+            if best.unwrap_or(0) + pending.len() < self.quorum {
+                break;
+            }
+        }
+
+        match last_failure {
+            Some(failure) if answers.is_empty() => failure,
+            _ => Err(TransportErrorKind::custom(NoQuorum {
+                quorum: self.quorum,
+                endpoints: self.endpoints.len(),
+                answers: answers.len(),
+            })),
+        }
+    }
+
+    /// Nodes a block or two apart disagree about `latest`, so reads of it are pinned to the
+    /// lowest head among the endpoints, which all of them have. Left as is if no endpoint reports
+    /// its head.
+    async fn pin_latest(&self, request: RequestPacket) -> TransportResult<RequestPacket> {
+        let reads_latest = match &request {
+            RequestPacket::Single(request) => reads_latest(request),
+            RequestPacket::Batch(requests) => requests.iter().any(reads_latest),
+        };
+        if !reads_latest {
+            return Ok(request);
+        }
+        let Some(head) = self.lowest_head().await else {
+            return Ok(request);
+        };
+        Ok(match request {
+            RequestPacket::Single(request) => RequestPacket::Single(pin(request, head)?),
+            RequestPacket::Batch(requests) => RequestPacket::Batch(
+                requests
+                    .into_iter()
+                    .map(|request| pin(request, head))
+                    .collect::<TransportResult<_>>()?,
+            ),
+        })
+    }
+
+    async fn lowest_head(&self) -> Option<u64> {
+        let request = Request::new("eth_blockNumber", Id::Number(0), ())
+            .serialize()
+            .ok()?;
+        let heads = self
+            .endpoints
+            .iter()
+            .map(|endpoint| endpoint.send(RequestPacket::Single(request.clone())));
+        join_all(heads)
+            .await
+            .into_iter()
+            .filter_map(|result| {
+                let head = answer(&result.ok()?);
+                u64::from_str_radix(head.as_str()?.strip_prefix("0x")?, 16).ok()
+            })
+            .min()
+    }
+}
+
+/// Too few endpoints gave the same answer. They answered, so asking them again is unlikely to
+/// change that, and the error is not retried.
+#[derive(Debug)]
+pub struct NoQuorum {
+    quorum: usize,
+    endpoints: usize,
+    answers: usize,
+}
+
+impl fmt::Display for NoQuorum {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "no quorum: {} of {} endpoints must agree, but they answered {} different ways",
+            self.quorum, self.endpoints, self.answers,
+        )
+    }
+}
+
+impl std::error::Error for NoQuorum {}
+
+impl Service<RequestPacket> for EndpointSet {
+    type Response = ResponsePacket;
+    type Error = TransportError;
+    type Future = TransportFut<'static>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: RequestPacket) -> Self::Future {
+        Box::pin(self.clone().send(request))
+    }
+}
+
+/// Whether a result is a failure another endpoint, or a later retry, may not have.
+fn failed(result: &TransportResult<ResponsePacket>) -> bool {
+    match result {
+        Ok(packet) => failed_packet(packet),
+        Err(error) => classify(error).retryable(),
+    }
+}
+
+fn failed_packet(packet: &ResponsePacket) -> bool {
+    packet
+        .as_error()
+        .is_some_and(|payload| classify(&RpcError::ErrorResp(payload.clone())).retryable())
+}
+
+/// Only reads of chain state are compared. Gas prices, the head block and the like differ between
+/// healthy nodes, and sending a transaction once is enough.
+fn needs_agreement(request: &RequestPacket) -> bool {
+    let reads_state = |method: &str| {
+        matches!(
+            method,
+            "eth_chainId"
+                | "eth_getBalance"
+                | "eth_getStorageAt"
+                | "eth_getCode"
+                | "eth_getTransactionCount"
+                | "eth_getProof"
+                | "eth_call"
+                | "eth_getLogs"
+                | "eth_getBlockByHash"
+                | "eth_getTransactionByHash"
+                | "eth_getTransactionReceipt"
+        )
+    };
+    match request {
+        RequestPacket::Single(request) => reads_state(request.method()),
+        RequestPacket::Batch(requests) => requests.iter().all(|r| reads_state(r.method())),
+    }
+}
+
+/// The tag that nodes at different heights resolve to different blocks.
+const LATEST: &str = "latest";
+
+fn params(request: &SerializedRequest) -> Vec<Value> {
+    request
+        .params()
+        .and_then(|params| serde_json::from_str(params.get()).ok())
+        .unwrap_or_default()
+}
+
+/// The block parameters of a request that name `latest`, including ones left to that default,
+/// which are filled in.
+fn latest_blocks<'a>(method: &str, params: &'a mut Vec<Value>) -> Vec<&'a mut Value> {
+    let is_latest = |block: &Value| block.as_str() == Some(LATEST);
+    if method == "eth_getLogs" {
+        let Some(Value::Object(filter)) = params.first_mut() else {
+            return Vec::new();
+        };
+        if filter.contains_key("blockHash") {
+            return Vec::new();
+        }
+        for key in ["fromBlock", "toBlock"] {
+            filter.entry(key).or_insert_with(|| LATEST.into());
+        }
+        return filter
+            .iter_mut()
+            .filter(|(key, block)| {
+                ["fromBlock", "toBlock"].contains(&key.as_str()) && is_latest(block)
+            })
+            .map(|(_, block)| block)
+            .collect();
+    }
+    let index = match method {
+        "eth_getBalance" | "eth_getCode" | "eth_getTransactionCount" | "eth_call" => 1,
+        "eth_getStorageAt" | "eth_getProof" => 2,
+        _ => return Vec::new(),
+    };
+    if params.len() == index {
+        params.push(LATEST.into());
+    }
+    params
+        .get_mut(index)
+        .filter(|block| is_latest(block))
+        .into_iter()
+        .collect()
+}
+
+fn reads_latest(request: &SerializedRequest) -> bool {
+    !latest_blocks(request.method(), &mut params(request)).is_empty()
+}
+
+fn pin(request: SerializedRequest, head: u64) -> TransportResult<SerializedRequest> {
+    let mut params = params(&request);
+    let blocks = latest_blocks(request.method(), &mut params);
+    if blocks.is_empty() {
+        return Ok(request);
+    }
+    for block in blocks {
+        *block = format!("{head:#x}").into();
+    }
+    Request {
+        meta: request.meta().clone(),
+        params,
+    }
+    .serialize()
+    .map_err(TransportError::ser_err)
+}
+
+/// The answer in a response, compared as JSON so formatting differences between nodes do not
+/// count as disagreement.
+fn answer(packet: &ResponsePacket) -> Value {
+    let answer = |response: &Response| match &response.payload {
+        ResponsePayload::Success(raw) => {
+            serde_json::from_str(raw.get()).unwrap_or_else(|_| Value::String(raw.get().into()))
+        }
+        ResponsePayload::Failure(error) => {
+            serde_json::json!({ "code": error.code, "message": error.message })
+        }
+    };
+    match packet {
+        ResponsePacket::Single(response) => answer(response),
+        ResponsePacket::Batch(responses) => responses.iter().map(answer).collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    use alloy_transport::HttpError;
+    use serde_json::{json, value::RawValue};
+    use tower::Layer;
+
+    use super::*;
+    use crate::common::{
+        error::ErrorKind,
+        meta::track,
+        retry::{RetryLayer, RetryPolicy},
+    };
+
+    /// A transport that answers `result` after `delay_ms`, or fails with a 503 if `result` is
+    /// `None`.
+    #[derive(Clone)]
+    struct Node {
+        result: Option<&'static str>,
+        delay_ms: u64,
+    }
+
+    impl Service<RequestPacket> for Node {
+        type Response = ResponsePacket;
+        type Error = TransportError;
+        type Future = TransportFut<'static>;
+
+        fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, _request: RequestPacket) -> Self::Future {
+            let node = self.clone();
+            Box::pin(async move {
+                tokio::time::sleep(Duration::from_millis(node.delay_ms)).await;
+                match node.result {
+                    Some(result) => Ok(ResponsePacket::Single(Response {
+                        id: Id::Number(1),
+                        payload: ResponsePayload::Success(
+                            RawValue::from_string(result.to_string()).unwrap(),
+                        ),
+                    })),
+                    None => Err(RpcError::Transport(TransportErrorKind::HttpError(
+                        HttpError {
+                            status: 503,
+                            body: String::new(),
+                        },
+                    ))),
+                }
+            })
+        }
+    }
+
+    fn set(nodes: &[(Option<&'static str>, u64)], strategy: Strategy) -> EndpointSet {
+        let endpoints = nodes
+            .iter()
+            .enumerate()
+            .map(|(i, &(result, delay_ms))| {
+                Endpoint::new(
+                    format!("node{i}"),
+                    BoxTransport::new(Node { result, delay_ms }),
+                )
+            })
+            .collect();
+        EndpointSet::new(endpoints, strategy, None)
+    }
+
+    fn request(method: &'static str) -> RequestPacket {
+        RequestPacket::Single(Request::new(method, Id::Number(1), ()).serialize().unwrap())
+    }
+
+    async fn call(set: &EndpointSet, method: &'static str) -> (Option<Value>, Vec<String>) {
+        let (result, meta) = track(set.clone().send(request(method))).await;
+        (result.ok().map(|packet| answer(&packet)), meta.endpoints)
+    }
+
+    #[tokio::test]
+    async fn test_failover_skips_failed_endpoints() {
+        let set = set(&[(None, 0), (Some("\"0x1\""), 0)], Strategy::Failover);
+        let (answer, answered) = call(&set, "eth_blockNumber").await;
+        assert_eq!(answer, Some(Value::from("0x1")));
+        assert_eq!(answered, ["node1"]);
+        // The failed endpoint is tried last until it recovers.
+        assert_eq!(set.ordered()[0].label, "node1");
+    }
+
+    #[tokio::test]
+    async fn test_fastest_wins() {
+        let set = set(
+            &[(Some("\"slow\""), 200), (Some("\"fast\""), 0)],
+            Strategy::Fastest,
+        );
+        let (answer, answered) = call(&set, "eth_blockNumber").await;
+        assert_eq!(answer, Some(Value::from("fast")));
+        assert_eq!(answered, ["node1"]);
+    }
+
+    #[tokio::test]
+    async fn test_quorum() {
+        let nodes = [
+            (Some("\"0x1\""), 0),
+            (Some("\"0x2\""), 0),
+            (Some("\"0x1\""), 10),
+        ];
+        let set = set(&nodes, Strategy::Quorum);
+        let (answer, mut answered) = call(&set, "eth_getBalance").await;
+        assert_eq!(answer, Some(Value::from("0x1")));
+        answered.sort();
+        assert_eq!(answered, ["node0", "node2"]);
+
+        // The head block is not compared.
+        let (answer, answered) = call(&set, "eth_blockNumber").await;
+        assert_eq!(answer, Some(Value::from("0x1")));
+        assert_eq!(answered, ["node0"]);
+
+        let set = set_with_quorum(&nodes, 3);
+        let (answer, answered) = call(&set, "eth_getBalance").await;
+        assert_eq!(answer, None);
+        assert!(answered.is_empty());
+    }
+
+    /// A node at `head` that answers reads with the block they resolve to.
+    #[derive(Clone)]
+    struct Chain {
+        head: u64,
+    }
+
+    impl Service<RequestPacket> for Chain {
+        type Response = ResponsePacket;
+        type Error = TransportError;
+        type Future = TransportFut<'static>;
+
+        fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, request: RequestPacket) -> Self::Future {
+            let RequestPacket::Single(request) = request else {
+                panic!("the chain only answers single requests");
+            };
+            let head = format!("\"{:#x}\"", self.head);
+            let result = match request.method() {
+                "eth_blockNumber" => head,
+                _ => match params(&request).get(1) {
+                    Some(Value::String(block)) if block != LATEST => format!("\"{block}\""),
+                    _ => head,
+                },
+            };
+            Box::pin(async move {
+                Ok(ResponsePacket::Single(Response {
+                    id: Id::Number(1),
+                    payload: ResponsePayload::Success(RawValue::from_string(result).unwrap()),
+                }))
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_quorum_pins_latest() {
+        let endpoints = [0x10, 0x11]
+            .into_iter()
+            .enumerate()
+            .map(|(i, head)| Endpoint::new(format!("node{i}"), BoxTransport::new(Chain { head })))
+            .collect();
+        let set = EndpointSet::new(endpoints, Strategy::Quorum, Some(2));
+        let address = "0x0000000000000000000000000000000000000001";
+        for params in [json!([address, "latest"]), json!([address])] {
+            let request = Request::new("eth_getBalance", Id::Number(1), params)
+                .serialize()
+                .unwrap();
+            let (result, mut meta) = track(set.clone().send(RequestPacket::Single(request))).await;
+            assert_eq!(answer(&result.unwrap()), Value::from("0x10"));
+            meta.endpoints.sort();
+            assert_eq!(meta.endpoints, ["node0", "node1"]);
+        }
+    }
+
+    #[test]
+    fn test_latest_blocks() {
+        let mut params = vec![json!({ "to": "0x01" })];
+        assert_eq!(latest_blocks("eth_call", &mut params).len(), 1);
+        assert_eq!(params[1], json!("latest"));
+
+        let mut params = vec![json!({ "to": "0x01" }), json!("0x10")];
+        assert!(latest_blocks("eth_call", &mut params).is_empty());
+
+        let mut params = vec![json!({ "fromBlock": "0x10" })];
+        assert_eq!(latest_blocks("eth_getLogs", &mut params).len(), 1);
+        assert_eq!(
+            params[0],
+            json!({ "fromBlock": "0x10", "toBlock": "latest" })
+        );
+
+        let mut params = vec![json!({ "blockHash": format!("0x{}", "00".repeat(32)) })];
+        assert!(latest_blocks("eth_getLogs", &mut params).is_empty());
+
+        let mut params = vec![json!("0x01"), json!("pending")];
+        assert!(latest_blocks("eth_getBalance", &mut params).is_empty());
+        assert!(latest_blocks("eth_blockNumber", &mut Vec::new()).is_empty());
+    }
+
+    /// Counts the requests sent to a set.
+    #[derive(Clone)]
+    struct Counted {
+        set: EndpointSet,
+        calls: Arc<AtomicU32>,
+    }
+
+    impl Service<RequestPacket> for Counted {
+        type Response = ResponsePacket;
+        type Error = TransportError;
+        type Future = TransportFut<'static>;
+
+        fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, request: RequestPacket) -> Self::Future {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            self.set.call(request)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_no_quorum_is_not_retried() {
+        let counted = Counted {
+            set: set_with_quorum(&[(Some("\"0x1\""), 0), (Some("\"0x2\""), 0)], 2),
+            calls: Arc::new(AtomicU32::new(0)),
+        };
+        let policy = RetryPolicy {
+            max_retries: 3,
+            initial_backoff_ms: 1,
+            max_backoff_ms: 10,
+            compute_units_per_second: None,
+        };
+        let mut service = RetryLayer::new(policy).layer(counted.clone());
+        let error = service.call(request("eth_getBalance")).await.unwrap_err();
+        assert_eq!(classify(&error), ErrorKind::RpcError);
+        assert!(!classify(&error).retryable());
+        assert!(error.to_string().contains("no quorum"));
+        assert_eq!(counted.calls.load(Ordering::SeqCst), 1);
+    }
+
+    fn set_with_quorum(nodes: &[(Option<&'static str>, u64)], quorum: usize) -> EndpointSet {
+        EndpointSet {
+            quorum,
+            ..set(nodes, Strategy::Quorum)
+        }
+    }
+
+    #[test]
+    fn test_quorum_defaults_to_majority() {
+        let nodes = [(None, 0); 4];
+        assert_eq!(set(&nodes, Strategy::Quorum).quorum, 3);
+        assert_eq!(set(&nodes[..1], Strategy::Quorum).quorum, 1);
+    }
+
+    #[test]
+    fn test_answers_compare_as_json() {
+        let response = |raw: &str| {
+            ResponsePacket::Single(Response {
+                id: Id::Number(1),
+                payload: ResponsePayload::Success(RawValue::from_string(raw.to_string()).unwrap()),
+            })
+        };
+        assert_eq!(
+            answer(&response(r#"{"a": 1, "b": 2}"#)),
+            answer(&response(r#"{"b":2,"a":1}"#))
+        );
+    }
+}
@@ -0,0 +1,453 @@
+//! The errors tools return. Every error carries a stable `kind` and whether the same call may
+//! succeed if retried in its data, so clients can decide whether to retry, fix their input or give
+//! up without parsing messages.
+
+use std::{borrow::Cow, error::Error};
+
+use alloy_primitives::hex;
+use alloy_transport::{RpcError, TransportError, TransportErrorKind};
+use rmcp::{ErrorData, model::ErrorCode, schemars};
+use serde_json::{Map, Value, json};
+
+use crate::common::{
+    abi,
+    endpoints::NoQuorum,
+    signatures::{self, SelectorKind},
+};
+
+/// What went wrong. Serialized as the `kind` of an error's data; the names are stable.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize, schemars::JsonSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorKind {
+    /// An address or ENS name argument is malformed or does not resolve.
+    InvalidAddress,
+    /// A block argument is malformed or points outside the chain.
+    InvalidBlock,
+    /// Any other argument is invalid.
+    InvalidInput,
+    /// The requested block, transaction or signature does not exist.
+    NotFound,
+    /// The endpoint could not be reached, timed out or answered with a server error.
+    RpcUnreachable,
+    /// The node rejected the request; `rpc_code` holds its JSON-RPC error code. Also reported
+    /// when too few of a quorum network's endpoints agree on an answer.
+    RpcError,
+    /// The call reverted; `revert_data` holds the raw data and `revert` its decoding when the
+    /// error is known.
+    ExecutionReverted,
+    /// The endpoint rate limited the request.
+    RateLimited,
+    /// The tool gave up waiting for something that may still happen, such as a receipt.
+    Timeout,
+    /// The node does not implement the method.
+    UnsupportedMethod,
+    /// The server itself failed.
+    Internal,
+}
+
+impl ErrorKind {
+    /// Whether the same call may succeed later. Everything else needs different input or a
+    /// different endpoint.
+    pub fn retryable(self) -> bool {
+        matches!(
+            self,
+            Self::RpcUnreachable | Self::RateLimited | Self::Timeout
+        )
+    }
+
+    fn code(self) -> ErrorCode {
+        match self {
+            Self::InvalidAddress | Self::InvalidBlock | Self::InvalidInput | Self::NotFound => {
+                ErrorCode::INVALID_PARAMS
+            }
+            _ => ErrorCode::INTERNAL_ERROR,
+        }
+    }
+}
+
+/// Build an error of `kind`. The fields of `data` are kept next to `kind` and `retryable`; data
+/// that is not an object is kept as `detail`.
+pub fn tool_error(
+    kind: ErrorKind,
+    message: impl Into<Cow<'static, str>>,
+    data: Option<Value>,
+) -> ErrorData {
+    let mut fields = match data {
+        Some(Value::Object(fields)) => fields,
+        Some(detail) => Map::from_iter([("detail".to_string(), detail)]),
+        None => Map::new(),
+    };
+    fields.insert("kind".to_string(), json!(kind));
+    fields.insert("retryable".to_string(), Value::Bool(kind.retryable()));
+    ErrorData::new(kind.code(), message, Some(Value::Object(fields)))
+}
+
+/// The kind of an error built by [`tool_error`]. Errors from elsewhere are `internal`.
+pub fn error_kind(error: &ErrorData) -> ErrorKind {
+    error
+        .data
+        .as_ref()
+        .and_then(|data| serde_json::from_value(data.get("kind")?.clone()).ok())
+        .unwrap_or(ErrorKind::Internal)
+}
+
+/// An invalid argument that is neither an address nor a block.
+pub fn invalid_input(message: impl Into<Cow<'static, str>>, data: Option<Value>) -> ErrorData {
+    tool_error(ErrorKind::InvalidInput, message, data)
+}
+
+/// A block, transaction or signature that does not exist.
+pub fn not_found(message: impl Into<Cow<'static, str>>, data: Option<Value>) -> ErrorData {
+    tool_error(ErrorKind::NotFound, message, data)
+}
+
+/// A failure of the server itself, such as a result that cannot be serialized.
+pub fn internal_error(
+    message: impl Into<Cow<'static, str>>,
+    e: impl std::fmt::Display,
+) -> ErrorData {
+    tool_error(
+        ErrorKind::Internal,
+        message,
+        Some(json!({ "detail": e.to_string() })),
+    )
+}
+
+/// Classify an error returned by the node or its transport.
+pub fn rpc_error(message: impl Into<Cow<'static, str>>, error: &TransportError) -> ErrorData {
+    let kind = classify(error);
+    let detail = error.to_string();
+    match error {
+        RpcError::ErrorResp(payload) => match payload.as_revert_data() {
+            Some(data) => reverted(message, &data, detail),
+            None => tool_error(
+                kind,
+                message,
+                Some(json!({
+                    "rpc_code": payload.code,
+                    "rpc_message": payload.message,
+                    "detail": detail,
+                })),
+            ),
+        },
+        RpcError::Transport(TransportErrorKind::HttpError(http)) => tool_error(
+            kind,
+            message,
+            Some(json!({ "http_status": http.status, "detail": detail })),
+        ),
+        RpcError::LocalUsageError(_) | RpcError::SerError(_) => internal_error(message, detail),
+        _ => tool_error(kind, message, Some(json!({ "detail": detail }))),
+    }
+}
+
+/// The kind of an error returned by the node or its transport.
+pub fn classify(error: &TransportError) -> ErrorKind {
+    match error {
+        RpcError::ErrorResp(payload) => {
+            let text = payload.message.to_ascii_lowercase();
+            match payload.code {
+                _ if payload.as_revert_data().is_some() => ErrorKind::ExecutionReverted,
+                3 => ErrorKind::ExecutionReverted,
+                _ if text.contains("execution reverted") => ErrorKind::ExecutionReverted,
+                -32601 => ErrorKind::UnsupportedMethod,
+                // EIP-1474 "limit exceeded", used by most providers for rate and compute limits,
+                // but also for log queries over too many blocks or results, which a retry does not
+                // fix.
+                -32005 if is_result_limit(&text) => ErrorKind::RpcError,
+                429 | -32005 => ErrorKind::RateLimited,
+                _ if is_rate_limit(&text) => ErrorKind::RateLimited,
+                _ if text.contains("not supported") || text.contains("not available") => {
+                    ErrorKind::UnsupportedMethod
+                }
+                _ => ErrorKind::RpcError,
+            }
+        }
+        RpcError::Transport(TransportErrorKind::HttpError(http)) => match http.status {
+            429 => ErrorKind::RateLimited,
+            500.. => ErrorKind::RpcUnreachable,
+            _ if is_rate_limit(&http.body.to_ascii_lowercase()) => ErrorKind::RateLimited,
+            _ => ErrorKind::RpcError,
+        },
+        RpcError::Transport(TransportErrorKind::Custom(custom)) if custom.is::<NoQuorum>() => {
+            ErrorKind::RpcError
+        }
+        RpcError::Transport(TransportErrorKind::Custom(_)) => {
+            match exhausted_retries(error).map(|text| text.to_ascii_lowercase()) {
+                Some(text) if is_result_limit(&text) => ErrorKind::RpcError,
+                Some(text) if text.contains("429") || text.contains("-32005") => {
+                    ErrorKind::RateLimited
+                }
+                Some(text) if is_rate_limit(&text) => ErrorKind::RateLimited,
+                _ => ErrorKind::RpcUnreachable,
+            }
+        }
+        RpcError::Transport(_) => ErrorKind::RpcUnreachable,
+        RpcError::UnsupportedFeature(_) => ErrorKind::UnsupportedMethod,
+        RpcError::LocalUsageError(_) | RpcError::SerError(_) => ErrorKind::Internal,
+        // Some providers answer a rate limited request with a body that is not JSON-RPC at all.
+        RpcError::DeserError { text, .. } if is_rate_limit(&text.to_ascii_lowercase()) => {
+            ErrorKind::RateLimited
+        }
+        // A null or unparseable response: the node answered, but not with what was asked for.
+        _ => ErrorKind::RpcError,
+    }
+}
+
+/// Classify an error that may wrap a node or transport error somewhere in its chain, such as the
+/// errors of `Cast` and of ENS resolution. Errors without one are reported as an `rpc_error`.
+pub fn upstream_error(
+    message: impl Into<Cow<'static, str>>,
+    error: &(dyn Error + 'static),
+) -> ErrorData {
+    match transport_error(error) {
+        Some(transport) => rpc_error(message, transport),
+        None => tool_error(
+            ErrorKind::RpcError,
+            message,
+            Some(json!({ "detail": error.to_string() })),
+        ),
+    }
+}
+
+/// Find the node or transport error `error` was caused by, if any.
+pub fn transport_error<'a>(error: &'a (dyn Error + 'static)) -> Option<&'a TransportError> {
+    let mut current = Some(error);
+    while let Some(error) = current {
+        if let Some(transport) = error.downcast_ref::<TransportError>() {
+            return Some(transport);
+        }
+        current = error.source();
+    }
+    None
+}
+
+/// An execution reverted error with the revert data, decoded as a builtin error or as the first
+/// bundled error signature it matches.
+fn reverted(message: impl Into<Cow<'static, str>>, data: &[u8], detail: String) -> ErrorData {
+    let mut fields = json!({ "revert_data": hex::encode_prefixed(data), "detail": detail });
+    let revert = abi::decode_revert(None, data).ok().or_else(|| {
+        let selector = hex::encode_prefixed(data.get(..4)?);
+        signatures::bundled(&selector, SelectorKind::Function)?
+            .iter()
+            .filter_map(|sig| alloy_json_abi::Error::parse(sig).ok())
+            .find_map(|error| abi::decode_revert(Some(&error), data).ok())
+    });
+    if let Some(revert) = revert {
+        fields["revert"] = json!(revert);
+    }
+    tool_error(ErrorKind::ExecutionReverted, message, Some(fields))
+}
+
+/// Whether the node rejected a query for spanning too many blocks or matching too many results,
+/// which a smaller range may fix.
+pub fn exceeds_result_limit(error: &TransportError) -> bool {
+    match error {
+        RpcError::ErrorResp(payload) => is_result_limit(&payload.message.to_ascii_lowercase()),
+        RpcError::Transport(TransportErrorKind::HttpError(http)) => {
+            http.status == 413 || is_result_limit(&http.body.to_ascii_lowercase())
+        }
+        _ => {
+            exhausted_retries(error).is_some_and(|text| is_result_limit(&text.to_ascii_lowercase()))
+        }
+    }
+}
+
+/// What foundry's provider prefixes an error with when its retry layer gives up on it.
+const RETRIES_EXCEEDED: &str = "Max retries exceeded ";
+
+/// The text of an error foundry's provider gave up retrying, which is all it keeps of it. Its
+/// retries are turned off, so this is any error its retry policy considers transient: rate limits,
+/// 503s and dropped connections.
+pub fn exhausted_retries(error: &TransportError) -> Option<String> {
+    let RpcError::Transport(TransportErrorKind::Custom(custom)) = error else {
+        return None;
+    };
+    custom
+        .to_string()
+        .strip_prefix(RETRIES_EXCEEDED)
+        .map(str::to_string)
+}
+
+fn is_rate_limit(text: &str) -> bool {
+    [
+        "rate limit",
+        "too many requests",
+        "request rate exceeded",
+        "request count exceeded",
+        "capacity",
+        "throughput",
+    ]
+    .iter()
+    .any(|needle| text.contains(needle))
+}
+
+/// Whether `text` is one of the ways providers reject a log query for its size: geth's "query
+/// returned more than 10000 results", Alchemy's "log response size exceeded", and block range
+/// limits such as BSC's "exceed maximum block range" or QuickNode's "limited to a 10,000 blocks
+/// range".
+fn is_result_limit(text: &str) -> bool {
+    [
+        "query returned more than",
+        "response size",
+        "block range",
+        "blocks range",
+        "too many results",
+    ]
+    .iter()
+    .any(|needle| text.contains(needle))
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_json_rpc::ErrorPayload;
+    use alloy_transport::HttpError;
+
+    use super::*;
+
+    fn kind(error: &ErrorData) -> Value {
+        error.data.as_ref().unwrap()["kind"].clone()
+    }
+
+    fn error_resp(code: i64, message: &'static str, data: Option<&str>) -> TransportError {
+        RpcError::ErrorResp(ErrorPayload {
+            code,
+            message: message.into(),
+            data: data
+                .map(|data| serde_json::value::RawValue::from_string(data.to_string()).unwrap()),
+        })
+    }
+
+    #[test]
+    fn test_tool_error_keeps_data() {
+        let error = invalid_input("Bad", Some(json!({ "reason": "missing" })));
+        assert_eq!(error.code, ErrorCode::INVALID_PARAMS);
+        let data = error.data.unwrap();
+        assert_eq!(data["reason"], "missing");
+        assert_eq!(data["kind"], "invalid_input");
+        assert_eq!(data["retryable"], false);
+
+        let error = tool_error(ErrorKind::RateLimited, "Slow down", Some(json!("429")));
+        assert_eq!(error.code, ErrorCode::INTERNAL_ERROR);
+        assert_eq!(error.data.as_ref().unwrap()["detail"], "429");
+        assert_eq!(error.data.unwrap()["retryable"], true);
+        assert!(ErrorKind::Timeout.retryable());
+        assert!(!ErrorKind::ExecutionReverted.retryable());
+    }
+
+    #[test]
+    fn test_rpc_error_classification() {
+        let error = rpc_error("Failed", &error_resp(-32601, "method not found", None));
+        assert_eq!(kind(&error), "unsupported_method");
+
+        let error = rpc_error("Failed", &error_resp(-32005, "limit exceeded", None));
+        assert_eq!(kind(&error), "rate_limited");
+
+        let message = "query returned more than 10000 results";
+        let error = rpc_error("Failed", &error_resp(-32005, message, None));
+        assert_eq!(kind(&error), "rpc_error");
+
+        let error = rpc_error("Failed", &error_resp(-32000, "header not found", None));
+        assert_eq!(kind(&error), "rpc_error");
+        assert_eq!(error.data.unwrap()["rpc_code"], -32000);
+
+        let http = |status| {
+            RpcError::Transport(TransportErrorKind::HttpError(HttpError {
+                status,
+                body: String::new(),
+            }))
+        };
+        assert_eq!(kind(&rpc_error("Failed", &http(429))), "rate_limited");
+        assert_eq!(kind(&rpc_error("Failed", &http(503))), "rpc_unreachable");
+        assert_eq!(kind(&rpc_error("Failed", &http(401))), "rpc_error");
+
+        let gone = RpcError::Transport(TransportErrorKind::BackendGone);
+        assert_eq!(kind(&rpc_error("Failed", &gone)), "rpc_unreachable");
+    }
+
+    #[test]
+    fn test_exhausted_retries_are_classified() {
+        let exhausted =
+            |text: &str| TransportErrorKind::custom_str(&format!("{RETRIES_EXCEEDED}{text}"));
+        let limited = exhausted("HTTP error 429 with body: Too Many Requests");
+        assert_eq!(classify(&limited), ErrorKind::RateLimited);
+        let limited =
+            exhausted("server returned an error response: error code -32005: limit exceeded");
+        assert_eq!(classify(&limited), ErrorKind::RateLimited);
+        let unavailable = exhausted("HTTP error 503 with body: ");
+        assert_eq!(classify(&unavailable), ErrorKind::RpcUnreachable);
+
+        let too_many = exhausted(
+            "server returned an error response: error code -32005: query returned more than 10000 results",
+        );
+        assert_eq!(classify(&too_many), ErrorKind::RpcError);
+        assert!(exceeds_result_limit(&too_many));
+
+        let other = TransportErrorKind::custom_str("no quorum");
+        assert_eq!(exhausted_retries(&other), None);
+    }
+
+    #[test]
+    fn test_exceeds_result_limit() {
+        let limited = [
+            error_resp(-32005, "query returned more than 10000 results", None),
+            error_resp(-32602, "Log response size exceeded.", None),
+            error_resp(-32000, "block range is too large", None),
+            error_resp(-32000, "exceed maximum block range: 5000", None),
+            error_resp(
+                -32602,
+                "eth_getLogs is limited to a 10,000 blocks range",
+                None,
+            ),
+        ];
+        assert!(limited.iter().all(exceeds_result_limit));
+
+        let other = [
+            error_resp(-32005, "limit exceeded", None),
+            error_resp(-32602, "invalid params", None),
+            RpcError::Transport(TransportErrorKind::BackendGone),
+        ];
+        assert!(!other.iter().any(exceeds_result_limit));
+
+        // Rate limits that happen to mention a number or a range are retried, not split.
+        let rate_limited = [
+            error_resp(-32005, "more than 10 requests per second", None),
+            error_resp(-32005, "block out of range", None),
+        ];
+        for error in &rate_limited {
+            assert!(!exceeds_result_limit(error));
+            assert_eq!(classify(error), ErrorKind::RateLimited);
+        }
+    }
+
+    #[test]
+    fn test_rpc_error_decodes_reverts() {
+        // Error("boom")
+        let data = "0x08c379a0\
+            0000000000000000000000000000000000000000000000000000000000000020\
+            0000000000000000000000000000000000000000000000000000000000000004\
+            626f6f6d00000000000000000000000000000000000000000000000000000000";
+        let error = rpc_error(
+            "Call failed",
+            &error_resp(3, "execution reverted", Some(&format!("\"{data}\""))),
+        );
+        let data = error.data.unwrap();
+        assert_eq!(data["kind"], "execution_reverted");
+        assert_eq!(data["revert"]["signature"], "Error(string)");
+        assert_eq!(data["revert"]["decoded"][0]["value"], "boom");
+
+        let error = rpc_error("Call failed", &error_resp(3, "execution reverted", None));
+        assert_eq!(kind(&error), "execution_reverted");
+    }
+
+    #[test]
+    fn test_upstream_error_finds_transport_error() {
+        let gone: TransportError = RpcError::Transport(TransportErrorKind::BackendGone);
+        assert!(transport_error(&gone).is_some());
+        assert_eq!(kind(&upstream_error("Failed", &gone)), "rpc_unreachable");
+
+        let error = upstream_error("Failed", &std::fmt::Error);
+        assert_eq!(kind(&error), "rpc_error");
+    }
+}
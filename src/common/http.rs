@@ -0,0 +1,132 @@
+use std::{net::SocketAddr, sync::Arc};
+
+use anyhow::{Context, Result};
+use axum::{Router, http::HeaderValue};
+use rmcp::transport::streamable_http_server::{
+    StreamableHttpServerConfig, StreamableHttpService, session::local::LocalSessionManager,
+};
+use tokio_util::sync::CancellationToken;
+use tower_http::cors::{AllowOrigin, Any, CorsLayer};
+
+use crate::common::server::Server;
+
+/// Options for serving the MCP server over streamable HTTP.
+#[derive(Debug, Clone)]
+pub struct HttpOptions {
+    /// The socket address to listen on.
+    pub bind: SocketAddr,
+
+    /// The path the MCP endpoint is mounted at.
+    pub path: String,
+
+    /// Origins allowed by CORS. An empty list or `*` allows any origin.
+    pub allowed_origins: Vec<String>,
+
+    /// Whether to keep a session per client instead of answering each request statelessly.
+    pub stateful: bool,
+}
+
+/// Serve `server` on an axum router until SIGINT or SIGTERM is received.
+pub async fn serve_http(server: Server, opts: HttpOptions) -> Result<()> {
+    let ct = CancellationToken::new();
+
+    let service = StreamableHttpService::new(
+        move || Ok(server.clone()),
+        Arc::new(LocalSessionManager::default()),
+        StreamableHttpServerConfig {
+            stateful_mode: opts.stateful,
+            cancellation_token: ct.child_token(),
+            ..Default::default()
+        },
+    );
+
+    let router = Router::new()
+        .nest_service(&opts.path, service)
+        .layer(cors_layer(&opts.allowed_origins)?);
+
+    let listener = tokio::net::TcpListener::bind(opts.bind)
+        .await
+        .with_context(|| format!("failed to bind {}", opts.bind))?;
+    tracing::info!("Listening on http://{}{}", opts.bind, opts.path);
+
+    axum::serve(listener, router)
+        .with_graceful_shutdown(async move {
+            shutdown_signal().await;
+            tracing::info!("Shutting down MCP server");
+            ct.cancel();
+        })
+        .await?;
+    Ok(())
+}
+
+fn cors_layer(allowed_origins: &[String]) -> Result<CorsLayer> {
+    let layer = CorsLayer::new()
+        .allow_methods(Any)
+        .allow_headers(Any)
+        .expose_headers(Any);
+
+    if allowed_origins.is_empty() || allowed_origins.iter().any(|o| o == "*") {
+        return Ok(layer.allow_origin(Any));
+    }
+
+    let origins = allowed_origins
+        .iter()
+        .map(|o| HeaderValue::from_str(o).with_context(|| format!("invalid CORS origin: {o}")))
+        .collect::<Result<Vec<_>>>()?;
+    Ok(layer.allow_origin(AllowOrigin::list(origins)))
+}
+
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            tracing::error!("failed to listen for SIGINT: {}", e);
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(e) => {
+                tracing::error!("failed to listen for SIGTERM: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cors_layer_any_origin() {
+        assert!(cors_layer(&[]).is_ok());
+        assert!(cors_layer(&["*".to_string()]).is_ok());
+    }
+
+    #[test]
+    fn test_cors_layer_origin_list() {
+        let origins = vec![
+            "https://a.example".to_string(),
+            "https://b.example".to_string(),
+        ];
+        assert!(cors_layer(&origins).is_ok());
+    }
+
+    #[test]
+    fn test_cors_layer_invalid_origin() {
+        let origins = vec!["bad\norigin".to_string()];
+        assert!(cors_layer(&origins).is_err());
+    }
+}
@@ -0,0 +1,86 @@
+//! How a tool call was answered, returned in the `_meta` of its result: the endpoints that
+//! answered its requests and how many requests the cache answered instead.
+
+use std::cell::RefCell;
+
+use rmcp::model::{CallToolResult, Meta};
+
+tokio::task_local! {
+    static CALL: RefCell<CallMeta>;
+}
+
+#[derive(Debug, Default)]
+pub struct CallMeta {
+    /// The endpoints that answered, in the order they first answered.
+    pub endpoints: Vec<String>,
+    /// The requests answered from the response cache.
+    pub cache_hits: usize,
+}
+
+impl CallMeta {
+    /// Add the metadata to the `_meta` of `result`.
+    pub fn apply(self, result: &mut CallToolResult) {
+        if self.endpoints.is_empty() && self.cache_hits == 0 {
+            return;
+        }
+        let meta = result.meta.get_or_insert_with(Meta::new);
+        if !self.endpoints.is_empty() {
+            meta.insert("endpoints".to_string(), self.endpoints.into());
+        }
+        if self.cache_hits > 0 {
+            meta.insert("cache_hits".to_string(), self.cache_hits.into());
+        }
+    }
+}
+
+/// Run `future`, returning its output and how its requests were answered.
+pub async fn track<F: Future>(future: F) -> (F::Output, CallMeta) {
+    CALL.scope(RefCell::new(CallMeta::default()), async move {
+        let output = future.await;
+        (output, CALL.with(RefCell::take))
+    })
+    .await
+}
+
+/// Note that the endpoint labelled `label` answered a request.
+pub fn record_endpoint(label: &str) {
+    // Requests made outside `track`, such as in tests, are not reported anywhere.
+    let _ = CALL.try_with(|call| {
+        let endpoints = &mut call.borrow_mut().endpoints;
+        if !endpoints.iter().any(|l| l == label) {
+            endpoints.push(label.to_string());
+        }
+    });
+}
+
+/// Note that the cache answered a request.
+pub fn record_cache_hit() {
+    let _ = CALL.try_with(|call| call.borrow_mut().cache_hits += 1);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_track_collects_meta() {
+        let ((), meta) = track(async {
+            record_endpoint("https://a.example/<redacted>");
+            record_cache_hit();
+            record_endpoint("https://a.example/<redacted>");
+            record_cache_hit();
+        })
+        .await;
+        assert_eq!(meta.endpoints, ["https://a.example/<redacted>"]);
+        assert_eq!(meta.cache_hits, 2);
+
+        let mut result = CallToolResult::success(vec![]);
+        meta.apply(&mut result);
+        let meta = result.meta.unwrap();
+        assert_eq!(meta["cache_hits"], 2);
+
+        let mut result = CallToolResult::success(vec![]);
+        CallMeta::default().apply(&mut result);
+        assert!(result.meta.is_none());
+    }
+}
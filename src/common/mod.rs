@@ -1,7 +1,19 @@
+mod abi;
 mod account_tools;
 mod block_tools;
+mod bytecode;
+mod cache;
 mod chain_tools;
+pub mod config;
+mod endpoints;
+mod error;
+pub mod http;
+mod meta;
+mod provider;
+mod retry;
 pub mod server;
+mod signatures;
+mod transport;
 
 mod common;
 mod utility_tools;
@@ -0,0 +1,362 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use alloy_provider::{Provider, RootProvider, network::AnyNetwork};
+use alloy_rpc_client::ClientBuilder;
+use alloy_transport::{BoxTransport, utils::guess_local_url};
+use foundry_cli::{
+    opts::RpcOpts,
+    utils::{self, LoadConfig},
+};
+use rmcp::ErrorData;
+use serde_json::json;
+use tower::Layer;
+
+use crate::common::{
+    cache::{CacheLayer, ResponseCache},
+    config::{redact_headers, redact_url},
+    endpoints::{Endpoint, EndpointSet, Strategy},
+    error::invalid_input,
+    retry::{BudgetLayer, ComputeBudgets, RetryLayer, RetryPolicy},
+    transport::HttpTransport,
+};
+
+/// The provider type returned by `utils::get_provider`, which pooled providers match.
+pub type RetryProvider = RootProvider<AnyNetwork>;
+
+/// Identifies a pooled provider. Two calls share a provider only if every option matches.
+#[derive(Clone, Default, PartialEq, Eq, Hash)]
+pub struct ProviderKey {
+    /// The endpoints of the network, in order of preference.
+    pub urls: Vec<String>,
+    pub strategy: Strategy,
+    pub quorum: Option<usize>,
+    pub headers: Vec<String>,
+    pub jwt_secret: Option<String>,
+    pub timeout_secs: Option<u64>,
+    pub accept_invalid_certs: bool,
+    pub no_proxy: bool,
+    pub retry: RetryPolicy,
+}
+
+impl ProviderKey {
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            urls: vec![url.into()],
+            ..Default::default()
+        }
+    }
+
+    fn rpc_opts(&self, url: &str) -> RpcOpts {
+        RpcOpts {
+            url: Some(url.to_string()),
+            accept_invalid_certs: self.accept_invalid_certs,
+            no_proxy: self.no_proxy,
+            flashbots: false,
+            jwt_secret: self.jwt_secret.clone(),
+            rpc_timeout: self.timeout_secs,
+            rpc_headers: (!self.headers.is_empty()).then(|| self.headers.clone()),
+            curl: false,
+        }
+    }
+}
+
+impl fmt::Debug for ProviderKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ProviderKey")
+            .field(
+                "urls",
+                &self
+                    .urls
+                    .iter()
+                    .map(|url| redact_url(url))
+                    .collect::<Vec<_>>(),
+            )
+            .field("strategy", &self.strategy)
+            .field("quorum", &self.quorum)
+            .field("headers", &redact_headers(&self.headers))
+            .field(
+                "jwt_secret",
+                &self.jwt_secret.as_ref().map(|_| "<redacted>"),
+            )
+            .field("timeout_secs", &self.timeout_secs)
+            .field("accept_invalid_certs", &self.accept_invalid_certs)
+            .field("no_proxy", &self.no_proxy)
+            .field("retry", &self.retry)
+            .finish()
+    }
+}
+
+struct PoolEntry {
+    provider: RetryProvider,
+    last_used: Instant,
+}
+
+/// Providers shared across tool calls, so foundry config is loaded and connections are opened
+/// once per endpoint instead of once per call.
+pub struct ProviderPool {
+    entries: Mutex<HashMap<ProviderKey, PoolEntry>>,
+    max_size: usize,
+    idle_timeout: Duration,
+    cache: Arc<ResponseCache>,
+    /// Outlive the providers, which come and go with the pool.
+    budgets: ComputeBudgets,
+}
+
+impl ProviderPool {
+    pub fn new(max_size: usize, idle_timeout: Duration, cache: Arc<ResponseCache>) -> Self {
+        Self {
+            entries: Mutex::new(HashMap::new()),
+            max_size: max_size.max(1),
+            idle_timeout,
+            cache,
+            budgets: ComputeBudgets::default(),
+        }
+    }
+
+    /// Return the pooled provider for `key`, building one if needed.
+    pub fn get(&self, key: &ProviderKey) -> Result<RetryProvider, ErrorData> {
+        self.get_or_insert_with(key, || build_provider(key, &self.cache, &self.budgets))
+    }
+
+    fn get_or_insert_with(
+        &self,
+        key: &ProviderKey,
+        build: impl FnOnce() -> Result<RetryProvider, ErrorData>,
+    ) -> Result<RetryProvider, ErrorData> {
+        if let Some(provider) = self.lookup(key) {
+            return Ok(provider);
+        }
+
+        // Building loads foundry config from disk and sets up transports, so it runs without the
+        // lock. Calls racing to build the same provider keep whichever was inserted first.
+        let provider = build()?;
+        let now = Instant::now();
+        let mut entries = self.lock();
+        if let Some(entry) = entries.get_mut(key) {
+            entry.last_used = now;
+            return Ok(entry.provider.clone());
+        }
+        if entries.len() >= self.max_size {
+            if let Some(oldest) = entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key, _)| key.clone())
+            {
+                entries.remove(&oldest);
+            }
+        }
+        entries.insert(
+            key.clone(),
+            PoolEntry {
+                provider: provider.clone(),
+                last_used: now,
+            },
+        );
+        Ok(provider)
+    }
+
+    /// Return the pooled provider for `key` if there is one, dropping idle providers first.
+    fn lookup(&self, key: &ProviderKey) -> Option<RetryProvider> {
+        let now = Instant::now();
+        let mut entries = self.lock();
+        entries.retain(|_, entry| now.duration_since(entry.last_used) < self.idle_timeout);
+        let entry = entries.get_mut(key)?;
+        entry.last_used = now;
+        Some(entry.provider.clone())
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<ProviderKey, PoolEntry>> {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+}
+
+/// Build the provider for `key` over the endpoints foundry's `utils::get_provider` would build,
+/// with a [`RetryLayer`] in place of foundry's retries. HTTP endpoints are sent requests by an
+/// [`HttpTransport`], which keeps the `Retry-After` header of a rate limited reply for the retry
+/// layer. Foundry's retry layer, used for the other endpoints, backs off without jitter and cannot
+/// be tuned beyond a retry count, so it is given no retries: it passes a transient error on at
+/// once, as text that `exhausted_retries` classifies and reads backoff hints from.
+///
+/// The endpoints of a network share one retry layer, so a request is retried once every endpoint
+/// failed it. Each endpoint keeps to its own compute-unit budget, and cached responses skip all of
+/// them.
+fn build_provider(
+    key: &ProviderKey,
+    cache: &Arc<ResponseCache>,
+    budgets: &ComputeBudgets,
+) -> Result<RetryProvider, ErrorData> {
+    let mut endpoints = Vec::with_capacity(key.urls.len());
+    let mut is_local = true;
+    for url in &key.urls {
+        let (transport, local) = endpoint_transport(key, url)?;
+        is_local &= local;
+        let transport = match key.retry.compute_units_per_second {
+            Some(per_second) => {
+                let budget = BudgetLayer::new(budgets.get(url, per_second));
+                BoxTransport::new(budget.layer(transport))
+            }
+            None => transport,
+        };
+        endpoints.push(Endpoint::new(redact_url(url), transport));
+    }
+    let client = ClientBuilder::default()
+        .layer(CacheLayer::new(cache.clone(), &key.urls))
+        .layer(RetryLayer::new(key.retry.clone()))
+        .transport(
+            EndpointSet::new(endpoints, key.strategy, key.quorum),
+            is_local,
+        );
+    Ok(RootProvider::new(client))
+}
+
+/// The transport of the endpoint at `url` with the key's options, and whether it is local. HTTP
+/// endpoints get an [`HttpTransport`]; the others, and those with a JWT secret, the transport of
+/// the provider foundry's `utils::get_provider` builds, with foundry's retries turned off.
+fn endpoint_transport(key: &ProviderKey, url: &str) -> Result<(BoxTransport, bool), ErrorData> {
+    let invalid_url = |e: &dyn fmt::Display| {
+        invalid_input("Invalid RPC URL", Some(json!({ "reason": e.to_string() })))
+    };
+    let config = key
+        .rpc_opts(url)
+        .load_config()
+        .map_err(|e| invalid_url(&e))?;
+    let resolved = config
+        .get_rpc_url_or_localhost_http()
+        .map_err(|e| invalid_url(&e))?;
+    if let Some(http) =
+        HttpTransport::from_config(&config, &resolved).map_err(|e| invalid_url(&e))?
+    {
+        return Ok((BoxTransport::new(http), guess_local_url(resolved.as_ref())));
+    }
+    let provider = utils::get_provider_builder(&config)
+        .and_then(|builder| builder.max_retry(0).build())
+        .map_err(|e| invalid_url(&e))?;
+    let client = provider.client();
+    Ok((client.transport().clone(), client.is_local()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(url: &str) -> ProviderKey {
+        ProviderKey::new(url)
+    }
+
+    fn pool(max_size: usize, idle_timeout: Duration) -> ProviderPool {
+        ProviderPool::new(
+            max_size,
+            idle_timeout,
+            Arc::new(ResponseCache::new(0, None, 0)),
+        )
+    }
+
+    #[test]
+    fn test_pool_reuses_provider() {
+        let pool = pool(4, Duration::from_secs(60));
+        let mut builds = 0;
+        for _ in 0..3 {
+            pool.get_or_insert_with(&key("http://localhost:8545"), || {
+                builds += 1;
+                build_provider(&key("http://localhost:8545"), &pool.cache, &pool.budgets)
+            })
+            .unwrap();
+        }
+        assert_eq!(builds, 1);
+        assert_eq!(pool.len(), 1);
+    }
+
+    #[test]
+    fn test_pool_caps_size() {
+        let pool = pool(2, Duration::from_secs(60));
+        for port in 0..5 {
+            pool.get(&key(&format!("http://localhost:{}", 8545 + port)))
+                .unwrap();
+        }
+        assert_eq!(pool.len(), 2);
+    }
+
+    #[test]
+    fn test_pool_evicts_idle_providers() {
+        let pool = pool(4, Duration::ZERO);
+        pool.get(&key("http://localhost:8545")).unwrap();
+        pool.get(&key("http://localhost:8546")).unwrap();
+        assert_eq!(pool.len(), 1);
+    }
+
+    #[test]
+    fn test_pool_keys_on_options() {
+        let pool = pool(4, Duration::from_secs(60));
+        let mut with_timeout = key("http://localhost:8545");
+        with_timeout.timeout_secs = Some(5);
+        pool.get(&key("http://localhost:8545")).unwrap();
+        pool.get(&with_timeout).unwrap();
+        assert_eq!(pool.len(), 2);
+    }
+
+    #[test]
+    fn test_provider_key_debug_is_redacted() {
+        let key = ProviderKey {
+            urls: vec!["https://eth.example/v2/secret-key".to_string()],
+            headers: vec!["Authorization: Bearer secret-token".to_string()],
+            jwt_secret: Some("secret-jwt".to_string()),
+            ..Default::default()
+        };
+        let debug = format!("{:?}", key);
+        assert!(!debug.contains("secret"));
+        assert!(debug.contains("Authorization"));
+    }
+
+    #[test]
+    fn test_pool_keys_on_retry_policy() {
+        let pool = pool(4, Duration::from_secs(60));
+        let mut limited = key("http://localhost:8545");
+        limited.retry.compute_units_per_second = Some(330);
+        pool.get(&key("http://localhost:8545")).unwrap();
+        pool.get(&limited).unwrap();
+        assert_eq!(pool.len(), 2);
+    }
+
+    #[test]
+    fn test_pool_builds_multi_endpoint_networks() {
+        let pool = pool(4, Duration::from_secs(60));
+        let mut network = key("http://localhost:8545");
+        network.urls.push("http://localhost:8546".to_string());
+        network.strategy = Strategy::Quorum;
+        pool.get(&network).unwrap();
+        network.strategy = Strategy::Fastest;
+        pool.get(&network).unwrap();
+        assert_eq!(pool.len(), 2);
+    }
+
+    #[test]
+    fn test_pool_builds_without_lock() {
+        let pool = pool(4, Duration::from_secs(60));
+        pool.get_or_insert_with(&key("http://localhost:8545"), || {
+            // Another call using the pool while this one builds must not block.
+            pool.get(&key("http://localhost:8546"))?;
+            build_provider(&key("http://localhost:8545"), &pool.cache, &pool.budgets)
+        })
+        .unwrap();
+        assert_eq!(pool.len(), 2);
+    }
+
+    #[test]
+    fn test_pool_build_error_is_not_cached() {
+        let pool = pool(4, Duration::from_secs(60));
+        let result = pool.get_or_insert_with(&key("http://localhost:8545"), || {
+            Err(ErrorData::internal_error("boom", None))
+        });
+        assert!(result.is_err());
+        assert_eq!(pool.len(), 0);
+    }
+}
@@ -0,0 +1,604 @@
+//! Retries for upstream RPC requests. Public endpoints routinely answer with 429s and transient
+//! 5xx errors, so requests that fail with a retryable error are sent again after an exponential,
+//! jittered backoff, and a compute-unit budget spaces requests out before the endpoint has to.
+
+use std::{
+    cell::Cell,
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use alloy_json_rpc::{RequestPacket, ResponsePacket};
+use alloy_transport::{
+    RpcError, TransportError, TransportErrorKind, TransportFut, TransportResult,
+};
+use serde::Deserialize;
+use serde_json::Value;
+use tower::{Layer, Service};
+
+use crate::common::error::{classify, exhausted_retries};
+
+tokio::task_local! {
+    /// The wait asked for by the endpoints the current attempt of a request was sent to.
+    static RETRY_AFTER: Cell<Option<Duration>>;
+}
+
+/// Note that an endpoint asked to wait `delay` before retrying the request being sent, as HTTP
+/// endpoints do with a `Retry-After` header. The longest wait any endpoint asked for is kept.
+pub fn record_retry_after(delay: Duration) {
+    // Requests sent outside a `RetryService` are not retried.
+    let _ = RETRY_AFTER.try_with(|hint| {
+        let longest = hint.get().map_or(delay, |hint| hint.max(delay));
+        hint.set(Some(longest));
+    });
+}
+
+/// How requests to an endpoint are retried and paced.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RetryPolicy {
+    /// How many times a failed request is retried. `0` disables retries.
+    #[serde(default = "default_max_retries")]
+    pub max_retries: u32,
+
+    /// The backoff before the first retry, in milliseconds. It doubles with every retry.
+    #[serde(default = "default_initial_backoff_ms")]
+    pub initial_backoff_ms: u64,
+
+    /// The longest backoff, in milliseconds. An endpoint asking to wait longer fails the request
+    /// instead.
+    #[serde(default = "default_max_backoff_ms")]
+    pub max_backoff_ms: u64,
+
+    /// The compute units each endpoint allows per second, as published by providers such as
+    /// Alchemy or Infura. Requests to an endpoint are held back once its budget is spent.
+    /// Unlimited by default.
+    #[serde(default)]
+    pub compute_units_per_second: Option<u64>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: default_max_retries(),
+            initial_backoff_ms: default_initial_backoff_ms(),
+            max_backoff_ms: default_max_backoff_ms(),
+            compute_units_per_second: None,
+        }
+    }
+}
+
+fn default_max_retries() -> u32 {
+    5
+}
+
+fn default_initial_backoff_ms() -> u64 {
+    500
+}
+
+fn default_max_backoff_ms() -> u64 {
+    30_000
+}
+
+impl RetryPolicy {
+    /// The backoff before retry number `attempt + 1`: a random duration between half and all of
+    /// the exponential backoff, so clients that failed together do not retry together.
+    fn backoff(&self, attempt: u32) -> Duration {
+        let ceiling = self
+            .initial_backoff_ms
+            .saturating_mul(1 << attempt.min(32))
+            .min(self.max_backoff_ms);
+        Duration::from_millis(rand::random_range(ceiling / 2..=ceiling))
+    }
+}
+
+/// Wraps a transport in a [`RetryService`].
+#[derive(Debug, Clone)]
+pub struct RetryLayer {
+    policy: Arc<RetryPolicy>,
+}
+
+impl RetryLayer {
+    pub fn new(policy: RetryPolicy) -> Self {
+        Self {
+            policy: Arc::new(policy),
+        }
+    }
+}
+
+impl<S> Layer<S> for RetryLayer {
+    type Service = RetryService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RetryService {
+            inner,
+            policy: self.policy.clone(),
+        }
+    }
+}
+
+/// A transport that retries requests failing with a retryable error.
+#[derive(Debug, Clone)]
+pub struct RetryService<S> {
+    inner: S,
+    policy: Arc<RetryPolicy>,
+}
+
+impl<S> Service<RequestPacket> for RetryService<S>
+where
+    S: Service<RequestPacket, Response = ResponsePacket, Error = TransportError>
+        + Clone
+        + Send
+        + Sync
+        + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = ResponsePacket;
+    type Error = TransportError;
+    type Future = TransportFut<'static>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: RequestPacket) -> Self::Future {
+        let this = self.clone();
+        Box::pin(this.send(request))
+    }
+}
+
+impl<S> RetryService<S>
+where
+    S: Service<RequestPacket, Response = ResponsePacket, Error = TransportError> + Clone,
+{
+    async fn send(mut self, request: RequestPacket) -> TransportResult<ResponsePacket> {
+        let mut attempt = 0;
+        loop {
+            let (response, hint) = RETRY_AFTER
+                .scope(Cell::new(None), async {
+                    let response = self.inner.call(request.clone()).await;
+                    (response, RETRY_AFTER.with(Cell::get))
+                })
+                .await;
+            let delay = match &response {
+                Ok(packet) => packet.as_error().and_then(|payload| {
+                    self.retry_delay(&RpcError::ErrorResp(payload.clone()), attempt, hint)
+                }),
+                Err(error) => self.retry_delay(error, attempt, hint),
+            };
+            let Some(delay) = delay else {
+                return response;
+            };
+            attempt += 1;
+            tracing::debug!(attempt, ?delay, "Retrying RPC request");
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    /// How long to wait before retrying after `error`, or `None` if the request should fail.
+    /// `hint` is the wait an endpoint asked for in a `Retry-After` header, which takes precedence
+    /// over hints in the error body.
+    fn retry_delay(
+        &self,
+        error: &TransportError,
+        attempt: u32,
+        hint: Option<Duration>,
+    ) -> Option<Duration> {
+        if attempt >= self.policy.max_retries || !classify(error).retryable() {
+            return None;
+        }
+        match hint.or_else(|| retry_after(error)) {
+            Some(hint) if hint > Duration::from_millis(self.policy.max_backoff_ms) => None,
+            Some(hint) => Some(hint),
+            None => Some(self.policy.backoff(attempt)),
+        }
+    }
+}
+
+/// The compute-unit budgets of the endpoints, kept by URL for as long as the server runs, so
+/// rebuilding or evicting a pooled provider does not hand its endpoint a fresh budget.
+#[derive(Debug, Default)]
+pub struct ComputeBudgets {
+    budgets: Mutex<HashMap<(String, u64), Arc<ComputeBudget>>>,
+}
+
+impl ComputeBudgets {
+    /// The budget of `per_second` compute units of the endpoint at `url`. Networks that give the
+    /// same URL different budgets each keep to their own.
+    pub fn get(&self, url: &str, per_second: u64) -> Arc<ComputeBudget> {
+        let mut budgets = self.budgets.lock().unwrap_or_else(|e| e.into_inner());
+        budgets
+            .entry((url.to_string(), per_second))
+            .or_insert_with(|| Arc::new(ComputeBudget::new(per_second)))
+            .clone()
+    }
+}
+
+/// Wraps an endpoint's transport in a [`BudgetService`].
+#[derive(Debug, Clone)]
+pub struct BudgetLayer {
+    budget: Arc<ComputeBudget>,
+}
+
+impl BudgetLayer {
+    pub fn new(budget: Arc<ComputeBudget>) -> Self {
+        Self { budget }
+    }
+}
+
+impl<S> Layer<S> for BudgetLayer {
+    type Service = BudgetService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        BudgetService {
+            inner,
+            budget: self.budget.clone(),
+        }
+    }
+}
+
+/// A transport that holds requests back until the endpoint's compute-unit budget allows them.
+#[derive(Debug, Clone)]
+pub struct BudgetService<S> {
+    inner: S,
+    budget: Arc<ComputeBudget>,
+}
+
+impl<S> Service<RequestPacket> for BudgetService<S>
+where
+    S: Service<RequestPacket, Response = ResponsePacket, Error = TransportError>
+        + Clone
+        + Send
+        + Sync
+        + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = ResponsePacket;
+    type Error = TransportError;
+    type Future = TransportFut<'static>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: RequestPacket) -> Self::Future {
+        let mut inner = self.inner.clone();
+        let wait = self.budget.reserve(request_cost(&request));
+        Box::pin(async move {
+            tokio::time::sleep(wait).await;
+            inner.call(request).await
+        })
+    }
+}
+
+/// A token bucket of compute units, refilled continuously up to one second's worth.
+#[derive(Debug)]
+pub struct ComputeBudget {
+    per_second: f64,
+    bucket: Mutex<Bucket>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    available: f64,
+    updated: Instant,
+}
+
+impl ComputeBudget {
+    fn new(per_second: u64) -> Self {
+        let per_second = per_second.max(1) as f64;
+        Self {
+            per_second,
+            bucket: Mutex::new(Bucket {
+                available: per_second,
+                updated: Instant::now(),
+            }),
+        }
+    }
+
+    /// Spend `cost` units and return how long to wait before sending. Units are spent up front,
+    /// so requests waiting for the budget are sent in the order they reserved it.
+    fn reserve(&self, cost: u64) -> Duration {
+        let mut bucket = self.bucket.lock().unwrap_or_else(|e| e.into_inner());
+        let now = Instant::now();
+        let refill = now.duration_since(bucket.updated).as_secs_f64() * self.per_second;
+        bucket.available = (bucket.available + refill).min(self.per_second);
+        bucket.updated = now;
+        bucket.available -= (cost as f64).min(self.per_second);
+        if bucket.available >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-bucket.available / self.per_second)
+        }
+    }
+}
+
+/// The compute units a request costs, roughly as priced by the common providers.
+fn request_cost(request: &RequestPacket) -> u64 {
+    let cost = |method: &str| match method {
+        "eth_chainId" | "net_version" | "web3_clientVersion" => 5,
+        "eth_blockNumber" | "eth_gasPrice" | "eth_maxPriorityFeePerGas" => 10,
+        "eth_getBalance" | "eth_getCode" | "eth_getStorageAt" | "eth_getTransactionCount" => 20,
+        "eth_call" | "eth_getProof" => 26,
+        "eth_getLogs" | "eth_feeHistory" => 75,
+        "eth_estimateGas" => 87,
+        method if method.starts_with("debug_") || method.starts_with("trace_") => 300,
+        _ => 20,
+    };
+    match request {
+        RequestPacket::Single(request) => cost(request.method()),
+        RequestPacket::Batch(requests) => requests.iter().map(|r| cost(r.method())).sum(),
+    }
+}
+
+/// How long the endpoint asked to wait before retrying, if it said so in the error body rather
+/// than a `Retry-After` header: as `retry_after`, `retryAfter` or Infura's `rate.backoff_seconds`,
+/// all in seconds.
+fn retry_after(error: &TransportError) -> Option<Duration> {
+    let body: Value = match error {
+        RpcError::ErrorResp(payload) => serde_json::from_str(payload.data.as_ref()?.get()).ok()?,
+        RpcError::Transport(TransportErrorKind::HttpError(http)) => {
+            error_data(serde_json::from_str(&http.body).ok()?)
+        }
+        // Only the text of an error foundry's provider gave up on is left; the body is the JSON in
+        // it.
+        RpcError::Transport(TransportErrorKind::Custom(_)) => {
+            let text = exhausted_retries(error)?;
+            let body = serde_json::Deserializer::from_str(&text[text.find('{')?..])
+                .into_iter::<Value>()
+                .next()?
+                .ok()?;
+            error_data(body)
+        }
+        _ => return None,
+    };
+    let seconds = [
+        "/retry_after",
+        "/retryAfter",
+        "/backoff_seconds",
+        "/rate/backoff_seconds",
+    ]
+    .iter()
+    .find_map(|pointer| {
+        let value = body.pointer(pointer)?;
+        value
+            .as_f64()
+            .or_else(|| value.as_str()?.trim().parse().ok())
+    })?;
+    Duration::try_from_secs_f64(seconds).ok()
+}
+
+/// The `error.data` of a JSON-RPC error response body, or the whole body if it is something else.
+fn error_data(body: Value) -> Value {
+    match body.pointer("/error/data") {
+        Some(data) => data.clone(),
+        None => body,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    use alloy_json_rpc::{ErrorPayload, Id, Request, Response, ResponsePayload};
+    use alloy_transport::HttpError;
+    use serde_json::value::RawValue;
+
+    use super::*;
+    use crate::common::error::ErrorKind;
+
+    fn http_error(status: u16, body: &str) -> TransportError {
+        RpcError::Transport(TransportErrorKind::HttpError(HttpError {
+            status,
+            body: body.to_string(),
+        }))
+    }
+
+    fn request(method: &'static str) -> RequestPacket {
+        RequestPacket::Single(Request::new(method, Id::Number(1), ()).serialize().unwrap())
+    }
+
+    /// A transport that fails with `error` a number of times, then answers `0x1`.
+    #[derive(Clone)]
+    struct Flaky {
+        failures: Arc<AtomicU32>,
+        calls: Arc<AtomicU32>,
+        error: fn() -> TransportError,
+        /// The wait each failure asks for, as an HTTP endpoint's `Retry-After` header would.
+        retry_after: Option<Duration>,
+    }
+
+    impl Flaky {
+        fn new(failures: u32, error: fn() -> TransportError) -> Self {
+            Self {
+                failures: Arc::new(AtomicU32::new(failures)),
+                calls: Arc::new(AtomicU32::new(0)),
+                error,
+                retry_after: None,
+            }
+        }
+
+        fn with_retry_after(mut self, delay: Duration) -> Self {
+            self.retry_after = Some(delay);
+            self
+        }
+    }
+
+    impl Service<RequestPacket> for Flaky {
+        type Response = ResponsePacket;
+        type Error = TransportError;
+        type Future = TransportFut<'static>;
+
+        fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, _request: RequestPacket) -> Self::Future {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            let failed = self
+                .failures
+                .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+                .is_ok();
+            let retry_after = self.retry_after.filter(|_| failed);
+            let result = if failed {
+                Err((self.error)())
+            } else {
+                Ok(ResponsePacket::Single(Response {
+                    id: Id::Number(1),
+                    payload: ResponsePayload::Success(
+                        RawValue::from_string("\"0x1\"".to_string()).unwrap(),
+                    ),
+                }))
+            };
+            Box::pin(async move {
+                if let Some(delay) = retry_after {
+                    record_retry_after(delay);
+                }
+                result
+            })
+        }
+    }
+
+    fn policy(max_retries: u32) -> RetryPolicy {
+        RetryPolicy {
+            max_retries,
+            initial_backoff_ms: 1,
+            max_backoff_ms: 10,
+            compute_units_per_second: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_retries_retryable_errors() {
+        let flaky = Flaky::new(2, || http_error(429, ""));
+        let mut service = RetryLayer::new(policy(3)).layer(flaky.clone());
+        assert!(service.call(request("eth_blockNumber")).await.is_ok());
+        assert_eq!(flaky.calls.load(Ordering::SeqCst), 3);
+
+        let flaky = Flaky::new(5, || http_error(503, ""));
+        let mut service = RetryLayer::new(policy(2)).layer(flaky.clone());
+        let error = service.call(request("eth_blockNumber")).await.unwrap_err();
+        assert_eq!(classify(&error), ErrorKind::RpcUnreachable);
+        assert_eq!(flaky.calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_does_not_retry_other_errors() {
+        let flaky = Flaky::new(1, || {
+            RpcError::ErrorResp(ErrorPayload {
+                code: 3,
+                message: "execution reverted".into(),
+                data: None,
+            })
+        });
+        let mut service = RetryLayer::new(policy(3)).layer(flaky.clone());
+        assert!(service.call(request("eth_call")).await.is_err());
+        assert_eq!(flaky.calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_gives_up_on_long_retry_after() {
+        let flaky = Flaky::new(1, || http_error(429, r#"{"retry_after": 60}"#));
+        let mut service = RetryLayer::new(policy(3)).layer(flaky.clone());
+        assert!(service.call(request("eth_blockNumber")).await.is_err());
+        assert_eq!(flaky.calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_honors_retry_after_header() {
+        let flaky = Flaky::new(1, || http_error(429, "")).with_retry_after(Duration::from_secs(60));
+        let mut service = RetryLayer::new(policy(3)).layer(flaky.clone());
+        assert!(service.call(request("eth_blockNumber")).await.is_err());
+        assert_eq!(flaky.calls.load(Ordering::SeqCst), 1);
+
+        // The header takes precedence over a hint in the body.
+        let flaky = Flaky::new(1, || http_error(503, r#"{"retry_after": 60}"#))
+            .with_retry_after(Duration::from_millis(5));
+        let mut service = RetryLayer::new(policy(3)).layer(flaky.clone());
+        assert!(service.call(request("eth_blockNumber")).await.is_ok());
+        assert_eq!(flaky.calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_backoff_grows_with_jitter() {
+        let policy = RetryPolicy {
+            initial_backoff_ms: 100,
+            max_backoff_ms: 1_000,
+            ..Default::default()
+        };
+        for attempt in 0..8 {
+            let ceiling = (100u64 << attempt).min(1_000);
+            let backoff = policy.backoff(attempt).as_millis() as u64;
+            assert!(
+                (ceiling / 2..=ceiling).contains(&backoff),
+                "{attempt}: {backoff}"
+            );
+        }
+        assert!(policy.backoff(u32::MAX) <= Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_retry_after_hints() {
+        let infura = r#"{"jsonrpc":"2.0","id":1,"error":{"code":-32005,"message":"project ID request rate exceeded","data":{"rate":{"backoff_seconds":30}}}}"#;
+        assert_eq!(
+            retry_after(&http_error(429, infura)),
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(
+            retry_after(&http_error(429, r#"{"retry_after":"1.5"}"#)),
+            Some(Duration::from_millis(1_500))
+        );
+        assert_eq!(retry_after(&http_error(429, "Too Many Requests")), None);
+
+        let resp = RpcError::ErrorResp(ErrorPayload {
+            code: 429,
+            message: "rate limited".into(),
+            data: Some(RawValue::from_string(r#"{"retryAfter":2}"#.to_string()).unwrap()),
+        });
+        assert_eq!(retry_after(&resp), Some(Duration::from_secs(2)));
+    }
+
+    #[test]
+    fn test_compute_budget() {
+        let budget = ComputeBudget::new(100);
+        assert_eq!(budget.reserve(60), Duration::ZERO);
+        assert_eq!(budget.reserve(40), Duration::ZERO);
+        let wait = budget.reserve(50);
+        assert!(wait > Duration::from_millis(400) && wait <= Duration::from_millis(500));
+        // A request costing more than a second's budget still gets sent.
+        assert_eq!(ComputeBudget::new(10).reserve(1_000), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_budgets_are_kept_by_url() {
+        let budgets = ComputeBudgets::default();
+        let budget = budgets.get("https://a.example", 100);
+        assert!(Arc::ptr_eq(&budget, &budgets.get("https://a.example", 100)));
+        assert!(!Arc::ptr_eq(
+            &budget,
+            &budgets.get("https://b.example", 100)
+        ));
+        assert!(!Arc::ptr_eq(
+            &budget,
+            &budgets.get("https://a.example", 330)
+        ));
+    }
+
+    #[test]
+    fn test_retry_after_of_exhausted_retries() {
+        let body = r#"{"jsonrpc":"2.0","id":1,"error":{"code":429,"message":"slow down","data":{"retry_after":3}}}"#;
+        let error = TransportErrorKind::custom_str(&format!(
+            "Max retries exceeded HTTP error 429 with body: {body}"
+        ));
+        assert_eq!(retry_after(&error), Some(Duration::from_secs(3)));
+    }
+
+    #[test]
+    fn test_request_cost() {
+        assert_eq!(request_cost(&request("eth_getLogs")), 75);
+        assert_eq!(request_cost(&request("debug_storageRangeAt")), 300);
+        assert_eq!(request_cost(&request("eth_getBlockByNumber")), 20);
+    }
+}
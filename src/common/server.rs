@@ -1,22 +1,66 @@
-use rmcp::{ServerHandler, handler::server::router::tool::ToolRouter, model::*, tool_handler};
+use std::{sync::Arc, time::Duration};
+
+use rmcp::{
+    ErrorData, RoleServer, ServerHandler,
+    handler::server::{router::tool::ToolRouter, tool::ToolCallContext},
+    model::*,
+    service::RequestContext,
+};
+
+use crate::common::{
+    cache::ResponseCache,
+    common::EndpointArgs,
+    config::{Redactor, ServerConfig},
+    meta,
+    provider::{ProviderPool, RetryProvider},
+};
 
 #[derive(Clone)]
 pub struct Server {
     tool_router: ToolRouter<Self>,
+    pub(crate) config: Arc<ServerConfig>,
+    pub(crate) providers: Arc<ProviderPool>,
+    pub(crate) cache: Arc<ResponseCache>,
+    redactor: Arc<Redactor>,
 }
 
 impl Server {
     pub fn new() -> Self {
+        Self::with_config(ServerConfig::default())
+    }
+
+    pub fn with_config(config: ServerConfig) -> Self {
+        let cache = Arc::new(ResponseCache::new(
+            config.cache.max_entries,
+            config.cache.path.clone(),
+            config.cache.max_files,
+        ));
+        let providers = ProviderPool::new(
+            config.provider_pool.max_size,
+            Duration::from_secs(config.provider_pool.idle_timeout_secs),
+            cache.clone(),
+        );
         Self {
             tool_router: Server::utility_router()
                 + Server::block_router()
                 + Server::chain_router()
                 + Server::account_router(),
+            redactor: Arc::new(Redactor::from_config(&config)),
+            config: Arc::new(config),
+            providers: Arc::new(providers),
+            cache,
         }
     }
+
+    /// Get a pooled provider for the endpoint a tool call names.
+    pub(crate) fn provider(&self, endpoint: &EndpointArgs) -> Result<RetryProvider, ErrorData> {
+        let key = self.config.resolve_endpoint(endpoint)?;
+        self.providers.get(&key)
+    }
 }
 
-#[tool_handler]
+// `#[tool_handler]` is expanded by hand so errors can be scrubbed of endpoint secrets before they
+// reach the client.
 impl ServerHandler for Server {
     fn get_info(&self) -> ServerInfo {
         ServerInfo {
@@ -25,6 +69,36 @@ impl ServerHandler for Server {
             ..Default::default()
         }
     }
+
+    async fn call_tool(
+        &self,
+        request: CallToolRequestParam,
+        context: RequestContext<RoleServer>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let redactor = self
+            .redactor
+            .with_call_arguments(request.arguments.as_ref());
+        let tcc = ToolCallContext::new(self, request, context);
+        let (result, meta) = meta::track(self.tool_router.call(tcc)).await;
+        result
+            .map(|mut result| {
+                meta.apply(&mut result);
+                result
+            })
+            .map_err(|e| {
+                let e = redactor.redact_error(e);
+                tracing::debug!("tool call failed: {}", e.message);
+                e
+            })
+    }
+
+    async fn list_tools(
+        &self,
+        _request: Option<PaginatedRequestParam>,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ListToolsResult, ErrorData> {
+        Ok(ListToolsResult::with_all_items(self.tool_router.list_all()))
+    }
 }
 
 #[cfg(test)]
@@ -177,4 +251,15 @@ mod tests {
             "Server capabilities should be consistent"
         );
     }
+
+    #[test]
+    fn test_every_tool_has_output_schema() {
+        let server = Server::new();
+        for tool in server.tool_router.list_all() {
+            let schema = tool
+                .output_schema
+                .unwrap_or_else(|| panic!("{} has no output schema", tool.name));
+            assert_eq!(schema["type"], "object", "{} output schema", tool.name);
+        }
+    }
 }
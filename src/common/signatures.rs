@@ -0,0 +1,415 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::LazyLock,
+    time::Duration,
+};
+
+use alloy_json_abi::{Error, Event, Function};
+use alloy_primitives::hex;
+use rmcp::ErrorData;
+use serde::Serialize;
+use serde_json::{Value, json};
+
+use crate::common::{
+    config::SignaturesConfig,
+    error::{ErrorKind, invalid_input, tool_error},
+};
+
+/// How long a remote signature lookup may take.
+const REMOTE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// What a selector identifies. Function and error selectors share the 4-byte namespace, events
+/// are identified by their 32-byte topic0.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SelectorKind {
+    Function,
+    Event,
+}
+
+impl SelectorKind {
+    fn len(self) -> usize {
+        match self {
+            Self::Function => 4,
+            Self::Event => 32,
+        }
+    }
+
+    /// The sections of foundry's signature cache holding this kind of selector.
+    fn cache_sections(self) -> &'static [&'static str] {
+        match self {
+            Self::Function => &["functions", "errors"],
+            Self::Event => &["events"],
+        }
+    }
+
+    /// The query parameter and result key of the remote signature database.
+    fn remote_key(self) -> &'static str {
+        match self {
+            Self::Function => "function",
+            Self::Event => "event",
+        }
+    }
+}
+
+/// Where the signatures of a selector were found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, schemars::JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Source {
+    Bundled,
+    FoundryCache,
+    Remote,
+}
+
+/// The signatures matching a selector. Selectors can collide, so there may be several.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Lookup {
+    pub signatures: Vec<String>,
+    pub source: Source,
+}
+
+/// The bundled signatures, keyed by lowercase 0x-prefixed selector.
+struct Bundled {
+    selectors: HashMap<String, Vec<String>>,
+    events: HashMap<String, Vec<String>>,
+}
+
+static BUNDLED: LazyLock<Bundled> = LazyLock::new(|| {
+    let mut bundled = Bundled {
+        selectors: HashMap::new(),
+        events: HashMap::new(),
+    };
+    for line in include_str!("signatures.txt").lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (map, selector, signature) = if let Some(sig) = line.strip_prefix("function ") {
+            let func = Function::parse(sig).expect("valid bundled function signature");
+            (
+                &mut bundled.selectors,
+                hex::encode_prefixed(func.selector()),
+                func.signature(),
+            )
+        } else if let Some(sig) = line.strip_prefix("error ") {
+            let error = Error::parse(sig).expect("valid bundled error signature");
+            (
+                &mut bundled.selectors,
+                hex::encode_prefixed(error.selector()),
+                error.signature(),
+            )
+        } else if let Some(sig) = line.strip_prefix("event ") {
+            let event = Event::parse(sig).expect("valid bundled event signature");
+            (
+                &mut bundled.events,
+                event.selector().to_string(),
+                event.signature(),
+            )
+        } else {
+            panic!("bundled signature without a function, event or error keyword: {line}");
+        };
+        let signatures = map.entry(selector).or_default();
+        if !signatures.contains(&signature) {
+            signatures.push(signature);
+        }
+    }
+    bundled
+});
+
+/// Parse a selector of `kind`. Function selectors may be given as whole calldata, of which the
+/// first 4 bytes are used.
+pub fn parse_selector(input: &str, kind: SelectorKind) -> Result<String, ErrorData> {
+    let bytes =
+        hex::decode(input.trim()).map_err(|e| selector_error(input, kind, e.to_string()))?;
+    let valid = match kind {
+        SelectorKind::Function => bytes.len() >= 4,
+        SelectorKind::Event => bytes.len() == 32,
+    };
+    if !valid {
+        return Err(selector_error(input, kind, "invalid_length".to_string()));
+    }
+    Ok(hex::encode_prefixed(&bytes[..kind.len()]))
+}
+
+fn selector_error(input: &str, kind: SelectorKind, reason: String) -> ErrorData {
+    let expected = match kind {
+        SelectorKind::Function => "a 4-byte selector, or calldata starting with one",
+        SelectorKind::Event => "a 32-byte event topic",
+    };
+    invalid_input(
+        format!("Invalid selector: {input}"),
+        Some(json!({ "input": input, "expected": expected, "reason": reason })),
+    )
+}
+
+/// The bundled signatures of `selector`, as returned by [`parse_selector`]. Never leaves the
+/// process, so it suits lookups in bulk.
+pub fn bundled(selector: &str, kind: SelectorKind) -> Option<&'static [String]> {
+    let bundled = match kind {
+        SelectorKind::Function => &BUNDLED.selectors,
+        SelectorKind::Event => &BUNDLED.events,
+    };
+    bundled.get(selector).map(Vec::as_slice)
+}
+
+/// Look up the signatures of `selector`, as returned by [`parse_selector`]: in the bundled list
+/// first, then foundry's signature cache, then the remote database if the configuration allows.
+pub async fn lookup(
+    config: &SignaturesConfig,
+    selector: &str,
+    kind: SelectorKind,
+) -> Result<Option<Lookup>, ErrorData> {
+    if let Some(signatures) = bundled(selector, kind) {
+        return Ok(Some(Lookup {
+            signatures: signatures.to_vec(),
+            source: Source::Bundled,
+        }));
+    }
+
+    if config.foundry_cache
+        && let Some(path) = foundry_cache_path(config)
+    {
+        let signatures = read_foundry_cache(&path, selector, kind).await;
+        if !signatures.is_empty() {
+            return Ok(Some(Lookup {
+                signatures,
+                source: Source::FoundryCache,
+            }));
+        }
+    }
+
+    if config.remote_lookup {
+        let signatures = remote_lookup(&config.remote_url, selector, kind).await?;
+        if !signatures.is_empty() {
+            return Ok(Some(Lookup {
+                signatures,
+                source: Source::Remote,
+            }));
+        }
+    }
+    Ok(None)
+}
+
+fn foundry_cache_path(config: &SignaturesConfig) -> Option<PathBuf> {
+    config
+        .foundry_cache_path
+        .clone()
+        .or_else(|| foundry_config::Config::foundry_cache_dir().map(|dir| dir.join("signatures")))
+}
+
+/// Read the signatures of `selector` from foundry's cache. A missing or unreadable cache is
+/// treated as empty, since it is only a convenience.
+async fn read_foundry_cache(path: &Path, selector: &str, kind: SelectorKind) -> Vec<String> {
+    let content = match tokio::fs::read_to_string(path).await {
+        Ok(content) => content,
+        Err(e) => {
+            tracing::debug!("Failed to read signature cache {}: {}", path.display(), e);
+            return Vec::new();
+        }
+    };
+    match serde_json::from_str::<Value>(&content) {
+        Ok(cache) => cached_signatures(&cache, selector, kind),
+        Err(e) => {
+            tracing::warn!("Failed to parse signature cache {}: {}", path.display(), e);
+            Vec::new()
+        }
+    }
+}
+
+/// Find `selector` in the sections of a foundry signature cache, whose keys may or may not carry
+/// the 0x prefix.
+fn cached_signatures(cache: &Value, selector: &str, kind: SelectorKind) -> Vec<String> {
+    let digits = selector.trim_start_matches("0x");
+    kind.cache_sections()
+        .iter()
+        .filter_map(|section| cache.get(section)?.as_object())
+        .flat_map(|entries| entries.iter())
+        .filter(|(key, _)| key.trim_start_matches("0x").eq_ignore_ascii_case(digits))
+        .flat_map(|(_, value)| match value {
+            Value::String(signature) => vec![signature.clone()],
+            Value::Array(items) => items
+                .iter()
+                .filter_map(|item| item.as_str().map(str::to_string))
+                .collect(),
+            _ => Vec::new(),
+        })
+        .collect()
+}
+
+async fn remote_lookup(
+    url: &str,
+    selector: &str,
+    kind: SelectorKind,
+) -> Result<Vec<String>, ErrorData> {
+    let remote_error = |e: reqwest::Error| {
+        let kind = if e.status() == Some(reqwest::StatusCode::TOO_MANY_REQUESTS) {
+            ErrorKind::RateLimited
+        } else {
+            ErrorKind::RpcUnreachable
+        };
+        tool_error(
+            kind,
+            format!("Remote signature lookup failed for {selector}"),
+            Some(json!({ "selector": selector, "reason": e.to_string() })),
+        )
+    };
+    let response = reqwest::Client::new()
+        .get(url)
+        .query(&[(kind.remote_key(), selector), ("filter", "true")])
+        .timeout(REMOTE_TIMEOUT)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(remote_error)?;
+    let body: Value = response.json().await.map_err(remote_error)?;
+    Ok(remote_signatures(&body, selector, kind))
+}
+
+/// Extract the signatures from a signature database response, shaped as
+/// `{"result": {"function": {"0x...": [{"name": "..."}]}}}`.
+fn remote_signatures(body: &Value, selector: &str, kind: SelectorKind) -> Vec<String> {
+    body["result"][kind.remote_key()][selector]
+        .as_array()
+        .map(|items| {
+            items
+                .iter()
+                .filter_map(|item| item["name"].as_str().map(str::to_string))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn offline() -> SignaturesConfig {
+        SignaturesConfig {
+            foundry_cache: false,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_bundled_signatures_parse() {
+        assert!(BUNDLED.selectors.len() > 100);
+        assert!(BUNDLED.events.len() > 20);
+    }
+
+    #[test]
+    fn test_parse_selector() {
+        assert_eq!(
+            parse_selector("0xa9059cbb", SelectorKind::Function).unwrap(),
+            "0xa9059cbb"
+        );
+        assert_eq!(
+            parse_selector("A9059CBB000000", SelectorKind::Function).unwrap(),
+            "0xa9059cbb"
+        );
+
+        let error = parse_selector("0xa905", SelectorKind::Function).unwrap_err();
+        assert_eq!(error.data.unwrap()["reason"], "invalid_length");
+        assert!(parse_selector("0xa9059cbb", SelectorKind::Event).is_err());
+        assert!(parse_selector("transfer", SelectorKind::Function).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_lookup_bundled() {
+        let config = offline();
+        let found = lookup(&config, "0xa9059cbb", SelectorKind::Function)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(found.source, Source::Bundled);
+        assert_eq!(found.signatures, vec!["transfer(address,uint256)"]);
+
+        let found = lookup(
+            &config,
+            "0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef",
+            SelectorKind::Event,
+        )
+        .await
+        .unwrap()
+        .unwrap();
+        assert_eq!(found.signatures, vec!["Transfer(address,address,uint256)"]);
+
+        // Error selectors share the function namespace.
+        let found = lookup(&config, "0x4e487b71", SelectorKind::Function)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(found.signatures, vec!["Panic(uint256)"]);
+
+        let missing = lookup(&config, "0xdeadbeef", SelectorKind::Function)
+            .await
+            .unwrap();
+        assert_eq!(missing, None);
+    }
+
+    #[tokio::test]
+    async fn test_lookup_foundry_cache() {
+        let path = std::env::temp_dir().join(format!("cast-mcp-signatures-{}", std::process::id()));
+        std::fs::write(
+            &path,
+            json!({
+                "functions": { "0xdeadbeef": "deadBeef(uint256)" },
+                "events": { "1111111111111111111111111111111111111111111111111111111111111111": "Dead()" },
+            })
+            .to_string(),
+        )
+        .unwrap();
+        let config = SignaturesConfig {
+            foundry_cache_path: Some(path.clone()),
+            ..Default::default()
+        };
+
+        let found = lookup(&config, "0xdeadbeef", SelectorKind::Function)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(found.source, Source::FoundryCache);
+        assert_eq!(found.signatures, vec!["deadBeef(uint256)"]);
+
+        let topic = format!("0x{}", "11".repeat(32));
+        let found = lookup(&config, &topic, SelectorKind::Event).await.unwrap();
+        assert_eq!(found.unwrap().signatures, vec!["Dead()"]);
+
+        let missing = lookup(&offline(), "0xdeadbeef", SelectorKind::Function)
+            .await
+            .unwrap();
+        assert_eq!(missing, None);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_lookup_missing_foundry_cache() {
+        let config = SignaturesConfig {
+            foundry_cache_path: Some(PathBuf::from("/nonexistent/cast-mcp/signatures")),
+            ..Default::default()
+        };
+        let missing = lookup(&config, "0xdeadbeef", SelectorKind::Function)
+            .await
+            .unwrap();
+        assert_eq!(missing, None);
+    }
+
+    #[test]
+    fn test_remote_signatures() {
+        let body = json!({
+            "ok": true,
+            "result": {
+                "function": {
+                    "0xa9059cbb": [
+                        { "name": "transfer(address,uint256)", "filtered": false },
+                        { "name": "many_msg_babbage(bytes1)", "filtered": false }
+                    ]
+                },
+                "event": {}
+            }
+        });
+        assert_eq!(
+            remote_signatures(&body, "0xa9059cbb", SelectorKind::Function),
+            vec!["transfer(address,uint256)", "many_msg_babbage(bytes1)"]
+        );
+        assert!(remote_signatures(&body, "0xdeadbeef", SelectorKind::Function).is_empty());
+    }
+}
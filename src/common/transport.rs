@@ -0,0 +1,146 @@
+//! The transport for HTTP endpoints. Alloy's HTTP transport, which foundry's providers use, drops
+//! the headers of a failed response, so the `Retry-After` header of a 429 or 503 never reaches the
+//! retry layer. This one sends requests the same way and records the header before failing.
+
+use std::{
+    str::FromStr,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use alloy_json_rpc::{RequestPacket, ResponsePacket};
+use alloy_transport::{TransportError, TransportErrorKind, TransportFut, TransportResult};
+use foundry_config::Config;
+use reqwest::{
+    StatusCode, Url,
+    header::{self, HeaderMap, HeaderName, HeaderValue},
+};
+use tower::Service;
+
+use crate::common::retry::record_retry_after;
+
+/// How long a request may take when the config sets no timeout, as in foundry.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(45);
+
+/// A transport posting JSON-RPC requests to an HTTP endpoint.
+#[derive(Debug, Clone)]
+pub struct HttpTransport {
+    client: reqwest::Client,
+    url: Url,
+}
+
+impl HttpTransport {
+    /// The transport for `url` with the headers, timeout and TLS and proxy options of `config`,
+    /// or `None` if `url` is not an HTTP URL or the endpoint authenticates with a JWT, which is
+    /// left to foundry's transport.
+    pub fn from_config(config: &Config, url: &str) -> Result<Option<Self>, String> {
+        let Ok(url) = Url::parse(url) else {
+            return Ok(None);
+        };
+        if !matches!(url.scheme(), "http" | "https") || config.eth_rpc_jwt.is_some() {
+            return Ok(None);
+        }
+
+        let mut headers = HeaderMap::new();
+        for header in config.eth_rpc_headers.iter().flatten() {
+            let (name, value) = header
+                .split_once(':')
+                .ok_or_else(|| format!("Invalid header {header:?}, expected `Name: value`"))?;
+            let name = HeaderName::from_str(name.trim()).map_err(|e| e.to_string())?;
+            let value = HeaderValue::from_str(value.trim()).map_err(|e| e.to_string())?;
+            headers.insert(name, value);
+        }
+        let mut client = reqwest::Client::builder()
+            .default_headers(headers)
+            .timeout(
+                config
+                    .eth_rpc_timeout
+                    .map_or(DEFAULT_TIMEOUT, Duration::from_secs),
+            )
+            .danger_accept_invalid_certs(config.eth_rpc_accept_invalid_certs);
+        if config.eth_rpc_no_proxy {
+            client = client.no_proxy();
+        }
+        let client = client.build().map_err(|e| e.to_string())?;
+        Ok(Some(Self { client, url }))
+    }
+
+    async fn send(self, request: RequestPacket) -> TransportResult<ResponsePacket> {
+        let response = self
+            .client
+            .post(self.url)
+            .json(&request)
+            .send()
+            .await
+            .map_err(TransportErrorKind::custom)?;
+        let status = response.status();
+        if matches!(
+            status,
+            StatusCode::TOO_MANY_REQUESTS | StatusCode::SERVICE_UNAVAILABLE
+        ) && let Some(delay) = response
+            .headers()
+            .get(header::RETRY_AFTER)
+            .and_then(|value| parse_retry_after(value.to_str().ok()?))
+        {
+            record_retry_after(delay);
+        }
+        let body = response.bytes().await.map_err(TransportErrorKind::custom)?;
+        if !status.is_success() {
+            return Err(TransportErrorKind::http_error(
+                status.as_u16(),
+                String::from_utf8_lossy(&body).into_owned(),
+            ));
+        }
+        serde_json::from_slice(&body)
+            .map_err(|e| TransportError::deser_err(e, String::from_utf8_lossy(&body)))
+    }
+}
+
+impl Service<RequestPacket> for HttpTransport {
+    type Response = ResponsePacket;
+    type Error = TransportError;
+    type Future = TransportFut<'static>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: RequestPacket) -> Self::Future {
+        Box::pin(self.clone().send(request))
+    }
+}
+
+/// The wait a `Retry-After` header asks for: a number of seconds, or an HTTP date. A date in the
+/// past asks for no wait.
+fn parse_retry_after(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    Some(
+        (date.to_utc() - chrono::Utc::now())
+            .to_std()
+            .unwrap_or_default(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_retry_after() {
+        assert_eq!(parse_retry_after("120"), Some(Duration::from_secs(120)));
+        assert_eq!(parse_retry_after(" 0 "), Some(Duration::ZERO));
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"),
+            Some(Duration::ZERO)
+        );
+        let date = (chrono::Utc::now() + chrono::Duration::seconds(30)).to_rfc2822();
+        let delay = parse_retry_after(&date).unwrap();
+        assert!(delay > Duration::from_secs(25) && delay <= Duration::from_secs(30));
+        assert_eq!(parse_retry_after("soon"), None);
+        assert_eq!(parse_retry_after("-1"), None);
+    }
+}
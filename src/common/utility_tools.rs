@@ -2,6 +2,7 @@
 #![allow(unused)]
 use std::{any::Any, sync::Arc};
 
+use alloy_dyn_abi::TypedData;
 use alloy_primitives::{Address, B256, eip191_hash_message, hex, keccak256};
 use cast::SimpleCast;
 use rand::random;
@@ -22,11 +23,17 @@ use rmcp::{
 };
 use serde::{Deserialize, Serialize};
 use serde_default::DefaultFromSerde;
-use serde_json::json;
+use serde_json::{Value, json};
 use tokio::sync::Mutex;
 use tracing::info;
 
-use crate::common::{common::*, server::Server};
+use crate::common::{
+    abi,
+    common::*,
+    error::*,
+    server::Server,
+    signatures::{self, SelectorKind},
+};
 
 #[derive(Debug, serde::Deserialize, DefaultFromSerde, schemars::JsonSchema)]
 pub struct MaxIntArgs {
@@ -42,57 +49,1006 @@ pub struct MaxUIntArgs {
     pub r#type: String,
 }
 
-#[tool_router(router = utility_router, vis = "pub")]
-impl Server {
-    #[tool(description = "Get maximum value for integer type.")]
-    async fn max_int(
-        &self,
-        Parameters(MaxIntArgs { r#type: t }): Parameters<MaxIntArgs>,
-    ) -> Result<CallToolResult, ErrorData> {
-        let res = SimpleCast::max_int(&t).map_err(|e| {
-            tracing::error!("Failed to get max int: {}", e);
-            ErrorData::invalid_params("Failed to get max int", None)
-        })?;
-        Ok(CallToolResult::success(vec![Content::text(res)]))
+#[derive(Debug, serde::Deserialize, DefaultFromSerde, schemars::JsonSchema)]
+pub struct AbiEncodeArgs {
+    /// The function signature whose input types the arguments are encoded as, e.g. transfer(address,uint256).
+    pub sig: String,
+
+    /// The arguments, one string per input.
+    #[serde(default)]
+    pub args: Vec<String>,
+}
+
+#[derive(Debug, serde::Deserialize, DefaultFromSerde, schemars::JsonSchema)]
+pub struct DecodeCalldataArgs {
+    /// The function signature, e.g. transfer(address,uint256).
+    pub sig: String,
+
+    /// The calldata to decode, as hex including the 4-byte selector.
+    pub data: String,
+}
+
+#[derive(Debug, serde::Deserialize, DefaultFromSerde, schemars::JsonSchema)]
+pub struct DecodeAbiArgs {
+    /// The function signature, e.g. balanceOf(address)(uint256). The data is decoded as its outputs unless `input` is set.
+    pub sig: String,
+
+    /// The ABI encoded data to decode, as hex without a selector.
+    pub data: String,
+
+    /// Decode the data as the function's inputs instead of its outputs.
+    #[serde(default)]
+    pub input: bool,
+}
+
+#[derive(Debug, serde::Deserialize, DefaultFromSerde, schemars::JsonSchema)]
+pub struct DecodeErrorArgs {
+    /// The custom error signature, e.g. InsufficientBalance(uint256,uint256). Without it, Error(string) and Panic(uint256) reverts are decoded.
+    #[serde(default)]
+    pub sig: Option<String>,
+
+    /// The revert data, as hex including the 4-byte selector.
+    pub data: String,
+}
+
+#[derive(Debug, serde::Deserialize, DefaultFromSerde, schemars::JsonSchema)]
+pub struct ValueArgs {
+    /// The value to convert.
+    pub value: String,
+}
+
+#[derive(Debug, serde::Deserialize, DefaultFromSerde, schemars::JsonSchema)]
+pub struct UnitArgs {
+    /// The amount to convert, as an exact decimal string such as 1.5. Numbers are never rounded through floating point.
+    pub value: String,
+
+    /// The unit: a named denomination such as wei, gwei or ether, or a number of decimals from 0 to 77. Defaults to ether.
+    #[serde(default = "default_unit")]
+    pub unit: UnitSpec,
+}
+
+#[derive(Debug, serde::Deserialize, DefaultFromSerde, schemars::JsonSchema)]
+pub struct NumberArgs {
+    /// The integer to convert. Prefixes such as 0x, 0o and 0b set its base unless `base_in` is given.
+    pub value: String,
+
+    /// The base of `value`: bin, oct, dec or hex. Detected from its prefix when omitted.
+    #[serde(default)]
+    pub base_in: Option<Base>,
+}
+
+#[derive(Debug, serde::Deserialize, DefaultFromSerde, schemars::JsonSchema)]
+pub struct ToBaseArgs {
+    /// The integer to convert. Prefixes such as 0x, 0o and 0b set its base unless `base_in` is given.
+    pub value: String,
+
+    /// The base of `value`: bin, oct, dec or hex. Detected from its prefix when omitted.
+    #[serde(default)]
+    pub base_in: Option<Base>,
+
+    /// The base to convert to: bin, oct, dec or hex. Defaults to hex.
+    #[serde(default = "default_base")]
+    pub base_out: Base,
+}
+
+#[derive(Debug, serde::Deserialize, DefaultFromSerde, schemars::JsonSchema)]
+pub struct ShiftArgs {
+    /// The integer to shift. Prefixes such as 0x, 0o and 0b set its base unless `base_in` is given.
+    pub value: String,
+
+    /// The number of bits to shift by, from 0 to 256.
+    pub bits: u16,
+
+    /// The base of `value`: bin, oct, dec or hex. Detected from its prefix when omitted.
+    #[serde(default)]
+    pub base_in: Option<Base>,
+
+    /// The base of the result: bin, oct, dec or hex. Defaults to hex.
+    #[serde(default = "default_base")]
+    pub base_out: Base,
+}
+
+#[derive(Debug, serde::Deserialize, DefaultFromSerde, schemars::JsonSchema)]
+pub struct HashArgs {
+    /// The data to hash. 0x-prefixed hex is hashed as bytes, anything else as UTF-8 text.
+    pub data: String,
+}
+
+#[derive(Debug, serde::Deserialize, DefaultFromSerde, schemars::JsonSchema)]
+pub struct SignatureArgs {
+    /// The signature, e.g. transfer(address,uint256) or event Transfer(address indexed from, address indexed to, uint256 value).
+    pub sig: String,
+}
+
+#[derive(Debug, serde::Deserialize, DefaultFromSerde, schemars::JsonSchema)]
+pub struct MessageArgs {
+    /// The message. 0x-prefixed hex is hashed as bytes, anything else as UTF-8 text.
+    pub message: String,
+}
+
+#[derive(Debug, serde::Deserialize, DefaultFromSerde, schemars::JsonSchema)]
+pub struct TypedDataArgs {
+    /// The EIP-712 typed data, as passed to eth_signTypedData_v4: an object with types, primaryType, domain and message, or that object as a JSON string.
+    pub typed_data: serde_json::Value,
+}
+
+#[derive(Debug, serde::Deserialize, DefaultFromSerde, schemars::JsonSchema)]
+pub struct NameArgs {
+    /// The ENS name, e.g. vitalik.eth.
+    pub name: String,
+}
+
+#[derive(Debug, serde::Deserialize, DefaultFromSerde, schemars::JsonSchema)]
+pub struct ComputeAddressArgs {
+    /// The deploying address, as hex.
+    pub address: String,
+
+    /// The deployer's nonce at the time of deployment.
+    pub nonce: u64,
+}
+
+#[derive(Debug, serde::Deserialize, DefaultFromSerde, schemars::JsonSchema)]
+pub struct Create2Args {
+    /// The factory address, as hex. Defaults to the deterministic deployment proxy 0x4e59b44847b379578588920cA78FbF26c0B4956C.
+    #[serde(default = "default_create2_deployer")]
+    pub deployer: String,
+
+    /// The salt, as hex of at most 32 bytes. Shorter salts are left-padded, as a uint256 would be.
+    pub salt: String,
+
+    /// The contract's init code, as hex. Either this or `init_code_hash` is required.
+    #[serde(default)]
+    pub init_code: Option<String>,
+
+    /// The keccak256 hash of the contract's init code.
+    #[serde(default)]
+    pub init_code_hash: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize, DefaultFromSerde, schemars::JsonSchema)]
+pub struct SelectorArgs {
+    /// The 4-byte function or error selector, as hex. Calldata is accepted too, of which the first 4 bytes are used.
+    pub selector: String,
+}
+
+#[derive(Debug, serde::Deserialize, DefaultFromSerde, schemars::JsonSchema)]
+pub struct TopicArgs {
+    /// The 32-byte event topic0, as hex.
+    pub topic: String,
+}
+
+#[derive(Debug, serde::Deserialize, DefaultFromSerde, schemars::JsonSchema)]
+pub struct DecodeSelectorArgs {
+    /// The calldata to identify and decode, as hex including the 4-byte selector.
+    pub data: String,
+}
+
+#[derive(Debug, serde::Serialize, schemars::JsonSchema)]
+pub struct ValueOutput {
+    /// The result.
+    pub value: String,
+}
+
+#[derive(Debug, serde::Serialize, schemars::JsonSchema)]
+pub struct AddressOutput {
+    /// The checksummed address.
+    pub address: String,
+}
+
+#[derive(Debug, serde::Serialize, schemars::JsonSchema)]
+pub struct HashOutput {
+    /// The 32-byte hash, as hex.
+    pub hash: String,
+}
+
+#[derive(Debug, serde::Serialize, schemars::JsonSchema)]
+pub struct EncodeOutput {
+    /// The encoded data, as hex.
+    pub hex: String,
+
+    /// The 4-byte function selector, for calldata.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub selector: Option<String>,
+
+    /// The canonical function signature.
+    pub signature: String,
+
+    /// The encoded arguments, with their names and types.
+    pub args: Vec<abi::DecodedParam>,
+}
+
+#[derive(Debug, serde::Serialize, schemars::JsonSchema)]
+pub struct DecodeOutput {
+    /// The 4-byte function selector, for calldata.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub selector: Option<String>,
+
+    /// The canonical function signature.
+    pub signature: String,
+
+    /// The decoded values, with their names and types.
+    pub decoded: Vec<abi::DecodedParam>,
+}
+
+#[derive(Debug, serde::Serialize, schemars::JsonSchema)]
+pub struct SelectorOutput {
+    /// The 4-byte selector.
+    pub selector: String,
+
+    /// The canonical function signature.
+    pub signature: String,
+}
+
+#[derive(Debug, serde::Serialize, schemars::JsonSchema)]
+pub struct TopicOutput {
+    /// The event topic0.
+    pub topic: String,
+
+    /// The canonical event signature.
+    pub signature: String,
+}
+
+#[derive(Debug, serde::Serialize, schemars::JsonSchema)]
+pub struct TypedDataHashOutput {
+    /// The EIP-712 signing hash.
+    pub hash: String,
+
+    /// The hash of the EIP712Domain.
+    pub domain_separator: String,
+
+    /// The hash of the message. Null when the primary type is EIP712Domain.
+    pub struct_hash: Option<String>,
+
+    /// The type of the message.
+    pub primary_type: String,
+}
+
+#[derive(Debug, serde::Serialize, schemars::JsonSchema)]
+pub struct SignatureLookupOutput {
+    /// The selector or topic looked up.
+    pub selector: String,
+
+    /// The matching signatures. Selectors can collide, so there may be several.
+    pub signatures: Vec<String>,
+
+    /// Where the signatures were found.
+    pub source: signatures::Source,
+}
+
+#[derive(Debug, serde::Serialize, schemars::JsonSchema)]
+pub struct DecodeSelectorOutput {
+    /// The selector of the calldata.
+    pub selector: String,
+
+    /// Where the signatures were found.
+    pub source: signatures::Source,
+
+    /// Each signature the calldata decodes as.
+    pub candidates: Vec<DecodedCandidate>,
+}
+
+#[derive(Debug, serde::Serialize, schemars::JsonSchema)]
+pub struct DecodedCandidate {
+    /// The function signature.
+    pub signature: String,
+
+    /// The decoded arguments, with their names and types.
+    pub decoded: Vec<abi::DecodedParam>,
+}
+
+#[derive(Debug, serde::Serialize, schemars::JsonSchema)]
+pub struct ClearCacheOutput {
+    /// The responses dropped from memory.
+    pub entries: usize,
+
+    /// The responses deleted from the on-disk cache, if one is configured.
+    pub files: usize,
+}
+
+#[tool_router(router = utility_router, vis = "pub")]
+impl Server {
+    #[tool(
+        description = "Get maximum value for integer type.",
+        output_schema = output_schema::<ValueOutput>()
+    )]
+    async fn max_int(
+        &self,
+        Parameters(MaxIntArgs { r#type: t }): Parameters<MaxIntArgs>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let res = SimpleCast::max_int(&t).map_err(|e| cast_error("Failed to get max int", e))?;
+        text(res)
+    }
+
+    #[tool(
+        description = "Get minimum value for integer type",
+        output_schema = output_schema::<ValueOutput>()
+    )]
+    async fn min_int(
+        &self,
+        Parameters(MaxIntArgs { r#type: t }): Parameters<MaxIntArgs>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let res = SimpleCast::min_int(&t).map_err(|e| cast_error("Failed to get min int", e))?;
+        text(res)
+    }
+
+    #[tool(
+        description = "Get maximum value for unsigned integer type.",
+        output_schema = output_schema::<ValueOutput>()
+    )]
+    async fn max_uint(
+        &self,
+        Parameters(MaxUIntArgs { r#type: t }): Parameters<MaxUIntArgs>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let res =
+            SimpleCast::max_int(&t).map_err(|e| cast_error("Failed to get max unsigned int", e))?;
+        text(res)
+    }
+
+    #[tool(
+        description = "Get the zero address",
+        output_schema = output_schema::<AddressOutput>()
+    )]
+    async fn address_zero(&self) -> Result<CallToolResult, ErrorData> {
+        address_text(Address::ZERO)
+    }
+
+    #[tool(
+        description = "Get the zero hash",
+        output_schema = output_schema::<HashOutput>()
+    )]
+    async fn hash_zero(&self) -> Result<CallToolResult, ErrorData> {
+        hash_text(B256::ZERO)
+    }
+
+    #[tool(
+        description = "ABI encode arguments as the inputs of a function signature, without a selector",
+        output_schema = output_schema::<EncodeOutput>()
+    )]
+    async fn abi_encode(
+        &self,
+        Parameters(args): Parameters<AbiEncodeArgs>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let func = abi::parse_function(&args.sig)?;
+        let values = abi::coerce_args_offline(&func.inputs, &args.args)?;
+        let encoded = SimpleCast::abi_encode(&args.sig, &args.args)
+            .map_err(|e| cast_error("Failed to ABI encode arguments", e))?;
+
+        json_output(&EncodeOutput {
+            hex: encoded,
+            selector: None,
+            signature: func.signature(),
+            args: abi::decoded_params(&func.inputs, &values),
+        })
+    }
+
+    #[tool(
+        description = "Tightly pack arguments as Solidity's abi.encodePacked would",
+        output_schema = output_schema::<EncodeOutput>()
+    )]
+    async fn abi_encode_packed(
+        &self,
+        Parameters(args): Parameters<AbiEncodeArgs>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let func = abi::parse_function(&args.sig)?;
+        let values = abi::coerce_args_offline(&func.inputs, &args.args)?;
+        let encoded = SimpleCast::abi_encode_packed(&args.sig, &args.args)
+            .map_err(|e| cast_error("Failed to pack arguments", e))?;
+
+        json_output(&EncodeOutput {
+            hex: encoded,
+            selector: None,
+            signature: func.signature(),
+            args: abi::decoded_params(&func.inputs, &values),
+        })
+    }
+
+    #[tool(
+        description = "Encode calldata: the function selector followed by the ABI encoded arguments",
+        output_schema = output_schema::<EncodeOutput>()
+    )]
+    async fn calldata(
+        &self,
+        Parameters(args): Parameters<AbiEncodeArgs>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let func = abi::parse_function(&args.sig)?;
+        let values = abi::coerce_args_offline(&func.inputs, &args.args)?;
+        let encoded = SimpleCast::calldata_encode(&args.sig, &args.args)
+            .map_err(|e| cast_error("Failed to encode calldata", e))?;
+
+        json_output(&EncodeOutput {
+            hex: encoded,
+            selector: Some(hex::encode_prefixed(func.selector())),
+            signature: func.signature(),
+            args: abi::decoded_params(&func.inputs, &values),
+        })
+    }
+
+    #[tool(
+        description = "Decode calldata, including its selector, as the inputs of a function signature",
+        output_schema = output_schema::<DecodeOutput>()
+    )]
+    async fn decode_calldata(
+        &self,
+        Parameters(args): Parameters<DecodeCalldataArgs>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let func = abi::parse_function(&args.sig)?;
+        let values = SimpleCast::calldata_decode(&args.sig, &args.data, true)
+            .map_err(|e| cast_error("Failed to decode calldata", e))?;
+
+        json_output(&DecodeOutput {
+            selector: Some(hex::encode_prefixed(func.selector())),
+            signature: func.signature(),
+            decoded: abi::decoded_params(&func.inputs, &values),
+        })
+    }
+
+    #[tool(
+        description = "Decode ABI encoded data, without a selector, as the outputs (or inputs) of a function signature",
+        output_schema = output_schema::<DecodeOutput>()
+    )]
+    async fn decode_abi(
+        &self,
+        Parameters(args): Parameters<DecodeAbiArgs>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let func = abi::parse_function(&args.sig)?;
+        let values = SimpleCast::abi_decode(&args.sig, &args.data, args.input)
+            .map_err(|e| cast_error("Failed to decode ABI data", e))?;
+        let params = if args.input {
+            &func.inputs
+        } else {
+            &func.outputs
+        };
+
+        json_output(&DecodeOutput {
+            selector: None,
+            signature: func.signature(),
+            decoded: abi::decoded_params(params, &values),
+        })
+    }
+
+    #[tool(
+        description = "Decode revert data as a custom error, or as Error(string) or Panic(uint256)",
+        output_schema = output_schema::<abi::DecodedRevert>()
+    )]
+    async fn decode_error(
+        &self,
+        Parameters(args): Parameters<DecodeErrorArgs>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let error = args.sig.as_deref().map(abi::parse_error).transpose()?;
+        let data = abi::parse_hex(&args.data)?;
+        let decoded = abi::decode_revert(error.as_ref(), &data)?;
+
+        json_output(&decoded)
+    }
+
+    #[tool(
+        description = "Convert an amount in a unit (ether by default) to wei, exactly",
+        output_schema = output_schema::<ValueOutput>()
+    )]
+    async fn to_wei(
+        &self,
+        Parameters(args): Parameters<UnitArgs>,
+    ) -> Result<CallToolResult, ErrorData> {
+        args.unit.unit()?;
+        text(
+            SimpleCast::to_wei(args.value.trim(), &args.unit.to_string())
+                .map_err(|e| cast_error("Failed to convert to wei", e))?,
+        )
+    }
+
+    #[tool(
+        description = "Convert an amount in wei to a unit (ether by default), exactly",
+        output_schema = output_schema::<ValueOutput>()
+    )]
+    async fn from_wei(
+        &self,
+        Parameters(args): Parameters<UnitArgs>,
+    ) -> Result<CallToolResult, ErrorData> {
+        args.unit.unit()?;
+        text(
+            SimpleCast::from_wei(args.value.trim(), &args.unit.to_string())
+                .map_err(|e| cast_error("Failed to convert from wei", e))?,
+        )
+    }
+
+    #[tool(
+        description = "Convert an amount, in wei or with a unit suffix such as 1gwei, to another unit (ether by default), exactly",
+        output_schema = output_schema::<ValueOutput>()
+    )]
+    async fn to_unit(
+        &self,
+        Parameters(args): Parameters<UnitArgs>,
+    ) -> Result<CallToolResult, ErrorData> {
+        args.unit.unit()?;
+        text(
+            SimpleCast::to_unit(args.value.trim(), &args.unit.to_string())
+                .map_err(|e| cast_error("Failed to convert units", e))?,
+        )
+    }
+
+    #[tool(
+        description = "Convert an integer to hexadecimal",
+        output_schema = output_schema::<ValueOutput>()
+    )]
+    async fn to_hex(
+        &self,
+        Parameters(args): Parameters<NumberArgs>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let base_in = args.base_in.map(Base::as_str);
+        text(
+            SimpleCast::to_base(args.value.trim(), base_in, "hex")
+                .map_err(|e| cast_error("Failed to convert to hex", e))?,
+        )
+    }
+
+    #[tool(
+        description = "Convert an integer to decimal",
+        output_schema = output_schema::<ValueOutput>()
+    )]
+    async fn to_dec(
+        &self,
+        Parameters(args): Parameters<NumberArgs>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let base_in = args.base_in.map(Base::as_str);
+        text(
+            SimpleCast::to_base(args.value.trim(), base_in, "dec")
+                .map_err(|e| cast_error("Failed to convert to decimal", e))?,
+        )
+    }
+
+    #[tool(
+        description = "Convert an integer between bases 2, 8, 10 and 16",
+        output_schema = output_schema::<ValueOutput>()
+    )]
+    async fn to_base(
+        &self,
+        Parameters(args): Parameters<ToBaseArgs>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let base_in = args.base_in.map(Base::as_str);
+        let base_out = args.base_out.as_str();
+        text(
+            SimpleCast::to_base(args.value.trim(), base_in, base_out)
+                .map_err(|e| cast_error("Failed to convert base", e))?,
+        )
+    }
+
+    #[tool(
+        description = "Convert a signed integer to its 32-byte two's complement hex encoding",
+        output_schema = output_schema::<ValueOutput>()
+    )]
+    async fn to_int256(
+        &self,
+        Parameters(args): Parameters<ValueArgs>,
+    ) -> Result<CallToolResult, ErrorData> {
+        text(
+            SimpleCast::to_int256(args.value.trim())
+                .map_err(|e| cast_error("Failed to convert to int256", e))?,
+        )
+    }
+
+    #[tool(
+        description = "Convert an unsigned integer to its 32-byte hex encoding",
+        output_schema = output_schema::<ValueOutput>()
+    )]
+    async fn to_uint256(
+        &self,
+        Parameters(args): Parameters<ValueArgs>,
+    ) -> Result<CallToolResult, ErrorData> {
+        text(
+            SimpleCast::to_uint256(args.value.trim())
+                .map_err(|e| cast_error("Failed to convert to uint256", e))?,
+        )
+    }
+
+    #[tool(
+        description = "Right-pad hex data to 32 bytes",
+        output_schema = output_schema::<ValueOutput>()
+    )]
+    async fn to_bytes32(
+        &self,
+        Parameters(args): Parameters<ValueArgs>,
+    ) -> Result<CallToolResult, ErrorData> {
+        text(
+            SimpleCast::to_bytes32(args.value.trim())
+                .map_err(|e| cast_error("Failed to convert to bytes32", e))?,
+        )
+    }
+
+    #[tool(
+        description = "Encode a string of at most 31 bytes as a bytes32 string",
+        output_schema = output_schema::<ValueOutput>()
+    )]
+    async fn format_bytes32_string(
+        &self,
+        Parameters(args): Parameters<ValueArgs>,
+    ) -> Result<CallToolResult, ErrorData> {
+        text(
+            SimpleCast::format_bytes32_string(&args.value)
+                .map_err(|e| cast_error("Failed to format bytes32 string", e))?,
+        )
+    }
+
+    #[tool(
+        description = "Decode a bytes32 string to text",
+        output_schema = output_schema::<ValueOutput>()
+    )]
+    async fn parse_bytes32_string(
+        &self,
+        Parameters(args): Parameters<ValueArgs>,
+    ) -> Result<CallToolResult, ErrorData> {
+        text(
+            SimpleCast::parse_bytes32_string(args.value.trim())
+                .map_err(|e| cast_error("Failed to parse bytes32 string", e))?,
+        )
+    }
+
+    #[tool(
+        description = "Decode hex data to ASCII text",
+        output_schema = output_schema::<ValueOutput>()
+    )]
+    async fn to_ascii(
+        &self,
+        Parameters(args): Parameters<ValueArgs>,
+    ) -> Result<CallToolResult, ErrorData> {
+        text(
+            SimpleCast::to_ascii(args.value.trim())
+                .map_err(|e| cast_error("Failed to convert to ASCII", e))?,
+        )
+    }
+
+    #[tool(
+        description = "Encode UTF-8 text as hex",
+        output_schema = output_schema::<ValueOutput>()
+    )]
+    async fn from_utf8(
+        &self,
+        Parameters(args): Parameters<ValueArgs>,
+    ) -> Result<CallToolResult, ErrorData> {
+        text(SimpleCast::from_utf8(&args.value))
+    }
+
+    #[tool(
+        description = "Shift an integer left by a number of bits, as a 256-bit value",
+        output_schema = output_schema::<ValueOutput>()
+    )]
+    async fn shl(
+        &self,
+        Parameters(args): Parameters<ShiftArgs>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let bits = check_bits(args.bits)?;
+        let base_in = args.base_in.map(Base::as_str);
+        let base_out = args.base_out.as_str();
+        text(
+            SimpleCast::left_shift(args.value.trim(), &bits, base_in, base_out)
+                .map_err(|e| cast_error("Failed to shift left", e))?,
+        )
+    }
+
+    #[tool(
+        description = "Shift an integer right by a number of bits, as a 256-bit value",
+        output_schema = output_schema::<ValueOutput>()
+    )]
+    async fn shr(
+        &self,
+        Parameters(args): Parameters<ShiftArgs>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let bits = check_bits(args.bits)?;
+        let base_in = args.base_in.map(Base::as_str);
+        let base_out = args.base_out.as_str();
+        text(
+            SimpleCast::right_shift(args.value.trim(), &bits, base_in, base_out)
+                .map_err(|e| cast_error("Failed to shift right", e))?,
+        )
+    }
+
+    #[tool(
+        description = "Hash data with keccak256",
+        output_schema = output_schema::<HashOutput>()
+    )]
+    async fn keccak(
+        &self,
+        Parameters(args): Parameters<HashArgs>,
+    ) -> Result<CallToolResult, ErrorData> {
+        hash_text(keccak256(message_bytes(&args.data)?))
+    }
+
+    #[tool(
+        description = "Get the 4-byte selector of a function signature",
+        output_schema = output_schema::<SelectorOutput>()
+    )]
+    async fn sig(
+        &self,
+        Parameters(args): Parameters<SignatureArgs>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let func = abi::parse_function(&args.sig)?;
+
+        json_output(&SelectorOutput {
+            selector: hex::encode_prefixed(func.selector()),
+            signature: func.signature(),
+        })
+    }
+
+    #[tool(
+        description = "Get the topic0 hash of an event signature",
+        output_schema = output_schema::<TopicOutput>()
+    )]
+    async fn sig_event(
+        &self,
+        Parameters(args): Parameters<SignatureArgs>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let event = abi::parse_event(&args.sig)?;
+
+        json_output(&TopicOutput {
+            topic: event.selector().to_string(),
+            signature: event.signature(),
+        })
+    }
+
+    #[tool(
+        description = "Hash a message as EIP-191 personal_sign does, with the Ethereum Signed Message prefix",
+        output_schema = output_schema::<HashOutput>()
+    )]
+    async fn hash_message(
+        &self,
+        Parameters(args): Parameters<MessageArgs>,
+    ) -> Result<CallToolResult, ErrorData> {
+        hash_text(eip191_hash_message(message_bytes(&args.message)?))
+    }
+
+    #[tool(
+        description = "Compute the EIP-712 signing hash of typed data, with its domain separator and struct hash",
+        output_schema = output_schema::<TypedDataHashOutput>()
+    )]
+    async fn eip712_hash(
+        &self,
+        Parameters(args): Parameters<TypedDataArgs>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let typed_data = parse_typed_data(args.typed_data)?;
+        let typed_data_error = |e: alloy_dyn_abi::Error| {
+            invalid_input(
+                "Failed to hash typed data",
+                Some(json!({ "reason": e.to_string() })),
+            )
+        };
+        let hash = typed_data.eip712_signing_hash().map_err(typed_data_error)?;
+        let struct_hash = if typed_data.primary_type == "EIP712Domain" {
+            None
+        } else {
+            Some(typed_data.hash_struct().map_err(typed_data_error)?)
+        };
+
+        json_output(&TypedDataHashOutput {
+            hash: hash.to_string(),
+            domain_separator: typed_data.domain.separator().to_string(),
+            struct_hash: struct_hash.map(|hash| hash.to_string()),
+            primary_type: typed_data.primary_type,
+        })
+    }
+
+    #[tool(
+        description = "Compute the ENS namehash of a name",
+        output_schema = output_schema::<HashOutput>()
+    )]
+    async fn namehash(
+        &self,
+        Parameters(args): Parameters<NameArgs>,
+    ) -> Result<CallToolResult, ErrorData> {
+        hash_text(alloy_ens::namehash(args.name.trim()))
+    }
+
+    #[tool(
+        description = "Compute the address of a contract deployed with CREATE",
+        output_schema = output_schema::<AddressOutput>()
+    )]
+    async fn compute_address(
+        &self,
+        Parameters(args): Parameters<ComputeAddressArgs>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let deployer = parse_address(&args.address)?;
+        address_text(deployer.create(args.nonce))
+    }
+
+    #[tool(
+        description = "Compute the address of a contract deployed with CREATE2 from a deployer, salt and init code (or its hash)",
+        output_schema = output_schema::<AddressOutput>()
+    )]
+    async fn create2(
+        &self,
+        Parameters(args): Parameters<Create2Args>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let deployer = parse_address(&args.deployer)?;
+        let salt = parse_salt(&args.salt)?;
+        let init_code_hash = match (&args.init_code, &args.init_code_hash) {
+            (Some(code), None) => keccak256(abi::parse_hex(code)?),
+            (None, Some(hash)) => parse_word(hash, "init_code_hash")?,
+            _ => {
+                return Err(invalid_input(
+                    "Exactly one of init_code or init_code_hash is required",
+                    Some(json!({ "reason": "ambiguous" })),
+                ));
+            }
+        };
+        address_text(deployer.create2(salt, init_code_hash))
+    }
+
+    #[tool(
+        description = "Find the function or error signatures matching a 4-byte selector, offline first",
+        output_schema = output_schema::<SignatureLookupOutput>()
+    )]
+    async fn four_byte(
+        &self,
+        Parameters(args): Parameters<SelectorArgs>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let selector = signatures::parse_selector(&args.selector, SelectorKind::Function)?;
+        let found =
+            signatures::lookup(&self.config.signatures, &selector, SelectorKind::Function).await?;
+        lookup_result(&selector, found)
+    }
+
+    #[tool(
+        description = "Find the event signatures matching a topic0 hash, offline first",
+        output_schema = output_schema::<SignatureLookupOutput>()
+    )]
+    async fn four_byte_event(
+        &self,
+        Parameters(args): Parameters<TopicArgs>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let topic = signatures::parse_selector(&args.topic, SelectorKind::Event)?;
+        let found =
+            signatures::lookup(&self.config.signatures, &topic, SelectorKind::Event).await?;
+        lookup_result(&topic, found)
+    }
+
+    #[tool(
+        description = "Identify calldata by its selector and decode its arguments with each matching signature",
+        output_schema = output_schema::<DecodeSelectorOutput>()
+    )]
+    async fn decode_selector(
+        &self,
+        Parameters(args): Parameters<DecodeSelectorArgs>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let data = abi::parse_hex(&args.data)?;
+        let selector = signatures::parse_selector(&args.data, SelectorKind::Function)?;
+        let Some(found) =
+            signatures::lookup(&self.config.signatures, &selector, SelectorKind::Function).await?
+        else {
+            return Err(unknown_selector(&selector));
+        };
+
+        // Colliding selectors are told apart by whether the arguments decode cleanly.
+        let candidates: Vec<DecodedCandidate> = found
+            .signatures
+            .iter()
+            .filter_map(|sig| {
+                let func = alloy_json_abi::Function::parse(sig).ok()?;
+                let values = func.abi_decode_input(&data[4..]).ok()?;
+                Some(DecodedCandidate {
+                    signature: func.signature(),
+                    decoded: abi::decoded_params(&func.inputs, &values),
+                })
+            })
+            .collect();
+        if candidates.is_empty() {
+            return Err(invalid_input(
+                format!("Calldata does not decode as any signature of {selector}"),
+                Some(json!({ "selector": selector, "signatures": found.signatures })),
+            ));
+        }
+
+        json_output(&DecodeSelectorOutput {
+            selector,
+            source: found.source,
+            candidates,
+        })
+    }
+
+    #[tool(
+        description = "Clear the cache of responses read at block hashes and finalized blocks",
+        output_schema = output_schema::<ClearCacheOutput>()
+    )]
+    async fn clear_cache(&self) -> Result<CallToolResult, ErrorData> {
+        let (entries, files) = self.cache.clear().await;
+        info!("Cleared {entries} cached responses and {files} cache files");
+        json_output(&ClearCacheOutput { entries, files })
+    }
+}
+
+fn lookup_result(
+    selector: &str,
+    found: Option<signatures::Lookup>,
+) -> Result<CallToolResult, ErrorData> {
+    let found = found.ok_or_else(|| unknown_selector(selector))?;
+    json_output(&SignatureLookupOutput {
+        selector: selector.to_string(),
+        signatures: found.signatures,
+        source: found.source,
+    })
+}
+
+fn unknown_selector(selector: &str) -> ErrorData {
+    not_found(
+        format!("No signature known for {selector}"),
+        Some(json!({ "selector": selector, "reason": "not_found" })),
+    )
+}
+
+/// Interpret `input` as bytes when it is 0x-prefixed hex, and as UTF-8 text otherwise, as
+/// `cast keccak` and `cast hash-message` do.
+fn message_bytes(input: &str) -> Result<Vec<u8>, ErrorData> {
+    if input.starts_with("0x") {
+        return abi::parse_hex(input);
     }
+    Ok(input.as_bytes().to_vec())
+}
 
-    #[tool(description = "Get minimum value for integer type")]
-    async fn min_int(
-        &self,
-        Parameters(MaxIntArgs { r#type: t }): Parameters<MaxIntArgs>,
-    ) -> Result<CallToolResult, ErrorData> {
-        let res = SimpleCast::min_int(&t).map_err(|e| {
-            tracing::error!("Failed to get min int: {}", e);
-            ErrorData::invalid_params("Failed to get min int", None)
-        })?;
-        Ok(CallToolResult::success(vec![Content::text(res)]))
-    }
+fn parse_typed_data(input: serde_json::Value) -> Result<TypedData, ErrorData> {
+    let result = match input {
+        serde_json::Value::String(s) => serde_json::from_str(&s),
+        value => serde_json::from_value(value),
+    };
+    result.map_err(|e| {
+        invalid_input(
+            "Invalid EIP-712 typed data",
+            Some(json!({
+                "expected": "an object with types, primaryType, domain and message",
+                "reason": e.to_string(),
+            })),
+        )
+    })
+}
 
-    #[tool(description = "Get maximum value for unsigned integer type.")]
-    async fn max_uint(
-        &self,
-        Parameters(MaxUIntArgs { r#type: t }): Parameters<MaxUIntArgs>,
-    ) -> Result<CallToolResult, ErrorData> {
-        let res = SimpleCast::max_int(&t).map_err(|e| {
-            tracing::error!("Failed to get max unsigned int: {}", e);
-            ErrorData::invalid_params("Failed to get max unsigned int", None)
-        })?;
-        Ok(CallToolResult::success(vec![Content::text(res)]))
+/// Parse a salt of at most 32 bytes, left-padding shorter ones.
+fn parse_salt(input: &str) -> Result<B256, ErrorData> {
+    let bytes = abi::parse_hex(input)?;
+    if bytes.len() > 32 {
+        return Err(invalid_input(
+            format!("Salt is longer than 32 bytes: {input}"),
+            Some(json!({ "input": input, "reason": "invalid_length" })),
+        ));
     }
+    Ok(B256::left_padding_from(&bytes))
+}
 
-    #[tool(description = "Get the zero address")]
-    async fn address_zero(&self) -> Result<CallToolResult, ErrorData> {
-        Ok(CallToolResult::success(vec![Content::text(
-            Address::ZERO.to_string(),
-        )]))
+fn parse_word(input: &str, name: &str) -> Result<B256, ErrorData> {
+    let bytes = abi::parse_hex(input)?;
+    if bytes.len() != 32 {
+        return Err(invalid_input(
+            format!("Invalid {name}, expected 32 bytes: {input}"),
+            Some(json!({ "input": input, "reason": "invalid_length" })),
+        ));
     }
+    Ok(B256::from_slice(&bytes))
+}
 
-    #[tool(description = "Get the zero hash")]
-    async fn hash_zero(&self) -> Result<CallToolResult, ErrorData> {
-        Ok(CallToolResult::success(vec![Content::text(
-            B256::ZERO.to_string(),
-        )]))
+fn check_bits(bits: u16) -> Result<String, ErrorData> {
+    if bits <= 256 {
+        return Ok(bits.to_string());
     }
+    Err(invalid_input(
+        format!("Cannot shift a 256-bit value by {bits} bits"),
+        Some(json!({ "input": bits, "expected": "0 to 256" })),
+    ))
+}
+
+fn text(value: String) -> Result<CallToolResult, ErrorData> {
+    structured(value.clone(), &ValueOutput { value })
+}
+
+fn address_text(address: Address) -> Result<CallToolResult, ErrorData> {
+    let address = address.to_checksum(None);
+    structured(address.clone(), &AddressOutput { address })
+}
+
+fn hash_text(hash: B256) -> Result<CallToolResult, ErrorData> {
+    let hash = hash.to_string();
+    structured(hash.clone(), &HashOutput { hash })
+}
+
+fn cast_error(message: &str, e: impl std::fmt::Display) -> ErrorData {
+    invalid_input(
+        message.to_string(),
+        Some(json!({ "reason": e.to_string() })),
+    )
 }
 
 #[cfg(test)]
@@ -338,4 +1294,636 @@ mod tests {
         );
         println!("Zero hash: {}", response_text.text);
     }
+
+    fn json_result(result: CallToolResult) -> serde_json::Value {
+        let text = &result.content[0].raw.as_text().unwrap().text;
+        let value: serde_json::Value = serde_json::from_str(text).unwrap();
+        assert_eq!(result.structured_content.as_ref(), Some(&value));
+        value
+    }
+
+    fn encode_args(sig: &str, args: &[&str]) -> Parameters<AbiEncodeArgs> {
+        Parameters(AbiEncodeArgs {
+            sig: sig.to_string(),
+            args: args.iter().map(|a| a.to_string()).collect(),
+        })
+    }
+
+    #[tokio::test]
+    async fn test_abi_encode() {
+        let server = Server::new();
+        let result = json_result(
+            server
+                .abi_encode(encode_args("f(uint256,bool)", &["1", "true"]))
+                .await
+                .unwrap(),
+        );
+        assert_eq!(result["hex"], format!("0x{:064x}{:064x}", 1, 1));
+        assert_eq!(result["signature"], "f(uint256,bool)");
+        assert_eq!(result["args"][0]["value"], "1");
+        assert_eq!(result["args"][1]["value"], true);
+    }
+
+    #[tokio::test]
+    async fn test_abi_encode_packed() {
+        let server = Server::new();
+        let result = json_result(
+            server
+                .abi_encode_packed(encode_args("f(uint8,uint16,string)", &["1", "2", "hi"]))
+                .await
+                .unwrap(),
+        );
+        assert_eq!(result["hex"], "0x0100026869");
+    }
+
+    #[tokio::test]
+    async fn test_calldata_round_trip() {
+        let server = Server::new();
+        let to = "0xd8dA6BF26964aF9D7eEd9e03E53415D37aA96045";
+        let encoded = json_result(
+            server
+                .calldata(encode_args("transfer(address,uint256)", &[to, "1000"]))
+                .await
+                .unwrap(),
+        );
+        assert_eq!(encoded["selector"], "0xa9059cbb");
+
+        let decoded = json_result(
+            server
+                .decode_calldata(Parameters(DecodeCalldataArgs {
+                    sig: "transfer(address,uint256)".to_string(),
+                    data: encoded["hex"].as_str().unwrap().to_string(),
+                }))
+                .await
+                .unwrap(),
+        );
+        assert_eq!(decoded["decoded"][0]["value"], to);
+        assert_eq!(decoded["decoded"][1]["value"], "1000");
+    }
+
+    #[tokio::test]
+    async fn test_encode_checks_arguments() {
+        let server = Server::new();
+        let error = server
+            .calldata(encode_args("transfer(address,uint256)", &["0x1234"]))
+            .await
+            .unwrap_err();
+        assert_eq!(error.data.unwrap()["reason"], "argument_count");
+
+        let error = server
+            .abi_encode(encode_args("f(uint8)", &["256"]))
+            .await
+            .unwrap_err();
+        assert_eq!(error.code, ErrorCode::INVALID_PARAMS);
+    }
+
+    #[tokio::test]
+    async fn test_decode_abi_outputs() {
+        let server = Server::new();
+        let result = json_result(
+            server
+                .decode_abi(Parameters(DecodeAbiArgs {
+                    sig: "balanceOf(address)(uint256 balance)".to_string(),
+                    data: format!("0x{:064x}", 1000),
+                    input: false,
+                }))
+                .await
+                .unwrap(),
+        );
+        assert_eq!(result["decoded"][0]["type"], "uint256");
+        assert_eq!(result["decoded"][0]["value"], "1000");
+    }
+
+    #[tokio::test]
+    async fn test_decode_error_builtin() {
+        let server = Server::new();
+        // Panic(0x11)
+        let result = json_result(
+            server
+                .decode_error(Parameters(DecodeErrorArgs {
+                    sig: None,
+                    data: format!("0x4e487b71{:064x}", 0x11),
+                }))
+                .await
+                .unwrap(),
+        );
+        assert_eq!(result["signature"], "Panic(uint256)");
+        assert_eq!(result["reason"], "arithmetic overflow or underflow");
+    }
+
+    #[tokio::test]
+    async fn test_decode_error_custom() {
+        let server = Server::new();
+        let error = alloy_json_abi::Error::parse("Unauthorized(address)").unwrap();
+        let data = format!(
+            "{}000000000000000000000000d8da6bf26964af9d7eed9e03e53415d37aa96045",
+            hex::encode_prefixed(error.selector())
+        );
+        let result = json_result(
+            server
+                .decode_error(Parameters(DecodeErrorArgs {
+                    sig: Some("Unauthorized(address caller)".to_string()),
+                    data: data.clone(),
+                }))
+                .await
+                .unwrap(),
+        );
+        assert_eq!(result["decoded"][0]["name"], "caller");
+
+        let unknown = server
+            .decode_error(Parameters(DecodeErrorArgs { sig: None, data }))
+            .await
+            .unwrap_err();
+        assert_eq!(unknown.data.unwrap()["reason"], "unknown_selector");
+    }
+
+    fn text_result(result: CallToolResult) -> String {
+        result.content[0].raw.as_text().unwrap().text.clone()
+    }
+
+    fn value(value: &str) -> Parameters<ValueArgs> {
+        Parameters(ValueArgs {
+            value: value.to_string(),
+        })
+    }
+
+    fn amount(value: &str, unit: &str) -> Parameters<UnitArgs> {
+        Parameters(serde_json::from_value(json!({ "value": value, "unit": unit })).unwrap())
+    }
+
+    #[test]
+    fn test_conversion_args_default() {
+        assert_eq!(UnitArgs::default().unit, UnitSpec::Named(UnitName::Ether));
+        assert_eq!(ToBaseArgs::default().base_out, Base::Hex);
+        assert_eq!(ShiftArgs::default().base_out, Base::Hex);
+        assert_eq!(NumberArgs::default().base_in, None);
+    }
+
+    #[tokio::test]
+    async fn test_to_wei_is_exact() {
+        let server = Server::new();
+        let wei = text_result(server.to_wei(amount("1.5", "ether")).await.unwrap());
+        assert_eq!(wei, "1500000000000000000");
+
+        let wei = text_result(server.to_wei(amount("0.1", "gwei")).await.unwrap());
+        assert_eq!(wei, "100000000");
+    }
+
+    #[tokio::test]
+    async fn test_from_wei_is_exact() {
+        let server = Server::new();
+        let ether = text_result(
+            server
+                .from_wei(amount("115792089237316195423570985008687907853269984665640564039457584007913129639935", "ether"))
+                .await
+                .unwrap(),
+        );
+        assert!(
+            ether.starts_with(
+                "115792089237316195423570985008687907853269984665640564039457.584007913129639935"
+            ),
+            "{ether}"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_unit_is_validated() {
+        let unit =
+            |unit: Value| serde_json::from_value::<UnitArgs>(json!({ "value": "1", "unit": unit }));
+        assert_eq!(
+            unit(json!("eth")).unwrap().unit,
+            UnitSpec::Named(UnitName::Ether)
+        );
+        assert_eq!(unit(json!(6)).unwrap().unit, UnitSpec::Decimals(6));
+        assert!(unit(json!("bitcoin")).is_err());
+
+        let server = Server::new();
+        let error = server
+            .to_wei(Parameters(unit(json!(78)).unwrap()))
+            .await
+            .unwrap_err();
+        assert_eq!(error.code, ErrorCode::INVALID_PARAMS);
+        assert!(error.message.contains("78"));
+    }
+
+    #[tokio::test]
+    async fn test_base_conversions() {
+        let server = Server::new();
+        let number = |value: &str| {
+            Parameters(NumberArgs {
+                value: value.to_string(),
+                base_in: None,
+            })
+        };
+        assert_eq!(
+            text_result(server.to_hex(number("255")).await.unwrap()),
+            "0xff"
+        );
+        assert_eq!(
+            text_result(server.to_dec(number("0xff")).await.unwrap()),
+            "255"
+        );
+
+        let binary = server
+            .to_base(Parameters(ToBaseArgs {
+                value: "255".to_string(),
+                base_in: Some(Base::Dec),
+                base_out: Base::Bin,
+            }))
+            .await
+            .unwrap();
+        assert_eq!(text_result(binary), "0b11111111");
+
+        let base = |base: &str| {
+            serde_json::from_value::<ToBaseArgs>(json!({ "value": "255", "base_out": base }))
+                .map(|args| args.base_out)
+        };
+        assert_eq!(base("16").unwrap(), Base::Hex);
+        assert!(base("3").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_int256_conversions() {
+        let server = Server::new();
+        let minus_one = text_result(server.to_int256(value("-1")).await.unwrap());
+        assert_eq!(minus_one, format!("0x{}", "f".repeat(64)));
+
+        let one = text_result(server.to_uint256(value("1")).await.unwrap());
+        assert_eq!(one, format!("0x{:064x}", 1));
+    }
+
+    #[tokio::test]
+    async fn test_string_conversions() {
+        let server = Server::new();
+        let hex = text_result(server.from_utf8(value("hello")).await.unwrap());
+        assert_eq!(hex, "0x68656c6c6f");
+        assert_eq!(
+            text_result(server.to_ascii(value(&hex)).await.unwrap()),
+            "hello"
+        );
+
+        let bytes32 = text_result(server.format_bytes32_string(value("hello")).await.unwrap());
+        assert_eq!(bytes32, format!("0x68656c6c6f{}", "0".repeat(54)));
+        let parsed = text_result(server.parse_bytes32_string(value(&bytes32)).await.unwrap());
+        assert_eq!(parsed, "hello");
+
+        let padded = text_result(server.to_bytes32(value("0x1234")).await.unwrap());
+        assert_eq!(padded, format!("0x1234{}", "0".repeat(60)));
+    }
+
+    #[tokio::test]
+    async fn test_shifts() {
+        let server = Server::new();
+        let shift = |value: &str, bits| {
+            Parameters(ShiftArgs {
+                value: value.to_string(),
+                bits,
+                base_in: None,
+                base_out: Base::Hex,
+            })
+        };
+        assert_eq!(
+            text_result(server.shl(shift("1", 8)).await.unwrap()),
+            "0x100"
+        );
+        assert_eq!(
+            text_result(server.shr(shift("0x100", 4)).await.unwrap()),
+            "0x10"
+        );
+
+        let error = server.shl(shift("1", 257)).await.unwrap_err();
+        assert_eq!(error.code, ErrorCode::INVALID_PARAMS);
+    }
+
+    #[tokio::test]
+    async fn test_keccak() {
+        let server = Server::new();
+        let empty = "0xc5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470";
+        for data in ["", "0x"] {
+            let hash = server
+                .keccak(Parameters(HashArgs {
+                    data: data.to_string(),
+                }))
+                .await
+                .unwrap();
+            assert_eq!(hash.structured_content, Some(json!({ "hash": empty })));
+            assert_eq!(text_result(hash), empty);
+        }
+
+        let error = server
+            .keccak(Parameters(HashArgs {
+                data: "0xzz".to_string(),
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(error.code, ErrorCode::INVALID_PARAMS);
+    }
+
+    #[tokio::test]
+    async fn test_sig_and_sig_event() {
+        let server = Server::new();
+        let signature = |sig: &str| {
+            Parameters(SignatureArgs {
+                sig: sig.to_string(),
+            })
+        };
+
+        let selector = json_result(
+            server
+                .sig(signature("function transfer(address to, uint256 amount)"))
+                .await
+                .unwrap(),
+        );
+        assert_eq!(selector["selector"], "0xa9059cbb");
+        assert_eq!(selector["signature"], "transfer(address,uint256)");
+
+        let topic = json_result(
+            server
+                .sig_event(signature(
+                    "Transfer(address indexed,address indexed,uint256)",
+                ))
+                .await
+                .unwrap(),
+        );
+        assert_eq!(
+            topic["topic"],
+            "0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef"
+        );
+
+        let error = server.sig(signature("transfer(address")).await.unwrap_err();
+        assert_eq!(error.code, ErrorCode::INVALID_PARAMS);
+    }
+
+    #[tokio::test]
+    async fn test_hash_message() {
+        let server = Server::new();
+        let hash = server
+            .hash_message(Parameters(MessageArgs {
+                message: "Hello World".to_string(),
+            }))
+            .await
+            .unwrap();
+        assert_eq!(
+            text_result(hash),
+            "0xa1de988600a42c4b4ab089b619297c17d53cffae5d5120d82d8a92d0bb3b78f2"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_eip712_hash() {
+        let server = Server::new();
+        let typed_data = json!({
+            "types": {
+                "EIP712Domain": [
+                    { "name": "name", "type": "string" },
+                    { "name": "version", "type": "string" },
+                    { "name": "chainId", "type": "uint256" },
+                    { "name": "verifyingContract", "type": "address" }
+                ],
+                "Person": [
+                    { "name": "name", "type": "string" },
+                    { "name": "wallet", "type": "address" }
+                ],
+                "Mail": [
+                    { "name": "from", "type": "Person" },
+                    { "name": "to", "type": "Person" },
+                    { "name": "contents", "type": "string" }
+                ]
+            },
+            "primaryType": "Mail",
+            "domain": {
+                "name": "Ether Mail",
+                "version": "1",
+                "chainId": 1,
+                "verifyingContract": "0xCcCCccccCCCCcCCCCCCcCcCccCcCCCcCcccccccC"
+            },
+            "message": {
+                "from": { "name": "Cow", "wallet": "0xCD2a3d9F938E13CD947Ec05AbC7FE734Df8DD826" },
+                "to": { "name": "Bob", "wallet": "0xbBbBBBBbbBBBbbbBbbBbbbbBBbBbbbbBbBbbBBbB" },
+                "contents": "Hello, Bob!"
+            }
+        });
+
+        // Both an object and its JSON text are accepted.
+        for typed_data in [typed_data.clone(), json!(typed_data.to_string())] {
+            let hash = json_result(
+                server
+                    .eip712_hash(Parameters(TypedDataArgs { typed_data }))
+                    .await
+                    .unwrap(),
+            );
+            assert_eq!(
+                hash["hash"],
+                "0xbe609aee343fb3c4b28e1df9e632fca64fcfaede20f02e86244efddf30957bd2"
+            );
+            assert_eq!(
+                hash["domain_separator"],
+                "0xf2cee375fa42b42143804025fc449deafd50cc031ca257e0b194a650a912090f"
+            );
+            assert_eq!(
+                hash["struct_hash"],
+                "0xc52c0ee5d84264471806290a3f2c4cecfc5490626bf912d01f240d7a274b371e"
+            );
+        }
+
+        let error = server
+            .eip712_hash(Parameters(TypedDataArgs {
+                typed_data: json!({ "message": {} }),
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(error.code, ErrorCode::INVALID_PARAMS);
+    }
+
+    #[tokio::test]
+    async fn test_namehash() {
+        let server = Server::new();
+        let hash = server
+            .namehash(Parameters(NameArgs {
+                name: "eth".to_string(),
+            }))
+            .await
+            .unwrap();
+        assert_eq!(
+            text_result(hash),
+            "0x93cdeb708b7545dc668eb9280176169d1c33cfd8ed6f04690a0bcc88a93fc4ae"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_compute_address() {
+        let server = Server::new();
+        let address = server
+            .compute_address(Parameters(ComputeAddressArgs {
+                address: "0xb20a608c624Ca5003905aA834De7156C68b2E1d0".to_string(),
+                nonce: 0,
+            }))
+            .await
+            .unwrap();
+        assert_eq!(
+            text_result(address),
+            "0x00000000219ab540356cBB839Cbe05303d7705Fa"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_create2() {
+        let server = Server::new();
+        let from_code = server
+            .create2(Parameters(Create2Args {
+                deployer: "0x8ba1f109551bD432803012645Ac136ddd64DBA72".to_string(),
+                salt: "0x7c5ea36004851c764c44143b1dcb59679b11c9a68e5f41497f6cf3d480715331"
+                    .to_string(),
+                init_code: Some(
+                    "0x6394198df16000526103ff60206004601c335afa6040516060f3".to_string(),
+                ),
+                init_code_hash: None,
+            }))
+            .await
+            .unwrap();
+        assert_eq!(
+            text_result(from_code),
+            "0x533ae9d683B10C02EbDb05471642F85230071FC3"
+        );
+
+        let from_hash = server
+            .create2(Parameters(Create2Args {
+                deployer: "0x5C69bEe701ef814a2B6a3EDD4B1652CB9cc5aA6f".to_string(),
+                salt: "0x2b2f5776e38002e0c013d0d89828fdb06fee595ea2d5ed4b194e3883e823e350"
+                    .to_string(),
+                init_code: None,
+                init_code_hash: Some(
+                    "0x96e8ac4277198ff8b6f785478aa9a39f403cb768dd02cbee326c3e7da348845f"
+                        .to_string(),
+                ),
+            }))
+            .await
+            .unwrap();
+        assert_eq!(
+            text_result(from_hash),
+            "0x0d4a11d5EEaaC28EC3F61d100daF4d40471f1852"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_create2_checks_arguments() {
+        let server = Server::new();
+        assert_eq!(
+            Create2Args::default().deployer,
+            "0x4e59b44847b379578588920cA78FbF26c0B4956C"
+        );
+
+        let error = server
+            .create2(Parameters(Create2Args {
+                salt: "0x01".to_string(),
+                ..Default::default()
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(error.data.unwrap()["reason"], "ambiguous");
+
+        let error = server
+            .create2(Parameters(Create2Args {
+                salt: format!("0x{}", "11".repeat(33)),
+                init_code: Some("0x00".to_string()),
+                ..Default::default()
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(error.data.unwrap()["reason"], "invalid_length");
+    }
+
+    fn offline_server() -> Server {
+        let mut config = crate::common::config::ServerConfig::default();
+        config.signatures.foundry_cache = false;
+        Server::with_config(config)
+    }
+
+    #[tokio::test]
+    async fn test_four_byte() {
+        let server = offline_server();
+        let result = json_result(
+            server
+                .four_byte(Parameters(SelectorArgs {
+                    selector: "0x095ea7b3".to_string(),
+                }))
+                .await
+                .unwrap(),
+        );
+        assert_eq!(result["signatures"][0], "approve(address,uint256)");
+        assert_eq!(result["source"], "bundled");
+
+        let error = server
+            .four_byte(Parameters(SelectorArgs {
+                selector: "0xdeadbeef".to_string(),
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(error.data.unwrap()["reason"], "not_found");
+    }
+
+    #[tokio::test]
+    async fn test_four_byte_event() {
+        let server = offline_server();
+        let result = json_result(
+            server
+                .four_byte_event(Parameters(TopicArgs {
+                    topic: "0x8c5be1e5ebec7d5bd14f71427d1e84f3dd0314c0f7b2291e5b200ac8c7c3b925"
+                        .to_string(),
+                }))
+                .await
+                .unwrap(),
+        );
+        assert_eq!(result["signatures"][0], "Approval(address,address,uint256)");
+
+        let error = server
+            .four_byte_event(Parameters(TopicArgs {
+                topic: "0x095ea7b3".to_string(),
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(error.data.unwrap()["reason"], "invalid_length");
+    }
+
+    #[tokio::test]
+    async fn test_decode_selector() {
+        let server = offline_server();
+        let data = format!(
+            "0xa9059cbb000000000000000000000000d8da6bf26964af9d7eed9e03e53415d37aa96045{:064x}",
+            1000
+        );
+        let result = json_result(
+            server
+                .decode_selector(Parameters(DecodeSelectorArgs { data }))
+                .await
+                .unwrap(),
+        );
+        assert_eq!(result["selector"], "0xa9059cbb");
+        let candidate = &result["candidates"][0];
+        assert_eq!(candidate["signature"], "transfer(address,uint256)");
+        assert_eq!(
+            candidate["decoded"][0]["value"],
+            "0xd8dA6BF26964aF9D7eEd9e03E53415D37aA96045"
+        );
+        assert_eq!(candidate["decoded"][1]["value"], "1000");
+
+        // The selector is known, but the arguments are missing.
+        let error = server
+            .decode_selector(Parameters(DecodeSelectorArgs {
+                data: "0xa9059cbb".to_string(),
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(error.code, ErrorCode::INVALID_PARAMS);
+    }
+
+    #[tokio::test]
+    async fn test_clear_cache() {
+        let server = Server::new();
+        server.cache.insert(B256::repeat_byte(1), "\"0x2a\"").await;
+        let result = json_result(server.clear_cache().await.unwrap());
+        assert_eq!(result["entries"], 1);
+        assert_eq!(result["files"], 0);
+        assert_eq!(server.cache.len(), 0);
+    }
 }
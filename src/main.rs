@@ -1,10 +1,60 @@
+use std::{net::SocketAddr, path::PathBuf};
+
 use anyhow::Result;
-use common::server::Server;
+use clap::{Parser, ValueEnum};
+use common::{
+    config::ServerConfig,
+    http::{HttpOptions, serve_http},
+    server::Server,
+};
 use rmcp::{ServiceExt, transport::stdio};
 use tracing_subscriber::{self, EnvFilter};
 mod common;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Transport {
+    /// Serve a single client over stdin/stdout.
+    Stdio,
+    /// Serve many clients over streamable HTTP.
+    Http,
+}
+
+#[derive(Debug, Parser)]
+#[command(version, about = "A MCP server for cast")]
+struct Cli {
+    /// Path to a TOML file defining named networks and the default network.
+    #[arg(long, env = "CAST_MCP_CONFIG")]
+    config: Option<PathBuf>,
+
+    /// The transport to serve the MCP server on.
+    #[arg(long, value_enum, default_value = "stdio", env = "CAST_MCP_TRANSPORT")]
+    transport: Transport,
+
+    /// The address to listen on when using the http transport.
+    #[arg(long, default_value = "127.0.0.1:8080", env = "CAST_MCP_BIND")]
+    bind: SocketAddr,
+
+    /// The path the MCP endpoint is mounted at when using the http transport.
+    #[arg(long, default_value = "/mcp", env = "CAST_MCP_PATH")]
+    path: String,
+
+    /// Origins allowed by CORS when using the http transport. Defaults to any origin.
+    #[arg(
+        long = "allowed-origin",
+        env = "CAST_MCP_ALLOWED_ORIGINS",
+        value_delimiter = ','
+    )]
+    allowed_origins: Vec<String>,
+
+    /// Answer every HTTP request statelessly instead of keeping a session per client.
+    #[arg(long)]
+    stateless: bool,
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
+    let cli = Cli::parse();
+
     // Initialize the tracing subscriber with file and stdout logging
     tracing_subscriber::fmt()
         .with_env_filter(EnvFilter::from_default_env().add_directive(tracing::Level::DEBUG.into()))
@@ -14,11 +64,29 @@ async fn main() -> Result<()> {
 
     tracing::info!("Starting MCP server");
 
-    // Create an instance of our counter router
-    let service = Server::new().serve(stdio()).await.inspect_err(|e| {
-        tracing::error!("serving error: {:?}", e);
-    })?;
+    let config = ServerConfig::load(cli.config.as_deref())?;
+    tracing::info!("Loaded server config: {:?}", config);
+    let server = Server::with_config(config);
+
+    match cli.transport {
+        Transport::Stdio => {
+            let service = server.serve(stdio()).await.inspect_err(|e| {
+                tracing::error!("serving error: {:?}", e);
+            })?;
 
-    service.waiting().await?;
+            service.waiting().await?;
+        }
+        Transport::Http => {
+            let opts = HttpOptions {
+                bind: cli.bind,
+                path: cli.path,
+                allowed_origins: cli.allowed_origins,
+                stateful: !cli.stateless,
+            };
+            serve_http(server, opts).await.inspect_err(|e| {
+                tracing::error!("serving error: {:?}", e);
+            })?;
+        }
+    }
     Ok(())
 }